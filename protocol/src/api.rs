@@ -13,11 +13,41 @@
 //!   - xfp allows for "mounting" different folders, each with their own authentication requirements.
 //!   - xfp does not have special handling for things such as calenders (CalDAV) or contacts (CardDAV).
 
+/// GET `/:mount/archive/:path`
+pub mod get_archive;
+/// GET `/:mount/changes`
+pub mod get_changes;
 /// GET `/:mount/dir/:path`
 pub mod get_dir;
 /// GET `/:mount/file/:path`
 pub mod get_file;
+/// GET `/:mount/hash/:path`
+pub mod get_hash;
 /// GET `/:mount/metadata/:path`
 pub mod get_metadata;
+/// GET `/.well-known/xfp/schema/:name`
+pub mod get_schema;
+/// GET `/:mount/signature/:path`
+pub mod get_signature;
+/// GET `/:mount/tree/:path`
+pub mod get_tree;
+/// GET `/:mount/watch/:path`
+pub mod get_watch;
+/// HEAD `/:mount/dir/:path`, `/:mount/file/:path`, `/:mount/metadata/:path`
+pub mod head;
 /// What are mounts, even?
 pub mod mounts;
+/// PATCH `/:mount/file/:path`
+pub mod patch_file;
+/// PATCH `/:mount/metadata/:path`
+pub mod patch_metadata;
+/// POST `/:mount/delta/:path`
+pub mod post_delta;
+/// POST `/:mount/length/:path`
+pub mod post_length;
+/// Resumable uploads, under `/:mount/upload/:path` and `/:mount/uploads/:id`
+pub mod uploads;
+/// GET `/.well-known/xfp`
+pub mod well_known;
+/// GET, PUT and DELETE `/:mount/xattr/:path`
+pub mod xattrs;
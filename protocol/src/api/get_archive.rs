@@ -0,0 +1,57 @@
+//! # GET `/:mount/archive/:path`
+//!
+//! This downloads the given directory in the given mount, and all of its contents, as a single archive.
+//!
+//! The archive is streamed as it is built, so servers must not buffer it in memory or in temporary files.
+//! As such, the response has no `Content-Length` header, and the `Range` header is not supported.
+//!
+//! ## Query Parameters
+//!
+//! - [`format`](crate::archive::ArchiveQuery#method.format): The format of the archive. This can either be `tar`, `tar.gz`, or `zip`. Defaults to `tar`. See [`ArchiveFormat`](crate::archive::ArchiveFormat).
+//!
+//! ## Return Codes
+//!
+//! ### OK
+//!  - `200 OK`: The archive was successfully started.
+//!
+//! ### Errors
+//! All errors will return a JSON error object, explaining what went wrong in more detail than just the status code.
+//!
+//! Any server which just returns the JSON without the proper status code, or vice versa,
+//! is **non-compliant** with the protocol.
+//!
+//!  - `400 Bad Request` - The requested format is not supported.
+//!  - `401 Unauthorized` - A proper `Authorization` header is required to access this mount or directory.
+//!  - `403 Forbidden` - The requested mount or directory is not accessible by the current authorized user.
+//!  - `404 Not Found` - The requested mount or directory was not found.
+//!  - `500 Internal Server Error` - The server encountered an error while processing the request.
+//!    A more detailed error should be in the `description` field of the JSON response.
+//!
+//! If an error is encountered after the response has started, the server must abort the connection
+//! instead of finishing the archive, so a truncated archive is never mistaken for a complete one.
+//!
+//! ## Response
+//!
+//! The response is the archive itself, with a `Content-Type` header matching the requested
+//! [format](crate::archive::ArchiveFormat#method.content_type), and a `Content-Disposition` header
+//! suggesting a file name based on the name of the directory.
+//!
+//! Paths inside the archive are relative to the requested directory.
+//! Each entry keeps the [`updated`](crate::metadata::Metadata#method.updated) time of the original file as its modification time,
+//! and on Unix-like platforms, its mode bits.
+//!
+//! ### Authorization
+//!
+//! Authorization is checked for every entry, not just the requested directory.
+//! Entries which are not accessible by the current authorized user are silently left out of the archive.
+//!
+//! ### Symlinks
+//!
+//! How symlinks are handled depends on the mount's symlink policy:
+//!  - By default, symlinks are stored as symlinks in the archive, with their target unchanged.
+//!  - If the mount follows symlinks, the file or directory the symlink points to is stored instead,
+//!    as long as it is inside the same mount. Symlinks pointing outside of the mount are left out.
+//!
+//! Servers serving a local directory can build the response body with
+//! [`ArchiveWriter::append_tree`](crate::archive::ArchiveWriter::append_tree),
+//! which takes the mount's [`SymlinkPolicy`](crate::archive::SymlinkPolicy) and checks authorization through a callback.
@@ -0,0 +1,49 @@
+//! # GET `/:mount/changes`
+//!
+//! This returns every change made to the given mount through xfp since a given [sync token](crate::journal::SyncToken),
+//! so clients which were offline can catch up without listing the whole mount again.
+//! Unlike [`get_watch`](crate::api::get_watch), this doesn't require the client to stay connected.
+//!
+//! Servers keep a change journal for each mount, persisted across restarts, which assigns
+//! monotonically increasing sequence numbers to every mutation made through xfp.
+//! Changes made directly to the backing store, bypassing xfp, are not recorded.
+//! Servers may truncate old entries from the journal, at which point clients with older tokens must resynchronize.
+//! [`Journal`](crate::journal::Journal) implements such a journal as a local file.
+//!
+//! ## Query Parameters
+//!
+//! - [`since`](crate::journal::ChangesQuery#method.since): The token returned by the previous request.
+//!   If absent, no changes are returned, only a token for the current end of the journal.
+//! - [`limit`](crate::journal::ChangesQuery#method.limit): The maximum amount of changes to return.
+//!
+//! ## Return Codes
+//!
+//! ### OK
+//!  - `200 OK`: The changes were successfully retrieved, and returned as a [`ChangeSet`](crate::journal::ChangeSet) object.
+//!
+//! ### Errors
+//! All errors will return a JSON error object, explaining what went wrong in more detail than just the status code.
+//!
+//! Any server which just returns the JSON without the proper status code, or vice versa,
+//! is **non-compliant** with the protocol.
+//!
+//!  - `400 Bad Request` - The `since` token is malformed. Tokens are always exactly 32 lowercase hex digits.
+//!  - `401 Unauthorized` - A proper `Authorization` header is required to access this mount.
+//!  - `403 Forbidden` - The requested mount is not accessible by the current authorized user.
+//!  - `404 Not Found` - The requested mount was not found, or doesn't keep a change journal.
+//!  - `500 Internal Server Error` - The server encountered an error while processing the request.
+//!    A more detailed error should be in the `description` field of the JSON response.
+//!
+//! ## Response
+//!
+//! The response is a JSON object, with the following fields:
+//!
+//! - [`changes`](crate::journal::ChangeSet#method.changes): An array of changes, ordered by sequence number. Each change has the same fields as a
+//!   [`WatchEvent`](crate::watch::WatchEvent), with its path relative to the root of the mount, along with:
+//!   - [`seq`](crate::journal::JournalEntry#method.seq): The sequence number of the change, as a number.
+//!   - [`time`](crate::journal::JournalEntry#method.time): The time the change was made, represented as a RFC3339-formatted [date-time](time::OffsetDateTime).
+//! - [`token`](crate::journal::ChangeSet#method.token): The token to pass as `since` in the next request.
+//! - [`more`](crate::journal::ChangeSet#method.more): `true` if more changes are available right away, because of the `limit`.
+//! - [`resync_required`](crate::journal::ChangeSet#method.resync_required): `true` if the journal no longer has the changes since `since`,
+//!   either because it was truncated or recreated. The client must list the mount again from scratch,
+//!   then continue from the returned `token`. `changes` is empty in this case.
@@ -3,11 +3,17 @@
 //! This is simple - it returns the contents of the given directory in the given mount,
 //! represented as a JSON array of [`DirectoryListEntry`](crate::dir::DirectoryListEntry) objects.
 //!
+//! `HEAD` is also supported on this endpoint, see [`head`](crate::api::head).
+//!
+//! Servers should look up owner and group names through a [`NameCache`](crate::metadata::NameCache)
+//! shared between requests, so that listing a large directory doesn't look up the same names for every entry.
+//!
 //! ## Return Codes
 //!
 //! ### OK
 //!  - `200 OK`: The directory listing was successfully retrieved.
 //!  - `204 No Content`: The directory listing was successfully retrieved, but the directory was empty.
+//!    This is never returned for paginated listings, which always have a body.
 //!
 //! ### Errors
 //! All errors will return a JSON error object, explaining what went wrong in more detail than just the status code.
@@ -15,16 +21,54 @@
 //! Any server which just returns the JSON without the proper status code, or vice versa,
 //! is **non-compliant** with the protocol.
 //!
+//!  - `400 Bad Request` - The query parameters were invalid, such as a malformed `cursor` or `glob`, or a `limit` of 0.
+//!  - `400 Bad Request` - A streamed listing was requested alongside `sort`, `limit`, or `cursor`.
 //!  - `401 Unauthorized` - A proper `Authorization` header is required to access this mount or directory.
 //!  - `403 Forbidden` - The requested mount or directory is not accessible by the current authorized user.
 //!  - `404 Not Found` - The requested mount or directory was not found.
+//!  - `406 Not Acceptable` - The `Accept` header doesn't allow JSON, NDJSON, CBOR, or MessagePack.
 //!  - `500 Internal Server Error` - The server encountered an error while processing the request.
 //!    A more detailed error should be in the `description` field of the JSON response.
 //!
 //! ## Response
 //!
 //! The response is a JSON array, with each object representing a file or directory in the directory.
-//! The order of the array is the same as they are read by the server.
+//! The order of the array is the same as they are read by the server, unless sorting is requested.
+//!
+//! ## Sorting and Filtering
+//!
+//! The listing can be sorted and filtered by the server, using the following query parameters:
+//!
+//! - [`sort`](crate::dir::DirectoryListQuery#method.sort): The key to sort by. This can either be `name`, `size`, `updated`, or `type`. See [`SortKey`](crate::dir::SortKey).
+//! - [`order`](crate::dir::DirectoryListQuery#method.order): The direction to sort in, either `asc` or `desc`. Defaults to `asc`.
+//! - [`natural`](crate::dir::DirectoryListQuery#method.natural): If `true`, names are compared case-insensitively, with numbers compared by value. Defaults to `false`.
+//! - [`type`](crate::dir::DirectoryListQuery#method.file_type): Only return entries of this type. This can either be `file`, `directory`, or `symlink`.
+//! - [`glob`](crate::dir::DirectoryListQuery#method.glob): Only return entries with a name matching this glob pattern.
+//! - [`hidden`](crate::dir::DirectoryListQuery#method.hidden): If `false`, entries with a name starting with `.` are omitted. Defaults to `true`.
+//!
+//! Entries which compare equal by the sort key are ordered by name.
+//! Filtering and sorting are applied before pagination.
+//!
+//! The `xattrs=true` query parameter can also be used to include the extended attributes of each entry
+//! in its metadata, just like with [`get_metadata`](crate::api::get_metadata).
+//!
+//! ## Pagination
+//!
+//! Large directories can be listed a page at a time, using the following query parameters:
+//!
+//! - [`limit`](crate::dir::DirectoryListQuery#method.limit): The maximum amount of entries to return in this page.
+//! - [`cursor`](crate::dir::DirectoryListQuery#method.cursor): The `next_cursor` returned by the previous page. Omit it to get the first page.
+//!
+//! If either of these parameters is present, the response is a [`DirectoryListingPage`](crate::dir::DirectoryListingPage)
+//! JSON object instead of a plain array, with the following fields:
+//!
+//! - [`entries`](crate::dir::DirectoryListingPage#method.entries): The entries in this page, as an array of directory entries.
+//! - [`next_cursor`](crate::dir::DirectoryListingPage#method.next_cursor): An opaque string to pass as `cursor` to get the next page. Absent on the last page.
+//!
+//! Paginated listings are ordered by name if no `sort` is given, rather than the order the server reads them in.
+//! Cursors are opaque to the client, but must stay valid across requests, even if entries are added or removed
+//! in the meantime: an entry that exists and isn't modified for the whole duration of the listing is returned exactly once.
+//! A cursor is only valid with the same `sort`, `order` and `natural` parameters it was returned with.
 //!
 //! ### Directory Entry
 //!
@@ -33,3 +77,31 @@
 //! - [`name`](crate::dir::DirectoryListEntry#method.name): The name of the file or directory.
 //! - [`type`](crate::dir::DirectoryListEntry#method.file_type): The type of the file or directory. This can either be `"file"`, `"directory"`, or `"symlink"`. See [`FileType`](crate::file::FileType).
 //! - [`metadata`](crate::dir::DirectoryListEntry#method.metadata): The metadata for the file or directory, represented as a JSON object. See [`Metadata`](crate::metadata::Metadata).
+//!
+//! ## Streaming
+//!
+//! If the request has an `Accept: application/x-ndjson` header, the listing is instead streamed as
+//! [newline-delimited JSON](http://ndjson.org/), with one directory entry object per line,
+//! sent as soon as the server reads it.
+//! The response will have a `Content-Type: application/x-ndjson` header.
+//!
+//! This allows listing huge directories with constant memory usage on both ends,
+//! see [`DirectoryEntryReader`](crate::dir::DirectoryEntryReader) for a streaming deserializer.
+//!
+//! The `type`, `glob`, and `hidden` filters can be used with streamed listings,
+//! but `sort`, `limit`, and `cursor` can't, as they require reading the whole directory first.
+//! An empty streamed listing still returns `200 OK`, with an empty body.
+//!
+//! ## Binary Formats
+//!
+//! If the request's `Accept` header prefers `application/cbor` or `application/msgpack`,
+//! the response is instead encoded as [CBOR](https://cbor.io/) or [MessagePack](https://msgpack.org/),
+//! with a matching `Content-Type` header. The structure is the same as the JSON response,
+//! but timestamps are encoded natively: as RFC 3339 strings with tag 0 in CBOR,
+//! and with the timestamp extension type in MessagePack.
+//! Clients should accept plain RFC 3339 strings as well, as [`Format::decode`](crate::format::Format::decode) does.
+//!
+//! Servers should pick the format, or streaming, with [`ListingFormat::negotiate`](crate::format::ListingFormat::negotiate),
+//! and clients can decode the response with [`Format::from_content_type`](crate::format::Format::from_content_type)
+//! and [`Format::decode`](crate::format::Format::decode). Errors are always JSON.
+//! Binary formats can't be combined with streaming.
@@ -5,6 +5,15 @@
 //!
 //! The HTTP `Range` header can be used to download a partial file.
 //!
+//! The response has a `Content-Type` header with the file's [`mime`](crate::metadata::Metadata#method.mime) type,
+//! or `application/octet-stream` if it couldn't be detected. The type is detected from the file extension,
+//! which mounts can override with their own mappings, and optionally from the first few bytes of the file.
+//! See [`MimeDetector`](crate::metadata::MimeDetector).
+//!
+//! The `Want-Repr-Digest` header can be used to get a hash of the file alongside it, see [`get_hash`](crate::api::get_hash#digest-headers).
+//!
+//! `HEAD` is also supported on this endpoint, see [`head`](crate::api::head).
+//!
 //! ## Return Codes
 //!
 //! ### OK
@@ -0,0 +1,48 @@
+//! # GET `/:mount/hash/:path`
+//!
+//! This returns a hash of the contents of the file at the given path in the given mount,
+//! represented as a [`FileHash`](crate::hash::FileHash) JSON object,
+//! so transfers can be verified end to end without downloading the file twice.
+//!
+//! Servers should cache hashes, keyed by the file's identity, modification time and size,
+//! so repeatedly hashing a large file that hasn't changed doesn't require reading it again.
+//! See [`HashCache`](crate::hash::HashCache).
+//!
+//! ## Query Parameters
+//!
+//! - [`algo`](crate::hash::HashQuery#method.algo): The algorithm to hash the file with. This can either be `sha256`, `blake3`, or `md5`. Defaults to `sha256`.
+//!
+//! ## Return Codes
+//!
+//! ### OK
+//!  - `200 OK`: The file was successfully hashed.
+//!
+//! ### Errors
+//! All errors will return a JSON error object, explaining what went wrong in more detail than just the status code.
+//!
+//! Any server which just returns the JSON without the proper status code, or vice versa,
+//! is **non-compliant** with the protocol.
+//!
+//!  - `400 Bad Request` - The requested algorithm is not supported, or the path is not a file.
+//!  - `401 Unauthorized` - A proper `Authorization` header is required to access this mount or file.
+//!  - `403 Forbidden` - The requested mount or file is not accessible by the current authorized user.
+//!  - `404 Not Found` - The requested mount or file was not found.
+//!  - `500 Internal Server Error` - The server encountered an error while processing the request.
+//!    A more detailed error should be in the `description` field of the JSON response.
+//!
+//! ## Response
+//!
+//! The response is a JSON object, with the following fields:
+//!
+//! - [`algorithm`](crate::hash::FileHash#method.algorithm): The algorithm used, in the same format as the `algo` query parameter.
+//! - [`hash`](crate::hash::FileHash#method.hash): The hash of the file, as a lowercase hexadecimal string.
+//!
+//! ## Digest Headers
+//!
+//! Hashes can also be exchanged using the `Repr-Digest` and `Want-Repr-Digest` headers from
+//! [RFC 9530](https://www.rfc-editor.org/rfc/rfc9530), where the algorithms are named `sha-256`, `blake3`, and `md5`.
+//!
+//! - If a [`get_file`](crate::api::get_file) request has a `Want-Repr-Digest` header, the response has a `Repr-Digest` header
+//!   with the hash of the whole file (not just the requested range), using the most preferred supported algorithm.
+//! - If a request uploading a file has a `Repr-Digest` header, the server must verify the uploaded contents against it,
+//!   and reject the upload with `400 Bad Request` if it doesn't match, leaving the original file untouched.
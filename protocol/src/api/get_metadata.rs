@@ -3,6 +3,10 @@
 //! This is simple - it returns the metadata for the given path in the given mount,
 //! represented as a JSON object.
 //!
+//! Metadata can be changed with [`patch_metadata`](crate::api::patch_metadata).
+//!
+//! `HEAD` is also supported on this endpoint, see [`head`](crate::api::head).
+//!
 //! ## Return Codes
 //!
 //! ### OK
@@ -17,6 +21,7 @@
 //!  - `401 Unauthorized` - A proper `Authorization` header is required to access this mount or file.
 //!  - `403 Forbidden` - The requested mount or file is not accessible by the current authorized user.
 //!  - `404 Not Found` - The requested mount or file was not found.
+//!  - `406 Not Acceptable` - The `Accept` header doesn't allow JSON, CBOR, or MessagePack.
 //!  - `500 Internal Server Error` - The server encountered an error while processing the request.
 //!    A more detailed error should be in the `description` field of the JSON response.
 //!
@@ -29,8 +34,18 @@
 //! - [`created`](crate::metadata::Metadata#method.created): The time the file was created, represented as a RFC3339-formatted [date-time](time::OffsetDateTime). Optional.
 //! - [`updated`](crate::metadata::Metadata#method.updated): The time the file was last updated, represented as a RFC3339-formatted [date-time](time::OffsetDateTime). Optional.
 //! - [`accessed`](crate::metadata::Metadata#method.accessed): The time the file was last accessed, represented as a RFC3339-formatted [date-time](time::OffsetDateTime). Optional.
+//! - [`changed`](crate::metadata::Metadata#method.changed): The time the file's contents or metadata last changed (the Unix `ctime`), represented as a RFC3339-formatted [date-time](time::OffsetDateTime). Optional, and never present on Windows.
 //! - [`permissions`](crate::metadata::Metadata#method.permissions): The permissions for the file, represented as a JSON object. See [`Permissions`](crate::metadata::FilePermissions).
-//! - [`size`](crate::metadata::Metadata#method.size): The size of the file, in bytes.
+//! - [`size`](crate::metadata::Metadata#method.size): The size of the file, in bytes, represented as a JSON number.
+//!   - **Note**: Version 0.1 of the protocol sent this as a string, such as `"1024"`. Clients should accept both forms.
+//!     Sizes above 2<sup>53</sup> bytes can't be represented exactly by JavaScript numbers, so JavaScript clients dealing with such files
+//!     need a JSON parser with big integer support.
+//! - [`mime`](crate::metadata::Metadata#method.mime): The media type of the file, such as `text/plain`, if it could be detected. Optional, and never present for directories.
+//! - [`xattrs`](crate::metadata::Metadata#method.xattrs): The extended attributes of the file, as an object mapping their full names to their base64-encoded values. Only present if the `xattrs=true` query parameter was given. See [`xattrs`](crate::api::xattrs).
+//!
+//! All times keep the full precision the server's filesystem provides, up to nanoseconds,
+//! using as many fractional second digits as needed, such as `2022-07-01T12:34:56.123456789Z`.
+//! Clients must accept any number of fractional digits, including none.
 //!
 //! ### Permissions
 //! The permissions are a JSON object, with the following fields:
@@ -38,9 +53,32 @@
 //! - [`read`](crate::metadata::FilePermissions#method.read): Whether the file is readable by the server.
 //! - [`write`](crate::metadata::FilePermissions#method.write): Whether the file is writable by the server.
 //! - [`execute`](crate::metadata::FilePermissions#method.execute): Whether the file is executable by the server.
+//! - [`mode`](crate::metadata::FilePermissions#method.mode): The Unix mode bits of the file, as a number. Optional, and never present on Windows.
 //! - [`owner`](crate::metadata::FilePermissions#method.owner): The owner of the file, represented as a JSON object. See [`FileOwner`](crate::metadata::FileOwner). Optional.
 //!   - **Note**: The contents of field differs based on if the server is running on Windows or a Unix-like platform.
+//!   - It always contains a `kind` field, which is either `unix` or `windows`.
 //!   - On Windows, it contains the `sid` and `domain` fields. On Unix-like platforms, it contains a `uid` field. Both platforms have a `name` field, which is the textual name of the user.
+//!   - Clients should also accept objects without a `kind` field from older servers, inferring the kind from whether `uid` or `sid` is present.
 //! - [`group`](crate::metadata::FilePermissions#method.group): The group of the file, represented as a JSON object. See [`FileGroup`](crate::metadata::FileGroup). Optional.
 //!   - **Note**: The contents of field differs based on if the server is running on Windows or a Unix-like platform.
+//!   - It always contains a `kind` field, which is either `unix` or `windows`.
 //!   - On Windows, it contains the `sid` and `domain` fields. On Unix-like platforms, it contains a `gid` field. Both platforms have a `name` field, which is the textual name of the group.
+//!   - Clients should also accept objects without a `kind` field from older servers, inferring the kind from whether `gid` or `sid` is present.
+//! - [`acl`](crate::metadata::FilePermissions#method.acl): The POSIX access control list of the file, represented as a JSON array. Optional, and only present if the file has an ACL.
+//!   - Each entry has a `tag` (`user_obj`, `user`, `group_obj`, `group`, `mask`, or `other`), an `id` (the uid or gid, for `user` and `group` entries only),
+//!     and `read`, `write` and `execute` booleans. See [`AclEntry`](crate::metadata::AclEntry).
+//!   - Clients checking whether a user has access to a file must take the ACL into account, rather than just the `mode`.
+//!     See [`FilePermissions::allows`](crate::metadata::FilePermissions::allows).
+//!
+//! ## Binary Formats
+//!
+//! If the request's `Accept` header prefers `application/cbor` or `application/msgpack`,
+//! the response is instead encoded as [CBOR](https://cbor.io/) or [MessagePack](https://msgpack.org/),
+//! with a matching `Content-Type` header. The structure is the same as the JSON response,
+//! but timestamps are encoded natively: as RFC 3339 strings with tag 0 in CBOR,
+//! and with the timestamp extension type in MessagePack.
+//! Clients should accept plain RFC 3339 strings as well, as [`Format::decode`](crate::format::Format::decode) does.
+//!
+//! Servers should pick the format with [`Format::negotiate`](crate::format::Format::negotiate),
+//! and clients can decode the response with [`Format::from_content_type`](crate::format::Format::from_content_type)
+//! and [`Format::decode`](crate::format::Format::decode). Errors are always JSON.
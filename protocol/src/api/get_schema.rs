@@ -0,0 +1,35 @@
+//! # GET `/.well-known/xfp/schema/:name`
+//!
+//! Returns the JSON Schema (draft 2020-12) of one of the protocol's types,
+//! so that services not written in Rust can validate the payloads they receive.
+//! Like [`well_known`](crate::api::well_known), this endpoint is not under any mount, and must not require authentication.
+//!
+//! The `name` is one of `DirectoryListEntry`, `FileGroup`, `FileOwner`, `FilePermissions`, `FileType` or `Metadata`.
+//!
+//! Schemas are only available if `xfp-protocol` is built with the `schema` feature,
+//! in which case they're generated by `schema::schema`. Servers which support this endpoint
+//! list `schema` in their [`Capabilities::endpoints`](crate::capabilities::Capabilities::endpoints).
+//!
+//! ## Return Codes
+//!
+//! ### OK
+//!  - `200 OK`: The schema was successfully retrieved.
+//!
+//! ### Errors
+//! All errors will return a JSON error object, explaining what went wrong in more detail than just the status code.
+//!
+//! Any server which just returns the JSON without the proper status code, or vice versa,
+//! is **non-compliant** with the protocol.
+//!
+//!  - `404 Not Found` - There is no type with the requested name, or the server doesn't publish schemas.
+//!  - `500 Internal Server Error` - The server encountered an error while processing the request.
+//!    A more detailed error should be in the `description` field of the JSON response.
+//!
+//! ## Response
+//!
+//! The schema, with a `Content-Type` of `application/schema+json`.
+//! The schemas describe everything a compliant client accepts, rather than exactly what a server sends. In particular:
+//! - Timestamps are strings with the `date-time` format, as defined by RFC 3339.
+//! - Sizes are either numbers, or strings containing only digits, for compatibility with older servers.
+//! - Owners and groups may omit their `kind` field, for compatibility with older servers.
+//! - Extended attribute values are strings with a `contentEncoding` of `base64`.
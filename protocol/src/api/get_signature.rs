@@ -0,0 +1,49 @@
+//! # GET `/:mount/signature/:path`
+//!
+//! This returns the block [`Signature`](crate::delta::Signature) of the file at the given path in the given mount,
+//! which is the first step of a delta transfer.
+//!
+//! Delta transfers allow uploading a new version of a large file which only changed slightly,
+//! such as a VM image or database, by only sending the parts that changed, much like rsync:
+//!  1. The client fetches the signature of the file on the server, which contains a weak rolling checksum
+//!     and a strong hash of every block.
+//!  2. The client computes a [`Delta`](crate::delta::Delta) between that signature and its local file,
+//!     consisting of references to blocks the server already has, and any new data.
+//!  3. The client sends the delta with [`post_delta`](crate::api::post_delta), and the server rebuilds the file.
+//!
+//! The response has the `ETag` of the file, which the client should send back as `If-Match`
+//! when posting the delta, so the delta is never applied to a different version of the file.
+//!
+//! ## Query Parameters
+//!
+//! - `block_size`: The size of each block, in bytes. Defaults to [`DEFAULT_BLOCK_SIZE`](crate::delta::DEFAULT_BLOCK_SIZE).
+//!   Smaller blocks find more matches, but make the signature larger.
+//!   Block sizes larger than [`MAX_BLOCK_SIZE`](crate::delta::MAX_BLOCK_SIZE) are rejected, as each block is read into memory whole.
+//!
+//! ## Return Codes
+//!
+//! ### OK
+//!  - `200 OK`: The signature was computed, and returned as a [`Signature`](crate::delta::Signature) object.
+//!
+//! ### Errors
+//! All errors will return a JSON error object, explaining what went wrong in more detail than just the status code.
+//!
+//! Any server which just returns the JSON without the proper status code, or vice versa,
+//! is **non-compliant** with the protocol.
+//!
+//!  - `400 Bad Request` - The block size is invalid or too large, or the path is not a file.
+//!  - `401 Unauthorized` - A proper `Authorization` header is required to access this mount or file.
+//!  - `403 Forbidden` - The requested mount or file is not accessible by the current authorized user.
+//!  - `404 Not Found` - The requested mount or file was not found.
+//!  - `500 Internal Server Error` - The server encountered an error while processing the request.
+//!    A more detailed error should be in the `description` field of the JSON response.
+//!
+//! ## Response
+//!
+//! The response is a JSON object, with the following fields:
+//!
+//! - [`block_size`](crate::delta::Signature#method.block_size): The size of each block, in bytes.
+//! - [`size`](crate::delta::Signature#method.size): The size of the whole file, in bytes.
+//! - [`blocks`](crate::delta::Signature#method.blocks): An array with an object for each block, in order, with the following fields:
+//!   - [`weak`](crate::delta::BlockSignature#method.weak): The rsync rolling checksum of the block, as a number.
+//!   - [`strong`](crate::delta::BlockSignature#method.strong): The first 128 bits of the BLAKE3 hash of the block, as a lowercase hexadecimal string.
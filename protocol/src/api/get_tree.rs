@@ -0,0 +1,61 @@
+//! # GET `/:mount/tree/:path`
+//!
+//! This returns the contents of the given directory in the given mount, and all of its subdirectories,
+//! so a whole tree can be mirrored with a single request instead of one [`get_dir`](crate::api::get_dir) request per directory.
+//!
+//! `HEAD` is also supported on this endpoint, see [`head`](crate::api::head).
+//!
+//! ## Query Parameters
+//!
+//! - [`depth`](crate::dir::TreeQuery#method.depth): The maximum depth to descend to. A depth of `1` only lists the direct children of the directory. Defaults to no limit.
+//! - [`limit`](crate::dir::TreeQuery#method.limit): The maximum amount of entries to return. Defaults to no limit, although servers may impose their own.
+//! - [`follow_symlinks`](crate::dir::TreeQuery#method.follow_symlinks): Whether symlinks to directories are descended into. Defaults to `false`.
+//!
+//! ## Return Codes
+//!
+//! ### OK
+//!  - `200 OK`: The tree listing was successfully started.
+//!
+//! ### Errors
+//! All errors will return a JSON error object, explaining what went wrong in more detail than just the status code.
+//!
+//! Any server which just returns the JSON without the proper status code, or vice versa,
+//! is **non-compliant** with the protocol.
+//!
+//!  - `400 Bad Request` - The query parameters were invalid.
+//!  - `401 Unauthorized` - A proper `Authorization` header is required to access this mount or directory.
+//!  - `403 Forbidden` - The requested mount or directory is not accessible by the current authorized user.
+//!  - `404 Not Found` - The requested mount or directory was not found.
+//!  - `500 Internal Server Error` - The server encountered an error while processing the request.
+//!    A more detailed error should be in the `description` field of the JSON response.
+//!
+//! As the listing is streamed, errors encountered after the response has started (such as a subdirectory
+//! becoming unreadable) can't change the status code. Unreadable subdirectories are listed, but not descended into.
+//!
+//! ## Response
+//!
+//! The response is always streamed as [newline-delimited JSON](http://ndjson.org/),
+//! with a `Content-Type: application/x-ndjson` header, in the same format as a
+//! [streamed directory listing](crate::api::get_dir#streaming).
+//!
+//! Entries are returned depth-first, with each directory immediately followed by its contents.
+//! Each entry has a [`path`](crate::dir::DirectoryListEntry#method.path) field in addition to the usual fields,
+//! containing its path relative to the requested directory, using `/` as the separator.
+//!
+//! If `limit` is reached while there are still entries left to list, the listing stops there,
+//! and ends with a `{"truncated":true}` line (see [`write_truncated`](crate::dir::write_truncated)).
+//! A tree with exactly `limit` entries doesn't end with this line, so clients can always tell the two apart.
+//! [`DirectoryEntryReader`](crate::dir::DirectoryEntryReader) reports this through
+//! [`is_truncated`](crate::dir::DirectoryEntryReader::is_truncated).
+//!
+//! ## Symlinks
+//!
+//! Symlinks are always listed as entries of type `symlink`. If `follow_symlinks` is `true`,
+//! symlinks to directories are also descended into, and their contents are listed under the symlink's path.
+//!
+//! To prevent infinite listings, servers must keep track of the directories on the current path
+//! (for example, by device and inode number), and must not descend into a directory which is already being listed.
+//! Such a symlink is still listed, but its contents are not.
+//!
+//! Servers serving a local directory can produce the whole response body with
+//! [`TreeQuery::write_tree`](crate::dir::TreeQuery::write_tree).
@@ -0,0 +1,53 @@
+//! # GET `/:mount/watch/:path`
+//!
+//! This streams changes to the given path in the given mount as they happen,
+//! so clients don't have to repeatedly poll [`get_dir`](crate::api::get_dir) to notice them.
+//!
+//! Events are sent as [Server-Sent Events](https://html.spec.whatwg.org/multipage/server-sent-events.html)
+//! if the request has an `Accept: text/event-stream` header. Servers may also support upgrading the request
+//! to a WebSocket, in which case each event is sent as a single text message containing its JSON object.
+//!
+//! ## Query Parameters
+//!
+//! - [`recursive`](crate::watch::WatchQuery#method.recursive): Whether changes in subdirectories are reported too. Defaults to `false`.
+//!
+//! ## Return Codes
+//!
+//! ### OK
+//!  - `200 OK`: The watch was started, and events will be streamed until the client disconnects.
+//!  - `101 Switching Protocols`: The watch was started over a WebSocket.
+//!
+//! ### Errors
+//! All errors will return a JSON error object, explaining what went wrong in more detail than just the status code.
+//!
+//! Any server which just returns the JSON without the proper status code, or vice versa,
+//! is **non-compliant** with the protocol.
+//!
+//!  - `401 Unauthorized` - A proper `Authorization` header is required to access this mount or path.
+//!  - `403 Forbidden` - The requested mount or path is not accessible by the current authorized user.
+//!  - `404 Not Found` - The requested mount or path was not found.
+//!  - `406 Not Acceptable` - The request asked for neither Server-Sent Events nor a WebSocket.
+//!  - `500 Internal Server Error` - The server encountered an error while processing the request.
+//!    A more detailed error should be in the `description` field of the JSON response.
+//!
+//! ## Response
+//!
+//! Each Server-Sent Event has its `event` field set to the kind of event,
+//! and its `data` field set to a [`WatchEvent`](crate::watch::WatchEvent) JSON object, with the following fields:
+//!
+//! - [`kind`](crate::watch::WatchEvent#method.kind): The kind of change. This can either be `create`, `modify`, `delete`, `rename`, or `overflow`. See [`WatchEventKind`](crate::watch::WatchEventKind).
+//! - [`path`](crate::watch::WatchEvent#method.path): The path that changed, relative to the watched path. For renames, this is the new path.
+//! - [`from`](crate::watch::WatchEvent#method.from): The old path of a renamed file object. Only present for renames.
+//! - [`type`](crate::watch::WatchEvent#method.file_type): The type of the file object that changed, in the same format as [`FileType`](crate::file::FileType). Optional.
+//!
+//! An `overflow` event means that some events were lost, and the client should list the path again.
+//!
+//! ## Backends
+//!
+//! Backends with native change notifications, such as inotify on Linux, should use them.
+//! Other backends may instead poll the watched path, comparing each listing to the previous one
+//! (see [`WatchEvent::diff`](crate::watch::WatchEvent::diff)). Polling can't tell renames apart from
+//! a deletion followed by a creation, so clients must not rely on receiving `rename` events.
+//!
+//! For the local filesystem, [`Watcher`](crate::watch::Watcher) does both: it uses inotify on Linux,
+//! and falls back to polling elsewhere, or when inotify can't be used.
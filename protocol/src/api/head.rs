@@ -0,0 +1,27 @@
+//! # HEAD `/:mount/dir/:path`, `/:mount/file/:path`, `/:mount/metadata/:path`
+//!
+//! Every `GET` endpoint in xfp also supports `HEAD`, which can be used to check if something exists,
+//! how large it is, or if it has changed, without transferring the body.
+//!
+//! A `HEAD` request must return the exact same status code and headers as the equivalent `GET` request would,
+//! just without a body. This includes, where applicable:
+//!
+//!  - `Content-Length`: The length of the body that a `GET` request would have returned.
+//!  - `Content-Type`: The type of the body that a `GET` request would have returned.
+//!  - `ETag`: The entity tag of the object, if the server supports them.
+//!  - `Accept-Ranges`: `bytes` if the endpoint supports the `Range` header, `none` otherwise.
+//!
+//! ## Object Type
+//!
+//! All three endpoints also return an [`X-XFP-Type`](crate::file::TYPE_HEADER) header on both `GET` and `HEAD`,
+//! containing the type of the object at the requested path.
+//! This can either be `file`, `directory`, or `symlink`, in the same format as [`FileType`](crate::file::FileType).
+//!
+//! This means that a `HEAD` request to `/:mount/file/:path` can be used to find out what is at a path,
+//! even if it isn't a file: in that case, the status code will be the same as the `GET` request's error,
+//! but the `X-XFP-Type` header will still be present.
+//!
+//! ## Return Codes
+//!
+//! The return codes are the same as the ones for the equivalent `GET` endpoint,
+//! although error responses will not have a JSON error object, as `HEAD` responses never have a body.
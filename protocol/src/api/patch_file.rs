@@ -0,0 +1,42 @@
+//! # PATCH `/:mount/file/:path`
+//!
+//! This writes the request body into an existing file in the given mount, without replacing the rest of it.
+//!
+//! The body can either be written at a given offset, using the `Content-Range` header,
+//! or appended to the end of the file, using the `append` query parameter.
+//!
+//! ## Query Parameters
+//!
+//! - [`append`](crate::write::WriteQuery#method.append): If `true`, the body is appended to the end of the file,
+//!   and the `Content-Range` header must not be present. Defaults to `false`.
+//!
+//! ## Headers
+//!
+//! - `Content-Range`: The range of the file to write the body to, such as `bytes 100-199/*`.
+//!   The length of the range must match the length of the body. The total length may be `*`;
+//!   if it isn't, the file is also resized to that length after writing. See [`ContentRange`](crate::write::ContentRange).
+//!   Writing past the end of the file extends it, filling any gap with zeroes.
+//! - `If-Match`: The [`ETag`](crate::api::head) the file must have for the write to happen.
+//!   Clients should always send this, to avoid overwriting changes made by someone else in the meantime.
+//!
+//! ## Return Codes
+//!
+//! ### OK
+//!  - `204 No Content`: The body was written. The response has the new `ETag` of the file.
+//!
+//! ### Errors
+//! All errors will return a JSON error object, explaining what went wrong in more detail than just the status code.
+//!
+//! Any server which just returns the JSON without the proper status code, or vice versa,
+//! is **non-compliant** with the protocol.
+//!
+//!  - `400 Bad Request` - The `Content-Range` header is missing, invalid, doesn't match the length of the body,
+//!    or was sent alongside `append`.
+//!  - `401 Unauthorized` - A proper `Authorization` header is required to access this mount or file.
+//!  - `403 Forbidden` - The requested mount or file is not writable by the current authorized user.
+//!  - `404 Not Found` - The requested mount or file was not found. Partial writes never create files.
+//!  - `412 Precondition Failed` - The file's `ETag` doesn't match the `If-Match` header. Nothing was written.
+//!  - `413 Request Entity Too Large` - If the server has any size limits,
+//!    then this will be returned if the file would become too large.
+//!  - `500 Internal Server Error` - The server encountered an error while processing the request.
+//!    A more detailed error should be in the `description` field of the JSON response.
@@ -0,0 +1,44 @@
+//! # PATCH `/:mount/metadata/:path`
+//!
+//! This changes the metadata of the given path in the given mount.
+//!
+//! The request body is a partial [`Metadata`](crate::metadata::Metadata) JSON object,
+//! represented as a [`MetadataPatch`](crate::metadata::MetadataPatch), where absent fields are left unchanged.
+//! The following fields can be changed:
+//!
+//! - [`updated`](crate::metadata::MetadataPatch#method.updated): The time the file was last updated, represented as a RFC3339-formatted [date-time](time::OffsetDateTime).
+//! - [`accessed`](crate::metadata::MetadataPatch#method.accessed): The time the file was last accessed, represented as a RFC3339-formatted [date-time](time::OffsetDateTime).
+//! - [`permissions`](crate::metadata::MetadataPatch#method.permissions): An object with any of the following fields:
+//!   - [`mode`](crate::metadata::PermissionsPatch#method.mode): The Unix mode bits, as a number. Only the permission, setuid, setgid and sticky bits (`0o7777`) may be set.
+//!   - [`owner`](crate::metadata::PermissionsPatch#method.owner): The new owner, either as a [`FileOwner`](crate::metadata::FileOwner) object,
+//!     or as an object with only a `name` field, in which case the server looks up the user by name.
+//!   - [`group`](crate::metadata::PermissionsPatch#method.group): The new group, either as a [`FileGroup`](crate::metadata::FileGroup) object,
+//!     or as an object with only a `name` field, in which case the server looks up the group by name.
+//!
+//! Any other field, such as `created` or `size`, is rejected.
+//! The patch is validated as a whole before anything is changed, so a rejected patch never partially applies.
+//! If the filesystem refuses one of the changes after validation, servers must undo the changes already made
+//! before responding, as far as they can. See [`apply_to_path`](crate::metadata::MetadataPatch::apply_to_path).
+//!
+//! ## Return Codes
+//!
+//! ### OK
+//!  - `200 OK`: The metadata was changed, and the new metadata is returned as a [`Metadata`](crate::metadata::Metadata) object.
+//!
+//! ### Errors
+//! All errors will return a JSON error object, explaining what went wrong in more detail than just the status code.
+//!
+//! Any server which just returns the JSON without the proper status code, or vice versa,
+//! is **non-compliant** with the protocol.
+//!
+//!  - `400 Bad Request` - The request body was not a valid patch, contained a field which can't be changed,
+//!    or had a `mode` with bits outside of `0o7777`.
+//!  - `401 Unauthorized` - A proper `Authorization` header is required to access this mount or file.
+//!  - `403 Forbidden` - The requested mount or file is not writable by the current authorized user,
+//!    or the server isn't allowed to change the owner or group to the requested one.
+//!  - `404 Not Found` - The requested mount or file was not found.
+//!  - `422 Unprocessable Entity` - The patch was valid, but can't be applied: the backend doesn't support one of the fields
+//!    (such as `mode` on Windows), or the requested user or group doesn't exist.
+//!    The `description` field of the JSON response names the offending field, such as `permissions.mode`.
+//!  - `500 Internal Server Error` - The server encountered an error while processing the request.
+//!    A more detailed error should be in the `description` field of the JSON response.
@@ -0,0 +1,40 @@
+//! # POST `/:mount/delta/:path`
+//!
+//! This rebuilds the file at the given path in the given mount from a [`Delta`](crate::delta::Delta),
+//! which is the last step of a delta transfer. See [`get_signature`](crate::api::get_signature) for an overview.
+//!
+//! The request body is a [`Delta`](crate::delta::Delta) JSON object, with the following fields:
+//!
+//! - [`block_size`](crate::delta::Delta#method.block_size): The block size of the signature the delta was computed against.
+//! - [`size`](crate::delta::Delta#method.size): The size of the rebuilt file, in bytes.
+//! - [`ops`](crate::delta::Delta#method.ops): An array of instructions, in order. Each one is an object with an `op` field, which is either:
+//!   - `copy`: Copy `count` consecutive blocks from the current file, starting at the block with index `block`.
+//!   - `data`: Write the base64-encoded `data`.
+//!
+//! The server rebuilds the file into a temporary file, and only replaces the original once it has been fully written,
+//! so the file is never left half-rebuilt. If the request has a `Repr-Digest` header, the rebuilt file is verified against it first,
+//! see [`get_hash`](crate::api::get_hash#digest-headers).
+//!
+//! The request should have an `If-Match` header with the `ETag` returned alongside the signature.
+//!
+//! ## Return Codes
+//!
+//! ### OK
+//!  - `204 No Content`: The file was rebuilt. The response has the new `ETag` of the file.
+//!
+//! ### Errors
+//! All errors will return a JSON error object, explaining what went wrong in more detail than just the status code.
+//!
+//! Any server which just returns the JSON without the proper status code, or vice versa,
+//! is **non-compliant** with the protocol.
+//!
+//!  - `400 Bad Request` - The delta is invalid, refers to blocks the file doesn't have, or doesn't match the `Repr-Digest` header.
+//!    The file was not changed.
+//!  - `401 Unauthorized` - A proper `Authorization` header is required to access this mount or file.
+//!  - `403 Forbidden` - The requested mount or file is not writable by the current authorized user.
+//!  - `404 Not Found` - The requested mount or file was not found.
+//!  - `412 Precondition Failed` - The file's `ETag` doesn't match the `If-Match` header. The file was not changed.
+//!  - `413 Request Entity Too Large` - If the server has any size limits,
+//!    then this will be returned if the rebuilt file would be too large.
+//!  - `500 Internal Server Error` - The server encountered an error while processing the request.
+//!    A more detailed error should be in the `description` field of the JSON response.
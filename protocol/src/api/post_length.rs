@@ -0,0 +1,32 @@
+//! # POST `/:mount/length/:path`
+//!
+//! This sets the length of an existing file in the given mount,
+//! truncating it, or extending it with zeroes.
+//!
+//! The request body is a [`SetLength`](crate::write::SetLength) JSON object, with the following fields:
+//!
+//! - [`length`](crate::write::SetLength#method.length): The new length of the file, in bytes.
+//!
+//! Like [partial writes](crate::api::patch_file), the `If-Match` header can be used
+//! to only change the length if the file hasn't changed in the meantime.
+//!
+//! ## Return Codes
+//!
+//! ### OK
+//!  - `204 No Content`: The length of the file was set. The response has the new `ETag` of the file.
+//!
+//! ### Errors
+//! All errors will return a JSON error object, explaining what went wrong in more detail than just the status code.
+//!
+//! Any server which just returns the JSON without the proper status code, or vice versa,
+//! is **non-compliant** with the protocol.
+//!
+//!  - `400 Bad Request` - The request body was invalid, or the path is not a file.
+//!  - `401 Unauthorized` - A proper `Authorization` header is required to access this mount or file.
+//!  - `403 Forbidden` - The requested mount or file is not writable by the current authorized user.
+//!  - `404 Not Found` - The requested mount or file was not found.
+//!  - `412 Precondition Failed` - The file's `ETag` doesn't match the `If-Match` header. The file was not changed.
+//!  - `413 Request Entity Too Large` - If the server has any size limits,
+//!    then this will be returned if the new length is too large.
+//!  - `500 Internal Server Error` - The server encountered an error while processing the request.
+//!    A more detailed error should be in the `description` field of the JSON response.
@@ -0,0 +1,82 @@
+//! # `/:mount/upload/:path` and `/:mount/uploads/:id`
+//!
+//! Resumable uploads allow large files to be uploaded in chunks, continuing where they left off
+//! after a network failure instead of starting over. The protocol is modelled on [tus](https://tus.io/).
+//!
+//! An upload goes through the following steps:
+//!  1. The client creates an upload session for the path it wants to write to.
+//!  2. The client sends the file in one or more chunks, each at the offset the server has received so far.
+//!  3. If a chunk fails, the client asks the server for its current offset, and continues from there.
+//!  4. The client finalizes the session, at which point the file is atomically moved into place.
+//!
+//! Until the session is finalized, the data received so far is not visible in the mount.
+//!
+//! All errors will return a JSON error object, explaining what went wrong in more detail than just the status code.
+//! Every endpoint can also return `401 Unauthorized`, `403 Forbidden`, and `500 Internal Server Error`,
+//! with the same meaning as they have everywhere else.
+//!
+//! ## POST `/:mount/upload/:path`
+//!
+//! Creates a new upload session, which will write to the given path in the given mount once finalized.
+//! Sessions have their own URL, under `/:mount/uploads/:id`, which is used for the rest of the upload.
+//! The request may have an [`Upload-Length`](crate::upload::UPLOAD_LENGTH_HEADER) header with the total size of the file.
+//!
+//! ### Return Codes
+//!  - `201 Created`: The session was created. The `Location` header contains the URL of the session,
+//!    and the body is an [`UploadSession`](crate::upload::UploadSession) JSON object.
+//!  - `404 Not Found` - The mount, or the directory the file would be written to, was not found.
+//!  - `413 Request Entity Too Large` - The `Upload-Length` is larger than the server allows.
+//!
+//! ## HEAD `/:mount/uploads/:id`
+//!
+//! Returns the current state of an upload session in the [`Upload-Offset`](crate::upload::UPLOAD_OFFSET_HEADER),
+//! [`Upload-Length`](crate::upload::UPLOAD_LENGTH_HEADER) and [`Upload-Expires`](crate::upload::UPLOAD_EXPIRES_HEADER) headers.
+//! A `GET` request returns the same information as an [`UploadSession`](crate::upload::UploadSession) JSON object.
+//!
+//! ### Return Codes
+//!  - `200 OK`: The session exists.
+//!  - `404 Not Found` - The session was not found, or has expired.
+//!
+//! ## PATCH `/:mount/uploads/:id`
+//!
+//! Appends a chunk to an upload session. The request must have a `Content-Type: application/offset+octet-stream` header,
+//! and an [`Upload-Offset`](crate::upload::UPLOAD_OFFSET_HEADER) header equal to the session's current offset.
+//!
+//! If the connection is interrupted, the server keeps every byte it has received and written,
+//! so the client can ask for the new offset with a `HEAD` request and resume from there.
+//!
+//! ### Return Codes
+//!  - `204 No Content`: The chunk was received. The response has the new `Upload-Offset` and `Upload-Expires` headers.
+//!  - `404 Not Found` - The session was not found, or has expired.
+//!  - `409 Conflict` - The `Upload-Offset` header doesn't match the session's current offset.
+//!  - `413 Request Entity Too Large` - The chunk would make the upload longer than its `Upload-Length`.
+//!  - `415 Unsupported Media Type` - The `Content-Type` header is not `application/offset+octet-stream`.
+//!
+//! ## POST `/:mount/uploads/:id/finalize`
+//!
+//! Finishes an upload session, atomically replacing the file at the session's path with the uploaded data.
+//! If the request has a `Repr-Digest` header, the uploaded data is verified against it first,
+//! see [`get_hash`](crate::api::get_hash#digest-headers).
+//!
+//! ### Return Codes
+//!  - `204 No Content`: The file was written, and the session no longer exists.
+//!  - `400 Bad Request` - The uploaded data doesn't match the `Repr-Digest` header. The session is kept, so the client may retry.
+//!  - `404 Not Found` - The session was not found, or has expired.
+//!  - `409 Conflict` - The session has an `Upload-Length`, and not all of it has been received yet.
+//!
+//! ## DELETE `/:mount/uploads/:id`
+//!
+//! Aborts an upload session, discarding any data received so far.
+//!
+//! ### Return Codes
+//!  - `204 No Content`: The session was aborted.
+//!  - `404 Not Found` - The session was not found, or has expired.
+//!
+//! ## Expiry
+//!
+//! Sessions expire if they don't receive any data for a while, as chosen by the server.
+//! Servers must periodically garbage collect expired sessions, deleting any partial data they hold,
+//! so abandoned uploads don't fill up the backing store.
+//!
+//! [`UploadStore`](crate::upload::UploadStore) implements sessions on a local filesystem,
+//! including expiry and garbage collection.
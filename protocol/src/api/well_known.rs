@@ -0,0 +1,48 @@
+//! # GET `/.well-known/xfp`
+//!
+//! Returns a description of what the server supports, as a [`Capabilities`](crate::capabilities::Capabilities) object.
+//! This endpoint is not under any mount, and must not require authentication,
+//! so clients can find out which authentication schemes are accepted before authenticating.
+//!
+//! Clients should request this once when connecting, and check it before using any endpoint or feature
+//! which not every server supports, using [`Capabilities::require`](crate::capabilities::Capabilities::require).
+//! A client should refuse to talk to a server with a different major version, see
+//! [`Capabilities::is_compatible`](crate::capabilities::Capabilities::is_compatible).
+//!
+//! ## Return Codes
+//!
+//! ### OK
+//!  - `200 OK`: The capabilities were successfully retrieved.
+//!
+//! ### Errors
+//! All errors will return a JSON error object, explaining what went wrong in more detail than just the status code.
+//!
+//! Any server which just returns the JSON without the proper status code, or vice versa,
+//! is **non-compliant** with the protocol.
+//!
+//!  - `404 Not Found` - The server predates capability negotiation. Clients should assume it only supports
+//!    the `dir`, `file` and `metadata` endpoints.
+//!  - `500 Internal Server Error` - The server encountered an error while processing the request.
+//!    A more detailed error should be in the `description` field of the JSON response.
+//!
+//! ## Response
+//!
+//! A JSON object, with the following fields:
+//! - [`version`](crate::capabilities::Capabilities#method.version): The protocol version, as a `major.minor` string.
+//! - [`endpoints`](crate::capabilities::Capabilities#method.endpoints): The supported endpoints, as an array of strings
+//!   such as `dir`, `file`, `archive` or `upload`. See [`Endpoint`](crate::capabilities::Endpoint).
+//!   Clients must ignore endpoints they don't know about.
+//! - [`features`](crate::capabilities::Capabilities#method.features): The optional features, as a JSON object.
+//!   See [`Features`](crate::capabilities::Features).
+//!   - `ranges`: Whether `Range` requests are supported when downloading files.
+//!   - `uploads`: Whether resumable uploads are supported.
+//!   - `watch`: Whether watching for changes is supported.
+//!   - `archives`: The supported archive formats, such as `tar`, `tar.gz` or `zip`.
+//!   - `hashes`: The supported hash algorithms, such as `sha256`, `blake3` or `md5`.
+//! - [`limits`](crate::capabilities::Capabilities#method.limits): The limits on requests, as a JSON object.
+//!   See [`Limits`](crate::capabilities::Limits). Every field is optional, and a missing field means there is no limit.
+//!   - `max_upload_size`: The largest file that can be uploaded, in bytes.
+//!   - `max_page_size`: The largest `limit` accepted when paginating a directory listing.
+//!   - `max_tree_depth`: The largest `depth` accepted when requesting a directory tree.
+//! - [`auth`](crate::capabilities::Capabilities#method.auth): The accepted authentication schemes,
+//!   as an array of strings such as `basic` or `bearer`.
@@ -0,0 +1,48 @@
+//! # `/:mount/xattr/:path`
+//!
+//! These endpoints read and change individual extended attributes of the given path in the given mount.
+//! All of them take the full name of the attribute, including its namespace (such as `user.provenance`),
+//! in the `name` query parameter.
+//!
+//! To read every attribute of a file at once, request its [metadata](crate::api::get_metadata)
+//! with the `xattrs=true` query parameter instead.
+//!
+//! Each mount only exposes the namespaces it allows, which is only `user` by default.
+//! Attributes in other namespaces are never listed, and requests for them are rejected with `403 Forbidden`.
+//! See [`XattrPolicy`](crate::metadata::XattrPolicy).
+//!
+//! All errors will return a JSON error object, explaining what went wrong in more detail than just the status code.
+//! Every endpoint can also return `401 Unauthorized`, `404 Not Found` (if the mount or path doesn't exist),
+//! and `500 Internal Server Error`, with the same meaning as they have everywhere else.
+//! If the backend doesn't support extended attributes at all, every endpoint returns `501 Not Implemented`.
+//!
+//! ## GET `/:mount/xattr/:path?name=:name`
+//!
+//! Returns a single attribute as an [`Xattr`](crate::metadata::Xattr) JSON object, with the following fields:
+//!
+//! - [`name`](crate::metadata::Xattr#method.name): The full name of the attribute.
+//! - [`value`](crate::metadata::Xattr#method.value): The value of the attribute, as a base64-encoded string, as values may be binary.
+//!
+//! ### Return Codes
+//!  - `200 OK`: The attribute was found.
+//!  - `403 Forbidden` - The file is not accessible by the current authorized user, or the attribute's namespace is not allowed.
+//!  - `404 Not Found` - The file doesn't have this attribute.
+//!
+//! ## PUT `/:mount/xattr/:path`
+//!
+//! Sets a single attribute, creating it if it doesn't exist.
+//! The request body is an [`Xattr`](crate::metadata::Xattr) JSON object, and the `name` query parameter is not used.
+//!
+//! ### Return Codes
+//!  - `204 No Content`: The attribute was set.
+//!  - `400 Bad Request` - The request body was invalid.
+//!  - `403 Forbidden` - The file is not writable by the current authorized user, or the attribute's namespace is not allowed.
+//!
+//! ## DELETE `/:mount/xattr/:path?name=:name`
+//!
+//! Removes a single attribute.
+//!
+//! ### Return Codes
+//!  - `204 No Content`: The attribute was removed.
+//!  - `403 Forbidden` - The file is not writable by the current authorized user, or the attribute's namespace is not allowed.
+//!  - `404 Not Found` - The file doesn't have this attribute.
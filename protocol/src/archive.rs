@@ -0,0 +1,86 @@
+mod crc;
+mod deflate;
+mod tar;
+mod writer;
+mod zip;
+
+pub use self::writer::{ArchiveWriter, SymlinkPolicy};
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+/// The format of a directory archive.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ArchiveFormat {
+	/// An uncompressed POSIX tar archive.
+	///
+	/// Represented as the string `tar` in the query string.
+	#[serde(rename = "tar")]
+	Tar,
+	/// A gzip-compressed POSIX tar archive.
+	///
+	/// Represented as the string `tar.gz` in the query string.
+	#[serde(rename = "tar.gz")]
+	TarGz,
+	/// A zip archive.
+	///
+	/// Represented as the string `zip` in the query string.
+	#[serde(rename = "zip")]
+	Zip,
+}
+
+impl ArchiveFormat {
+	/// The value of the `Content-Type` header for archives of this format.
+	#[inline]
+	pub fn content_type(&self) -> &'static str {
+		match self {
+			ArchiveFormat::Tar => "application/x-tar",
+			ArchiveFormat::TarGz => "application/gzip",
+			ArchiveFormat::Zip => "application/zip",
+		}
+	}
+
+	/// The file extension for archives of this format, without the leading dot.
+	#[inline]
+	pub fn extension(&self) -> &'static str {
+		match self {
+			ArchiveFormat::Tar => "tar",
+			ArchiveFormat::TarGz => "tar.gz",
+			ArchiveFormat::Zip => "zip",
+		}
+	}
+}
+
+impl Default for ArchiveFormat {
+	#[inline]
+	fn default() -> Self {
+		ArchiveFormat::Tar
+	}
+}
+
+/// The query parameters accepted by an archive download.
+#[skip_serializing_none]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ArchiveQuery {
+	format: Option<ArchiveFormat>,
+}
+
+impl ArchiveQuery {
+	/// The format of the archive. Defaults to [`ArchiveFormat::Tar`].
+	///
+	/// Represented as either `tar`, `tar.gz`, or `zip` in the query string, with the key `format`.
+	#[inline]
+	pub fn format(&self) -> ArchiveFormat {
+		self.format.unwrap_or_default()
+	}
+
+	/// Duplicates this query object,
+	/// updating the format with the specified value.
+	#[inline]
+	pub fn with_format(self, format: impl Into<Option<ArchiveFormat>>) -> Self {
+		Self {
+			format: format.into(),
+		}
+	}
+}
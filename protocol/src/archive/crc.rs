@@ -0,0 +1,41 @@
+/// The CRC-32 checksum used by both gzip and zip, with the reflected polynomial `0xEDB88320`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub(super) struct Crc32(u32);
+
+const TABLE: [u32; 256] = table();
+
+const fn table() -> [u32; 256] {
+	let mut table = [0; 256];
+	let mut byte = 0;
+	while byte < 256 {
+		let mut crc = byte as u32;
+		let mut bit = 0;
+		while bit < 8 {
+			crc = if crc & 1 != 0 {
+				0xEDB8_8320 ^ (crc >> 1)
+			} else {
+				crc >> 1
+			};
+			bit += 1;
+		}
+		table[byte] = crc;
+		byte += 1;
+	}
+	table
+}
+
+impl Crc32 {
+	pub(super) fn new() -> Self {
+		Self(!0)
+	}
+
+	pub(super) fn update(&mut self, data: &[u8]) {
+		for &byte in data {
+			self.0 = TABLE[((self.0 ^ u32::from(byte)) & 0xFF) as usize] ^ (self.0 >> 8);
+		}
+	}
+
+	pub(super) fn value(&self) -> u32 {
+		!self.0
+	}
+}
@@ -0,0 +1,314 @@
+use super::crc::Crc32;
+use std::{
+	io::{self, Write},
+	mem,
+};
+
+/// The most input compressed into a single block, which is also the most a stored block can hold.
+const BLOCK_SIZE: usize = 65535;
+
+/// How far back a match can refer to.
+const WINDOW_SIZE: usize = 32 * 1024;
+
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+
+/// How many earlier positions with the same hash are tried when looking for a match,
+/// trading compression for speed.
+const MAX_CHAIN: usize = 64;
+
+const HASH_BITS: u32 = 15;
+
+/// The end of block symbol.
+const END_OF_BLOCK: u16 = 256;
+
+const LENGTH_BASE: [u16; 29] = [
+	3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+	163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u32; 29] = [
+	0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+	1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+	2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u32; 30] = [
+	0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+	13,
+];
+
+enum Symbol {
+	Literal(u8),
+	Match { length: u16, distance: u16 },
+}
+
+/// The index of the last entry of `bases` which is at most `value`.
+fn code_index(bases: &[u16], value: u16) -> usize {
+	bases.iter().rposition(|base| *base <= value).unwrap_or(0)
+}
+
+/// The code and length of `symbol` in the fixed literal/length Huffman code.
+fn fixed_code(symbol: u16) -> (u32, u32) {
+	let symbol = u32::from(symbol);
+	match symbol {
+		0..=143 => (0x30 + symbol, 8),
+		144..=255 => (0x190 + symbol - 144, 9),
+		256..=279 => (symbol - 256, 7),
+		_ => (0xC0 + symbol - 280, 8),
+	}
+}
+
+fn fixed_bits(symbol: &Symbol) -> u64 {
+	let bits = match *symbol {
+		Symbol::Literal(byte) => fixed_code(u16::from(byte)).1,
+		Symbol::Match { length, distance } => {
+			let length = code_index(&LENGTH_BASE, length);
+			let distance = code_index(&DISTANCE_BASE, distance);
+			fixed_code(257 + length as u16).1 + LENGTH_EXTRA[length] + 5 + DISTANCE_EXTRA[distance]
+		}
+	};
+	u64::from(bits)
+}
+
+/// Collects bits least significant first, as DEFLATE packs them.
+#[derive(Default)]
+struct BitWriter {
+	out: Vec<u8>,
+	buffer: u64,
+	count: u32,
+}
+
+impl BitWriter {
+	fn put(&mut self, value: u32, count: u32) {
+		self.buffer |= u64::from(value) << self.count;
+		self.count += count;
+		while self.count >= 8 {
+			self.out.push(self.buffer as u8);
+			self.buffer >>= 8;
+			self.count -= 8;
+		}
+	}
+
+	/// Writes a Huffman code, which unlike everything else is packed most significant bit first.
+	fn put_code(&mut self, code: u32, count: u32) {
+		self.put(code.reverse_bits() >> (32 - count), count);
+	}
+
+	fn put_symbol(&mut self, symbol: u16) {
+		let (code, count) = fixed_code(symbol);
+		self.put_code(code, count);
+	}
+
+	/// Pads the output with zero bits up to the next byte boundary.
+	fn align(&mut self) {
+		if self.count > 0 {
+			self.put(0, 8 - self.count);
+		}
+	}
+
+	/// Takes every complete byte written so far.
+	fn take(&mut self) -> Vec<u8> {
+		mem::take(&mut self.out)
+	}
+}
+
+/// A raw DEFLATE (RFC 1951) compressor, matching repeated strings with the fixed Huffman codes,
+/// and falling back to stored blocks for data which doesn't compress.
+pub(super) struct Deflater<W> {
+	inner: W,
+	/// Already compressed input kept for matches to refer to, followed by input not yet compressed.
+	data: Vec<u8>,
+	/// Where the input not yet compressed starts in `data`.
+	start: usize,
+	bits: BitWriter,
+}
+
+impl<W: Write> Deflater<W> {
+	pub(super) fn new(inner: W) -> Self {
+		Self {
+			inner,
+			data: Vec::new(),
+			start: 0,
+			bits: BitWriter::default(),
+		}
+	}
+
+	/// Compresses the rest of the input as the final block, returning the underlying writer.
+	pub(super) fn finish(mut self) -> io::Result<W> {
+		while self.data.len() - self.start > BLOCK_SIZE {
+			self.compress_block(false)?;
+		}
+		self.compress_block(true)?;
+		self.bits.align();
+		self.inner.write_all(&self.bits.take())?;
+		Ok(self.inner)
+	}
+
+	fn compress_block(&mut self, last: bool) -> io::Result<()> {
+		let end = self.data.len().min(self.start + BLOCK_SIZE);
+		let symbols = self.find_matches(end);
+		let input = &self.data[self.start..end];
+		let fixed = 3 + symbols.iter().map(fixed_bits).sum::<u64>() + 7;
+		let padding = (8 - (self.bits.count + 3) % 8) % 8;
+		let stored = 3 + u64::from(padding) + 32 + 8 * input.len() as u64;
+		self.bits.put(u32::from(last), 1);
+		if fixed < stored {
+			self.bits.put(1, 2);
+			for symbol in &symbols {
+				match *symbol {
+					Symbol::Literal(byte) => self.bits.put_symbol(u16::from(byte)),
+					Symbol::Match { length, distance } => {
+						let length_index = code_index(&LENGTH_BASE, length);
+						self.bits.put_symbol(257 + length_index as u16);
+						self.bits.put(
+							u32::from(length - LENGTH_BASE[length_index]),
+							LENGTH_EXTRA[length_index],
+						);
+						let distance_index = code_index(&DISTANCE_BASE, distance);
+						self.bits.put_code(distance_index as u32, 5);
+						self.bits.put(
+							u32::from(distance - DISTANCE_BASE[distance_index]),
+							DISTANCE_EXTRA[distance_index],
+						);
+					}
+				}
+			}
+			self.bits.put_symbol(END_OF_BLOCK);
+		} else {
+			self.bits.put(0, 2);
+			self.bits.align();
+			let len = input.len() as u16;
+			self.bits.out.extend_from_slice(&len.to_le_bytes());
+			self.bits.out.extend_from_slice(&(!len).to_le_bytes());
+			self.bits.out.extend_from_slice(input);
+		}
+		self.inner.write_all(&self.bits.take())?;
+		// Only the end of what was just compressed can still be referred to by the next block.
+		let keep_from = end.saturating_sub(WINDOW_SIZE);
+		self.data.drain(..keep_from);
+		self.start = end - keep_from;
+		Ok(())
+	}
+
+	/// Splits the input from `start` to `end` into literals and matches.
+	fn find_matches(&self, end: usize) -> Vec<Symbol> {
+		let data = &self.data[..end];
+		let mut head = vec![usize::MAX; 1 << HASH_BITS];
+		let mut prev = vec![usize::MAX; end];
+		let hash = |i: usize| {
+			((usize::from(data[i]) << 10)
+				^ (usize::from(data[i + 1]) << 5)
+				^ usize::from(data[i + 2]))
+				& ((1 << HASH_BITS) - 1)
+		};
+		let insert = |head: &mut [usize], prev: &mut [usize], i: usize| {
+			if i + MIN_MATCH <= end {
+				let hash = hash(i);
+				prev[i] = head[hash];
+				head[hash] = i;
+			}
+		};
+		for i in 0..self.start {
+			insert(&mut head, &mut prev, i);
+		}
+		let mut symbols = Vec::new();
+		let mut i = self.start;
+		while i < end {
+			let max_len = (end - i).min(MAX_MATCH);
+			let (mut best_len, mut best_distance) = (0, 0);
+			if max_len >= MIN_MATCH {
+				let mut candidate = head[hash(i)];
+				let mut chain = 0;
+				while candidate != usize::MAX && i - candidate <= WINDOW_SIZE && chain < MAX_CHAIN {
+					let len = data[candidate..]
+						.iter()
+						.zip(&data[i..i + max_len])
+						.take_while(|(a, b)| a == b)
+						.count();
+					if len > best_len {
+						(best_len, best_distance) = (len, i - candidate);
+						if len == max_len {
+							break;
+						}
+					}
+					candidate = prev[candidate];
+					chain += 1;
+				}
+			}
+			if best_len >= MIN_MATCH {
+				symbols.push(Symbol::Match {
+					length: best_len as u16,
+					distance: best_distance as u16,
+				});
+				for j in i..i + best_len {
+					insert(&mut head, &mut prev, j);
+				}
+				i += best_len;
+			} else {
+				symbols.push(Symbol::Literal(data[i]));
+				insert(&mut head, &mut prev, i);
+				i += 1;
+			}
+		}
+		symbols
+	}
+}
+
+impl<W: Write> Write for Deflater<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.data.extend_from_slice(buf);
+		while self.data.len() - self.start > BLOCK_SIZE {
+			self.compress_block(false)?;
+		}
+		Ok(buf.len())
+	}
+
+	#[inline]
+	fn flush(&mut self) -> io::Result<()> {
+		self.inner.flush()
+	}
+}
+
+/// A gzip (RFC 1952) stream, compressed with a [`Deflater`].
+pub(super) struct GzipWriter<W> {
+	deflater: Deflater<W>,
+	crc: Crc32,
+	size: u32,
+}
+
+impl<W: Write> GzipWriter<W> {
+	/// Starts a gzip stream, writing its header to `inner`.
+	pub(super) fn new(mut inner: W) -> io::Result<Self> {
+		// No modification time, no extra flags, and an unknown operating system.
+		inner.write_all(&[0x1F, 0x8B, 8, 0, 0, 0, 0, 0, 0, 255])?;
+		Ok(Self {
+			deflater: Deflater::new(inner),
+			crc: Crc32::new(),
+			size: 0,
+		})
+	}
+
+	/// Ends the stream, returning the underlying writer.
+	pub(super) fn finish(self) -> io::Result<W> {
+		let mut inner = self.deflater.finish()?;
+		inner.write_all(&self.crc.value().to_le_bytes())?;
+		inner.write_all(&self.size.to_le_bytes())?;
+		Ok(inner)
+	}
+}
+
+impl<W: Write> Write for GzipWriter<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.deflater.write_all(buf)?;
+		self.crc.update(buf);
+		// The size is only kept modulo 2^32.
+		self.size = self.size.wrapping_add(buf.len() as u32);
+		Ok(buf.len())
+	}
+
+	#[inline]
+	fn flush(&mut self) -> io::Result<()> {
+		self.deflater.flush()
+	}
+}
@@ -0,0 +1,155 @@
+use super::writer::{copy_exact, Entry, EntryKind};
+use std::io::{self, Read, Write};
+
+const BLOCK: usize = 512;
+
+/// The largest value which fits in an octal header field of `len` bytes, leaving room for the terminating NUL.
+fn octal_max(len: usize) -> u64 {
+	(1 << (3 * (len - 1))) - 1
+}
+
+/// Writes `value` to `field` in octal, zero-padded and terminated by a NUL.
+fn put_octal(field: &mut [u8], value: u64) {
+	let digits = format!("{:0width$o}", value, width = field.len() - 1);
+	field[..digits.len()].copy_from_slice(digits.as_bytes());
+}
+
+/// Copies as much of `value` as fits into `field`.
+fn put_str(field: &mut [u8], value: &str) {
+	let len = value.len().min(field.len());
+	field[..len].copy_from_slice(&value.as_bytes()[..len]);
+}
+
+/// Appends a PAX extended header record, whose length includes its own decimal digits.
+fn put_record(records: &mut Vec<u8>, key: &str, value: &str) {
+	let len = key.len() + value.len() + 3;
+	let mut total = len + 1;
+	while total != len + total.to_string().len() {
+		total = len + total.to_string().len();
+	}
+	records.extend_from_slice(format!("{} {}={}\n", total, key, value).as_bytes());
+}
+
+/// Splits a path too long for the name field between the prefix and name fields, if possible.
+fn split_path(path: &str) -> Option<(&str, &str)> {
+	if path.len() <= 100 {
+		return Some(("", path));
+	}
+	path.match_indices('/')
+		.map(|(index, _)| (&path[..index], &path[index + 1..]))
+		.find(|(prefix, name)| prefix.len() <= 155 && name.len() <= 100 && !name.is_empty())
+}
+
+/// A streaming POSIX tar archive writer, using PAX extended headers for anything ustar can't hold.
+pub(super) struct TarWriter<W> {
+	inner: W,
+}
+
+impl<W: Write> TarWriter<W> {
+	pub(super) fn new(inner: W) -> Self {
+		Self { inner }
+	}
+
+	/// Appends `entry`, followed by `size` bytes read from `data`.
+	pub(super) fn append(&mut self, entry: &Entry, data: &mut dyn Read) -> io::Result<()> {
+		let name = match entry.kind {
+			EntryKind::Directory => format!("{}/", entry.path),
+			_ => entry.path.to_string(),
+		};
+		let mut records = Vec::new();
+		let (prefix, short_name) = split_path(&name).unwrap_or_else(|| {
+			put_record(&mut records, "path", &name);
+			("", &name)
+		});
+		let link = match entry.kind {
+			EntryKind::Symlink(target) => target,
+			_ => "",
+		};
+		if link.len() > 100 {
+			put_record(&mut records, "linkpath", link);
+		}
+		let size = entry.size;
+		if size > octal_max(12) {
+			put_record(&mut records, "size", &size.to_string());
+		}
+		let mtime = entry.updated.map_or(0, |updated| updated.unix_timestamp());
+		let in_range = u64::try_from(mtime).is_ok_and(|mtime| mtime <= octal_max(12));
+		if !in_range {
+			put_record(&mut records, "mtime", &mtime.to_string());
+		}
+		let (uid, user) = entry.owner.unwrap_or_default();
+		let (gid, group) = entry.group.unwrap_or_default();
+		if u64::from(uid) > octal_max(8) {
+			put_record(&mut records, "uid", &uid.to_string());
+		}
+		if u64::from(gid) > octal_max(8) {
+			put_record(&mut records, "gid", &gid.to_string());
+		}
+		if user.len() > 32 {
+			put_record(&mut records, "uname", user);
+		}
+		if group.len() > 32 {
+			put_record(&mut records, "gname", group);
+		}
+
+		if !records.is_empty() {
+			let mut header = [0; BLOCK];
+			put_str(&mut header[..100], "PaxHeader");
+			put_octal(&mut header[100..108], 0o644);
+			put_octal(&mut header[124..136], records.len() as u64);
+			header[156] = b'x';
+			self.write_header(header)?;
+			self.inner.write_all(&records)?;
+			self.pad(records.len() as u64)?;
+		}
+
+		let mut header = [0; BLOCK];
+		put_str(&mut header[..100], short_name);
+		put_octal(&mut header[100..108], u64::from(entry.mode));
+		put_octal(&mut header[108..116], u64::from(uid).min(octal_max(8)));
+		put_octal(&mut header[116..124], u64::from(gid).min(octal_max(8)));
+		put_octal(&mut header[124..136], size.min(octal_max(12)));
+		put_octal(
+			&mut header[136..148],
+			if in_range { mtime as u64 } else { 0 },
+		);
+		header[156] = match entry.kind {
+			EntryKind::File => b'0',
+			EntryKind::Symlink(_) => b'2',
+			EntryKind::Directory => b'5',
+		};
+		put_str(&mut header[157..257], link);
+		put_str(&mut header[265..297], user);
+		put_str(&mut header[297..329], group);
+		put_str(&mut header[345..500], prefix);
+		self.write_header(header)?;
+		copy_exact(data, size, &mut self.inner, |_| {})?;
+		self.pad(size)
+	}
+
+	/// Ends the archive with two empty blocks, returning the underlying writer.
+	pub(super) fn finish(mut self) -> io::Result<W> {
+		self.inner.write_all(&[0; 2 * BLOCK])?;
+		Ok(self.inner)
+	}
+
+	/// Fills in the magic and checksum of `header`, and writes it.
+	fn write_header(&mut self, mut header: [u8; BLOCK]) -> io::Result<()> {
+		header[257..263].copy_from_slice(b"ustar\0");
+		header[263..265].copy_from_slice(b"00");
+		// The checksum is calculated as if its own field were spaces.
+		header[148..156].fill(b' ');
+		let checksum = header.iter().map(|byte| u32::from(*byte)).sum::<u32>();
+		header[148..156].copy_from_slice(format!("{:06o}\0 ", checksum).as_bytes());
+		self.inner.write_all(&header)
+	}
+
+	/// Pads data of `len` bytes to a whole amount of blocks.
+	fn pad(&mut self, len: u64) -> io::Result<()> {
+		let remainder = (len % BLOCK as u64) as usize;
+		if remainder == 0 {
+			return Ok(());
+		}
+		self.inner.write_all(&[0; BLOCK][remainder..])
+	}
+}
@@ -0,0 +1,304 @@
+use super::{deflate::GzipWriter, tar::TarWriter, zip::ZipWriter, ArchiveFormat};
+use crate::{
+	dir::{read_children, DirKey},
+	file::FileType,
+	metadata::{FileGroup, FileOwner, Metadata, NameCache},
+};
+use std::{
+	fs::{self, File},
+	io::{self, Read, Write},
+	path::{Path, PathBuf},
+};
+use time::OffsetDateTime;
+
+/// How symlinks inside a directory are stored in its archive, as configured for each mount.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum SymlinkPolicy {
+	/// Symlinks are stored as symlinks, with their target unchanged.
+	Preserve,
+	/// The file or directory a symlink points to is stored in its place, as long as it's inside the archived directory.
+	/// Symlinks pointing anywhere else, or which are broken, are left out.
+	Follow,
+}
+
+impl Default for SymlinkPolicy {
+	#[inline]
+	fn default() -> Self {
+		SymlinkPolicy::Preserve
+	}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(super) enum EntryKind<'a> {
+	Directory,
+	File,
+	Symlink(&'a str),
+}
+
+/// An archive entry, in the terms every format needs.
+pub(super) struct Entry<'a> {
+	pub(super) path: &'a str,
+	pub(super) kind: EntryKind<'a>,
+	/// The permission bits, without the file type.
+	pub(super) mode: u32,
+	pub(super) updated: Option<OffsetDateTime>,
+	/// The size of the contents following the entry, which is always 0 for directories and symlinks.
+	pub(super) size: u64,
+	pub(super) owner: Option<(u32, &'a str)>,
+	pub(super) group: Option<(u32, &'a str)>,
+}
+
+impl<'a> Entry<'a> {
+	fn new(path: &'a str, kind: EntryKind<'a>, metadata: &'a Metadata) -> Self {
+		let permissions = metadata.permissions();
+		let mode = permissions.mode().map_or(
+			match kind {
+				EntryKind::Directory => 0o755,
+				EntryKind::Symlink(_) => 0o777,
+				EntryKind::File if permissions.execute() => 0o755,
+				EntryKind::File => 0o644,
+			},
+			|mode| mode & 0o7777,
+		);
+		Self {
+			path,
+			kind,
+			mode,
+			updated: metadata.updated(),
+			size: match kind {
+				EntryKind::File => metadata.size(),
+				_ => 0,
+			},
+			owner: match permissions.owner() {
+				Some(FileOwner::Unix { uid, name }) => Some((*uid, name.as_str())),
+				_ => None,
+			},
+			group: match permissions.group() {
+				Some(FileGroup::Unix { gid, name }) => Some((*gid, name.as_str())),
+				_ => None,
+			},
+		}
+	}
+}
+
+/// Copies exactly `size` bytes from `data` to `out`, passing each chunk to `inspect` on the way.
+///
+/// Anything after the first `size` bytes is ignored, and running out early is an [`UnexpectedEof`](io::ErrorKind::UnexpectedEof) error,
+/// as the size has usually been written before the data.
+pub(super) fn copy_exact(
+	data: &mut dyn Read,
+	size: u64,
+	out: &mut dyn Write,
+	mut inspect: impl FnMut(&[u8]),
+) -> io::Result<()> {
+	let mut buf = vec![0; 64 * 1024];
+	let mut left = size;
+	while left > 0 {
+		let len = buf.len().min(usize::try_from(left).unwrap_or(usize::MAX));
+		let read = match data.read(&mut buf[..len]) {
+			Ok(0) => {
+				return Err(io::Error::new(
+					io::ErrorKind::UnexpectedEof,
+					"file is shorter than its size",
+				))
+			}
+			Ok(read) => read,
+			Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+			Err(err) => return Err(err),
+		};
+		inspect(&buf[..read]);
+		out.write_all(&buf[..read])?;
+		left -= read as u64;
+	}
+	Ok(())
+}
+
+enum Inner<W: Write> {
+	Tar(TarWriter<W>),
+	TarGz(TarWriter<GzipWriter<W>>),
+	Zip(ZipWriter<W>),
+}
+
+/// Writes a [directory archive](crate::api::get_archive) as it's built, without buffering entries or using temporary files.
+///
+/// Entries keep the [`updated`](Metadata::updated) time of their metadata as their modification time,
+/// and its [mode bits](crate::metadata::FilePermissions::mode) if known. Paths are relative to the archived directory,
+/// using `/` as the separator, and must not contain empty, `.` or `..` components.
+///
+/// If writing fails partway, the archive is left incomplete, and the response should be aborted.
+pub struct ArchiveWriter<W: Write> {
+	inner: Inner<W>,
+}
+
+impl<W: Write> ArchiveWriter<W> {
+	/// Starts an archive of the given format, written to `writer`.
+	pub fn new(format: ArchiveFormat, writer: W) -> io::Result<Self> {
+		let inner = match format {
+			ArchiveFormat::Tar => Inner::Tar(TarWriter::new(writer)),
+			ArchiveFormat::TarGz => Inner::TarGz(TarWriter::new(GzipWriter::new(writer)?)),
+			ArchiveFormat::Zip => Inner::Zip(ZipWriter::new(writer)),
+		};
+		Ok(Self { inner })
+	}
+
+	/// Appends a directory. Its contents have to be appended separately.
+	pub fn append_dir(&mut self, path: &str, metadata: &Metadata) -> io::Result<()> {
+		self.append(
+			Entry::new(path, EntryKind::Directory, metadata),
+			&mut io::empty(),
+		)
+	}
+
+	/// Appends a file, whose contents are read from `data`.
+	///
+	/// Exactly as many bytes as the [size](Metadata::size) in `metadata` are read,
+	/// so that a file which changes while being archived can't corrupt the archive.
+	/// A file which turns out shorter than its size is an [`UnexpectedEof`](io::ErrorKind::UnexpectedEof) error.
+	pub fn append_file<R: Read>(
+		&mut self,
+		path: &str,
+		metadata: &Metadata,
+		mut data: R,
+	) -> io::Result<()> {
+		self.append(Entry::new(path, EntryKind::File, metadata), &mut data)
+	}
+
+	/// Appends a symlink pointing to `target`.
+	pub fn append_symlink(
+		&mut self,
+		path: &str,
+		metadata: &Metadata,
+		target: &str,
+	) -> io::Result<()> {
+		self.append(
+			Entry::new(path, EntryKind::Symlink(target), metadata),
+			&mut io::empty(),
+		)
+	}
+
+	fn append(&mut self, entry: Entry, data: &mut dyn Read) -> io::Result<()> {
+		let valid = !entry.path.is_empty()
+			&& entry
+				.path
+				.split('/')
+				.all(|component| !matches!(component, "" | "." | ".."));
+		if !valid {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				format!("invalid archive path '{}'", entry.path),
+			));
+		}
+		match &mut self.inner {
+			Inner::Tar(tar) => tar.append(&entry, data),
+			Inner::TarGz(tar) => tar.append(&entry, data),
+			Inner::Zip(zip) => zip.append(&entry, data),
+		}
+	}
+
+	/// Appends everything inside the directory at `root`, in name order, with paths relative to it.
+	///
+	/// `authorize` is called with the path and metadata of every entry before it's added,
+	/// and entries it returns `false` for are left out, along with their contents if they're directories.
+	/// Symlinks are handled according to `symlinks`. When following them, directories already being archived
+	/// higher up are only stored as empty directories, so symlink loops can't make the archive infinite.
+	/// Owner and group names are looked up through `names`.
+	pub fn append_tree<P: AsRef<Path>>(
+		&mut self,
+		root: P,
+		symlinks: SymlinkPolicy,
+		names: &NameCache,
+		mut authorize: impl FnMut(&str, &Metadata) -> bool,
+	) -> io::Result<()> {
+		let root = root.as_ref();
+		let mut walk = ArchiveWalk {
+			canonical_root: root.canonicalize()?,
+			symlinks,
+			names,
+			authorize: &mut authorize,
+			ancestors: vec![DirKey::new(root, &fs::metadata(root)?)?],
+		};
+		walk.append_children(self, root, "")
+	}
+
+	/// Ends the archive, returning the underlying writer.
+	pub fn finish(self) -> io::Result<W> {
+		match self.inner {
+			Inner::Tar(tar) => tar.finish(),
+			Inner::TarGz(tar) => tar.finish()?.finish(),
+			Inner::Zip(zip) => zip.finish(),
+		}
+	}
+}
+
+struct ArchiveWalk<'a> {
+	canonical_root: PathBuf,
+	symlinks: SymlinkPolicy,
+	names: &'a NameCache,
+	authorize: &'a mut dyn FnMut(&str, &Metadata) -> bool,
+	/// The directories on the current path, starting with the root.
+	ancestors: Vec<DirKey>,
+}
+
+impl ArchiveWalk<'_> {
+	fn append_children<W: Write>(
+		&mut self,
+		archive: &mut ArchiveWriter<W>,
+		dir: &Path,
+		relative: &str,
+	) -> io::Result<()> {
+		for child in read_children(dir)? {
+			let name = child.file_name().to_string_lossy().into_owned();
+			let path = if relative.is_empty() {
+				name
+			} else {
+				format!("{}/{}", relative, name)
+			};
+			let child_path = child.path();
+			let mut file_type = FileType::from(child.file_type()?);
+			if file_type == FileType::Symlink {
+				match self.symlinks {
+					SymlinkPolicy::Preserve => {
+						let target = fs::read_link(&child_path)?;
+						let metadata = Metadata::default().with_updated(
+							fs::symlink_metadata(&child_path)?
+								.modified()
+								.ok()
+								.map(OffsetDateTime::from),
+						);
+						if (self.authorize)(&path, &metadata) {
+							archive.append_symlink(&path, &metadata, &target.to_string_lossy())?;
+						}
+						continue;
+					}
+					SymlinkPolicy::Follow => match child_path.canonicalize() {
+						Ok(target) if target.starts_with(&self.canonical_root) => {
+							file_type = if target.is_dir() {
+								FileType::Directory
+							} else {
+								FileType::File
+							};
+						}
+						_ => continue,
+					},
+				}
+			}
+			let metadata = Metadata::from_path_cached(&child_path, self.names)?;
+			if !(self.authorize)(&path, &metadata) {
+				continue;
+			}
+			if file_type == FileType::Directory {
+				archive.append_dir(&path, &metadata)?;
+				let key = DirKey::new(&child_path, &fs::metadata(&child_path)?)?;
+				if self.ancestors.contains(&key) {
+					continue;
+				}
+				self.ancestors.push(key);
+				self.append_children(archive, &child_path, &path)?;
+				self.ancestors.pop();
+			} else {
+				archive.append_file(&path, &metadata, File::open(&child_path)?)?;
+			}
+		}
+		Ok(())
+	}
+}
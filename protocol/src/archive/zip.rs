@@ -0,0 +1,299 @@
+use super::{
+	crc::Crc32,
+	deflate::Deflater,
+	writer::{copy_exact, Entry, EntryKind},
+};
+use std::io::{self, Read, Write};
+use time::OffsetDateTime;
+
+const LOCAL_HEADER: u32 = 0x0403_4B50;
+const DATA_DESCRIPTOR: u32 = 0x0807_4B50;
+const CENTRAL_HEADER: u32 = 0x0201_4B50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4B50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY: u32 = 0x0606_4B50;
+const ZIP64_LOCATOR: u32 = 0x0706_4B50;
+
+const ZIP64_EXTRA: u16 = 0x0001;
+const TIMESTAMP_EXTRA: u16 = 0x5455;
+
+/// The sizes and CRC follow the data in a data descriptor.
+const FLAG_DATA_DESCRIPTOR: u16 = 1 << 3;
+/// Names are UTF-8.
+const FLAG_UTF8: u16 = 1 << 11;
+
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+
+const VERSION: u16 = 20;
+const VERSION_ZIP64: u16 = 45;
+/// The version made by, with the upper byte saying external attributes hold Unix mode bits.
+const VERSION_MADE_BY: u16 = 3 << 8 | VERSION_ZIP64;
+
+/// Files at least this big get ZIP64 sizes in their local header, as their compressed size isn't known up front,
+/// and may end up slightly bigger than the file for incompressible data.
+const ZIP64_THRESHOLD: u64 = 0xF000_0000;
+
+/// Written in place of values which are stored in the ZIP64 extra field instead.
+const MAX_U32: u64 = u32::MAX as u64;
+
+const S_IFDIR: u32 = 0o040000;
+const S_IFREG: u32 = 0o100000;
+const S_IFLNK: u32 = 0o120000;
+const MSDOS_DIRECTORY: u32 = 0x10;
+
+/// Counts the bytes written through it, so entries know their offset.
+struct Counter<W> {
+	inner: W,
+	written: u64,
+}
+
+impl<W: Write> Write for Counter<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let written = self.inner.write(buf)?;
+		self.written += written as u64;
+		Ok(written)
+	}
+
+	#[inline]
+	fn flush(&mut self) -> io::Result<()> {
+		self.inner.flush()
+	}
+}
+
+/// What the central directory needs to know about an entry written earlier.
+struct CentralEntry {
+	name: String,
+	flags: u16,
+	method: u16,
+	time: u16,
+	date: u16,
+	mtime: Option<i32>,
+	crc: u32,
+	compressed: u64,
+	size: u64,
+	offset: u64,
+	external: u32,
+	zip64: bool,
+}
+
+/// Converts a time to MS-DOS date and time fields, which only hold the years 1980 to 2107, in two second steps.
+fn dos_time(time: Option<OffsetDateTime>) -> (u16, u16) {
+	let Some(time) = time else {
+		return (0, 1 << 5 | 1);
+	};
+	match time.year() {
+		..1980 => (0, 1 << 5 | 1),
+		2108.. => (23 << 11 | 59 << 5 | 29, 127 << 9 | 12 << 5 | 31),
+		year => (
+			(u16::from(time.hour()) << 11)
+				| (u16::from(time.minute()) << 5)
+				| (u16::from(time.second()) / 2),
+			((year - 1980) as u16) << 9
+				| (u8::from(time.month()) as u16) << 5
+				| u16::from(time.day()),
+		),
+	}
+}
+
+/// Appends an extended timestamp extra field, holding the modification time in seconds, if it fits.
+fn timestamp_extra(extra: &mut Vec<u8>, mtime: Option<i32>) {
+	if let Some(mtime) = mtime {
+		extra.extend_from_slice(&TIMESTAMP_EXTRA.to_le_bytes());
+		extra.extend_from_slice(&5u16.to_le_bytes());
+		// Only the modification time is present.
+		extra.push(1);
+		extra.extend_from_slice(&mtime.to_le_bytes());
+	}
+}
+
+/// A streaming zip archive writer.
+///
+/// File contents are compressed with DEFLATE and followed by a data descriptor,
+/// so nothing has to be read twice or buffered. ZIP64 records are only used when needed.
+pub(super) struct ZipWriter<W> {
+	inner: Counter<W>,
+	entries: Vec<CentralEntry>,
+}
+
+impl<W: Write> ZipWriter<W> {
+	pub(super) fn new(inner: W) -> Self {
+		Self {
+			inner: Counter { inner, written: 0 },
+			entries: Vec::new(),
+		}
+	}
+
+	/// Appends `entry`, followed by `size` bytes read from `data` if it's a file.
+	pub(super) fn append(&mut self, entry: &Entry, data: &mut dyn Read) -> io::Result<()> {
+		let (time, date) = dos_time(entry.updated);
+		let (name, file_type, link) = match entry.kind {
+			EntryKind::Directory => (format!("{}/", entry.path), S_IFDIR, ""),
+			EntryKind::File => (entry.path.to_string(), S_IFREG, ""),
+			EntryKind::Symlink(target) => (entry.path.to_string(), S_IFLNK, target),
+		};
+		let mut external = (file_type | entry.mode) << 16;
+		if entry.kind == EntryKind::Directory {
+			external |= MSDOS_DIRECTORY;
+		}
+		let mut central = CentralEntry {
+			name,
+			flags: FLAG_UTF8,
+			method: METHOD_STORED,
+			time,
+			date,
+			mtime: entry
+				.updated
+				.and_then(|updated| i32::try_from(updated.unix_timestamp()).ok()),
+			crc: 0,
+			compressed: 0,
+			size: 0,
+			offset: self.inner.written,
+			external,
+			zip64: false,
+		};
+		if entry.kind == EntryKind::File {
+			central.flags |= FLAG_DATA_DESCRIPTOR;
+			central.method = METHOD_DEFLATED;
+			central.zip64 = entry.size >= ZIP64_THRESHOLD;
+			self.write_local_header(&central)?;
+			let start = self.inner.written;
+			let mut crc = Crc32::new();
+			let mut deflater = Deflater::new(&mut self.inner);
+			copy_exact(data, entry.size, &mut deflater, |chunk| crc.update(chunk))?;
+			deflater.finish()?;
+			central.crc = crc.value();
+			central.compressed = self.inner.written - start;
+			central.size = entry.size;
+			let mut descriptor = DATA_DESCRIPTOR.to_le_bytes().to_vec();
+			descriptor.extend_from_slice(&central.crc.to_le_bytes());
+			if central.zip64 {
+				descriptor.extend_from_slice(&central.compressed.to_le_bytes());
+				descriptor.extend_from_slice(&central.size.to_le_bytes());
+			} else {
+				descriptor.extend_from_slice(&(central.compressed as u32).to_le_bytes());
+				descriptor.extend_from_slice(&(central.size as u32).to_le_bytes());
+			}
+			self.inner.write_all(&descriptor)?;
+		} else {
+			let mut crc = Crc32::new();
+			crc.update(link.as_bytes());
+			central.crc = crc.value();
+			central.compressed = link.len() as u64;
+			central.size = link.len() as u64;
+			self.write_local_header(&central)?;
+			self.inner.write_all(link.as_bytes())?;
+		}
+		self.entries.push(central);
+		Ok(())
+	}
+
+	fn write_local_header(&mut self, entry: &CentralEntry) -> io::Result<()> {
+		let mut extra = Vec::new();
+		timestamp_extra(&mut extra, entry.mtime);
+		let (version, compressed, size) = if entry.zip64 {
+			// The real sizes are in the data descriptor, so these are only placeholders.
+			extra.extend_from_slice(&ZIP64_EXTRA.to_le_bytes());
+			extra.extend_from_slice(&16u16.to_le_bytes());
+			extra.extend_from_slice(&[0; 16]);
+			(VERSION_ZIP64, u32::MAX, u32::MAX)
+		} else {
+			(VERSION, entry.compressed as u32, entry.size as u32)
+		};
+		let mut header = LOCAL_HEADER.to_le_bytes().to_vec();
+		header.extend_from_slice(&version.to_le_bytes());
+		header.extend_from_slice(&entry.flags.to_le_bytes());
+		header.extend_from_slice(&entry.method.to_le_bytes());
+		header.extend_from_slice(&entry.time.to_le_bytes());
+		header.extend_from_slice(&entry.date.to_le_bytes());
+		header.extend_from_slice(&entry.crc.to_le_bytes());
+		header.extend_from_slice(&compressed.to_le_bytes());
+		header.extend_from_slice(&size.to_le_bytes());
+		header.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+		header.extend_from_slice(&(extra.len() as u16).to_le_bytes());
+		header.extend_from_slice(entry.name.as_bytes());
+		header.extend_from_slice(&extra);
+		self.inner.write_all(&header)
+	}
+
+	/// Writes the central directory, returning the underlying writer.
+	pub(super) fn finish(mut self) -> io::Result<W> {
+		let directory_offset = self.inner.written;
+		for entry in &self.entries {
+			let mut extra = Vec::new();
+			timestamp_extra(&mut extra, entry.mtime);
+			let zip64_sizes = entry.zip64 || entry.size >= MAX_U32 || entry.compressed >= MAX_U32;
+			let zip64_offset = entry.offset >= MAX_U32;
+			let mut zip64 = Vec::new();
+			if zip64_sizes {
+				zip64.extend_from_slice(&entry.size.to_le_bytes());
+				zip64.extend_from_slice(&entry.compressed.to_le_bytes());
+			}
+			if zip64_offset {
+				zip64.extend_from_slice(&entry.offset.to_le_bytes());
+			}
+			if !zip64.is_empty() {
+				extra.extend_from_slice(&ZIP64_EXTRA.to_le_bytes());
+				extra.extend_from_slice(&(zip64.len() as u16).to_le_bytes());
+				extra.extend_from_slice(&zip64);
+			}
+			let clamp = |value: u64, zip64: bool| if zip64 { u32::MAX } else { value as u32 };
+			let version = if zip64.is_empty() {
+				VERSION
+			} else {
+				VERSION_ZIP64
+			};
+			let mut header = CENTRAL_HEADER.to_le_bytes().to_vec();
+			header.extend_from_slice(&VERSION_MADE_BY.to_le_bytes());
+			header.extend_from_slice(&version.to_le_bytes());
+			header.extend_from_slice(&entry.flags.to_le_bytes());
+			header.extend_from_slice(&entry.method.to_le_bytes());
+			header.extend_from_slice(&entry.time.to_le_bytes());
+			header.extend_from_slice(&entry.date.to_le_bytes());
+			header.extend_from_slice(&entry.crc.to_le_bytes());
+			header.extend_from_slice(&clamp(entry.compressed, zip64_sizes).to_le_bytes());
+			header.extend_from_slice(&clamp(entry.size, zip64_sizes).to_le_bytes());
+			header.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+			header.extend_from_slice(&(extra.len() as u16).to_le_bytes());
+			// No comment, starting on the first disk, and no internal attributes.
+			header.extend_from_slice(&[0; 6]);
+			header.extend_from_slice(&entry.external.to_le_bytes());
+			header.extend_from_slice(&clamp(entry.offset, zip64_offset).to_le_bytes());
+			header.extend_from_slice(entry.name.as_bytes());
+			header.extend_from_slice(&extra);
+			self.inner.write_all(&header)?;
+		}
+		let directory_size = self.inner.written - directory_offset;
+		let count = self.entries.len() as u64;
+		let zip64 = count >= u64::from(u16::MAX)
+			|| directory_offset >= MAX_U32
+			|| directory_size >= MAX_U32;
+		let mut end = Vec::new();
+		if zip64 {
+			let record_offset = self.inner.written;
+			end.extend_from_slice(&ZIP64_END_OF_CENTRAL_DIRECTORY.to_le_bytes());
+			// The size of the rest of the record.
+			end.extend_from_slice(&44u64.to_le_bytes());
+			end.extend_from_slice(&VERSION_MADE_BY.to_le_bytes());
+			end.extend_from_slice(&VERSION_ZIP64.to_le_bytes());
+			end.extend_from_slice(&[0; 8]);
+			end.extend_from_slice(&count.to_le_bytes());
+			end.extend_from_slice(&count.to_le_bytes());
+			end.extend_from_slice(&directory_size.to_le_bytes());
+			end.extend_from_slice(&directory_offset.to_le_bytes());
+			end.extend_from_slice(&ZIP64_LOCATOR.to_le_bytes());
+			end.extend_from_slice(&0u32.to_le_bytes());
+			end.extend_from_slice(&record_offset.to_le_bytes());
+			end.extend_from_slice(&1u32.to_le_bytes());
+		}
+		let count = count.min(u64::from(u16::MAX)) as u16;
+		end.extend_from_slice(&END_OF_CENTRAL_DIRECTORY.to_le_bytes());
+		end.extend_from_slice(&[0; 4]);
+		end.extend_from_slice(&count.to_le_bytes());
+		end.extend_from_slice(&count.to_le_bytes());
+		end.extend_from_slice(&(directory_size.min(MAX_U32) as u32).to_le_bytes());
+		end.extend_from_slice(&(directory_offset.min(MAX_U32) as u32).to_le_bytes());
+		end.extend_from_slice(&0u16.to_le_bytes());
+		self.inner.write_all(&end)?;
+		Ok(self.inner.inner)
+	}
+}
@@ -0,0 +1,496 @@
+use crate::{archive::ArchiveFormat, hash::HashAlgorithm};
+use serde::{de::IgnoredAny, Deserialize, Deserializer, Serialize};
+use serde_with::skip_serializing_none;
+use std::fmt;
+
+/// The path of the capability descriptor, which is not under any mount.
+pub const WELL_KNOWN_PATH: &str = "/.well-known/xfp";
+
+/// The version of the protocol described by this crate.
+pub const PROTOCOL_VERSION: &str = "0.2";
+
+/// An endpoint a server may support.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Endpoint {
+	/// `GET /:mount/archive/:path`
+	Archive,
+	/// `GET /:mount/changes`
+	Changes,
+	/// `POST /:mount/delta/:path`
+	Delta,
+	/// `GET /:mount/dir/:path`
+	Dir,
+	/// `GET` and `PATCH /:mount/file/:path`
+	File,
+	/// `GET /:mount/hash/:path`
+	Hash,
+	/// `POST /:mount/length/:path`
+	Length,
+	/// `GET` and `PATCH /:mount/metadata/:path`
+	Metadata,
+	/// `GET /.well-known/xfp/schema/:name`
+	Schema,
+	/// `GET /:mount/signature/:path`
+	Signature,
+	/// `GET /:mount/tree/:path`
+	Tree,
+	/// `/:mount/upload/:path` and `/:mount/uploads/:id`
+	Upload,
+	/// `GET /:mount/watch/:path`
+	Watch,
+	/// `GET`, `PUT` and `DELETE /:mount/xattr/:path`
+	Xattr,
+	/// An endpoint from a newer version of the protocol, which this crate doesn't know about.
+	#[serde(other)]
+	Unknown,
+}
+
+impl Endpoint {
+	/// The name of this endpoint, as represented in JSON.
+	#[inline]
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Endpoint::Archive => "archive",
+			Endpoint::Changes => "changes",
+			Endpoint::Delta => "delta",
+			Endpoint::Dir => "dir",
+			Endpoint::File => "file",
+			Endpoint::Hash => "hash",
+			Endpoint::Length => "length",
+			Endpoint::Metadata => "metadata",
+			Endpoint::Schema => "schema",
+			Endpoint::Signature => "signature",
+			Endpoint::Tree => "tree",
+			Endpoint::Upload => "upload",
+			Endpoint::Watch => "watch",
+			Endpoint::Xattr => "xattr",
+			Endpoint::Unknown => "unknown",
+		}
+	}
+}
+
+/// An authentication scheme a server may accept in the `Authorization` header.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum AuthScheme {
+	/// HTTP Basic authentication.
+	///
+	/// Represented as the string `basic` in JSON.
+	Basic,
+	/// A bearer token.
+	///
+	/// Represented as the string `bearer` in JSON.
+	Bearer,
+	/// A scheme from a newer version of the protocol, which this crate doesn't know about.
+	#[serde(other)]
+	Unknown,
+}
+
+impl AuthScheme {
+	/// The name of this scheme, as represented in JSON.
+	#[inline]
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			AuthScheme::Basic => "basic",
+			AuthScheme::Bearer => "bearer",
+			AuthScheme::Unknown => "unknown",
+		}
+	}
+}
+
+/// The optional features of a server, beyond which endpoints it supports.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct Features {
+	ranges: bool,
+	uploads: bool,
+	watch: bool,
+	#[serde(deserialize_with = "deserialize_known")]
+	archives: Vec<ArchiveFormat>,
+	#[serde(deserialize_with = "deserialize_known")]
+	hashes: Vec<HashAlgorithm>,
+}
+
+/// An array entry which is either known to this crate, or from a newer version of the protocol.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MaybeKnown<T> {
+	Known(T),
+	Unknown(IgnoredAny),
+}
+
+/// Deserializes an array, skipping any entries this crate doesn't know about,
+/// so a server supporting newer archive formats or hash algorithms doesn't make the whole descriptor invalid.
+fn deserialize_known<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+	D: Deserializer<'de>,
+	T: Deserialize<'de>,
+{
+	let entries = Vec::<MaybeKnown<T>>::deserialize(deserializer)?;
+	Ok(entries
+		.into_iter()
+		.filter_map(|entry| match entry {
+			MaybeKnown::Known(entry) => Some(entry),
+			MaybeKnown::Unknown(_) => None,
+		})
+		.collect())
+}
+
+impl Features {
+	/// Returns if the server supports `Range` requests when downloading files.
+	///
+	/// Represented as a bool in JSON, with the key `ranges`.
+	#[inline]
+	pub fn ranges(&self) -> bool {
+		self.ranges
+	}
+
+	/// Duplicates this features object,
+	/// setting the range support flag to `ranges`.
+	#[inline]
+	pub fn with_ranges(self, ranges: bool) -> Self {
+		Self { ranges, ..self }
+	}
+
+	/// Returns if the server supports resumable uploads.
+	///
+	/// Represented as a bool in JSON, with the key `uploads`.
+	#[inline]
+	pub fn uploads(&self) -> bool {
+		self.uploads
+	}
+
+	/// Duplicates this features object,
+	/// setting the resumable upload support flag to `uploads`.
+	#[inline]
+	pub fn with_uploads(self, uploads: bool) -> Self {
+		Self { uploads, ..self }
+	}
+
+	/// Returns if the server supports watching for changes.
+	///
+	/// Represented as a bool in JSON, with the key `watch`.
+	#[inline]
+	pub fn watch(&self) -> bool {
+		self.watch
+	}
+
+	/// Duplicates this features object,
+	/// setting the watch support flag to `watch`.
+	#[inline]
+	pub fn with_watch(self, watch: bool) -> Self {
+		Self { watch, ..self }
+	}
+
+	/// Returns the archive formats the server can produce.
+	/// Formats this crate doesn't know about are left out.
+	///
+	/// Represented as an array of strings in JSON, with the key `archives`.
+	#[inline]
+	pub fn archives(&self) -> &[ArchiveFormat] {
+		&self.archives
+	}
+
+	/// Duplicates this features object,
+	/// setting the supported archive formats to `archives`.
+	#[inline]
+	pub fn with_archives(self, archives: Vec<ArchiveFormat>) -> Self {
+		Self { archives, ..self }
+	}
+
+	/// Returns the hash algorithms the server can compute.
+	/// Algorithms this crate doesn't know about are left out.
+	///
+	/// Represented as an array of strings in JSON, with the key `hashes`.
+	#[inline]
+	pub fn hashes(&self) -> &[HashAlgorithm] {
+		&self.hashes
+	}
+
+	/// Duplicates this features object,
+	/// setting the supported hash algorithms to `hashes`.
+	#[inline]
+	pub fn with_hashes(self, hashes: Vec<HashAlgorithm>) -> Self {
+		Self { hashes, ..self }
+	}
+}
+
+/// The limits a server places on requests. Every limit is optional, and a missing limit means there is none.
+#[skip_serializing_none]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Limits {
+	#[serde(default)]
+	max_upload_size: Option<u64>,
+	#[serde(default)]
+	max_page_size: Option<u64>,
+	#[serde(default)]
+	max_tree_depth: Option<u32>,
+}
+
+impl Limits {
+	/// The largest file that can be uploaded, in bytes.
+	///
+	/// Represented as a number in JSON, with the key `max_upload_size`.
+	#[inline]
+	pub fn max_upload_size(&self) -> Option<u64> {
+		self.max_upload_size
+	}
+
+	/// Duplicates this limits object,
+	/// setting the largest upload size to `max_upload_size`.
+	#[inline]
+	pub fn with_max_upload_size(self, max_upload_size: impl Into<Option<u64>>) -> Self {
+		Self {
+			max_upload_size: max_upload_size.into(),
+			..self
+		}
+	}
+
+	/// The largest `limit` accepted when paginating a directory listing.
+	///
+	/// Represented as a number in JSON, with the key `max_page_size`.
+	#[inline]
+	pub fn max_page_size(&self) -> Option<u64> {
+		self.max_page_size
+	}
+
+	/// Duplicates this limits object,
+	/// setting the largest page size to `max_page_size`.
+	#[inline]
+	pub fn with_max_page_size(self, max_page_size: impl Into<Option<u64>>) -> Self {
+		Self {
+			max_page_size: max_page_size.into(),
+			..self
+		}
+	}
+
+	/// The largest `depth` accepted when requesting a directory tree.
+	///
+	/// Represented as a number in JSON, with the key `max_tree_depth`.
+	#[inline]
+	pub fn max_tree_depth(&self) -> Option<u32> {
+		self.max_tree_depth
+	}
+
+	/// Duplicates this limits object,
+	/// setting the largest tree depth to `max_tree_depth`.
+	#[inline]
+	pub fn with_max_tree_depth(self, max_tree_depth: impl Into<Option<u32>>) -> Self {
+		Self {
+			max_tree_depth: max_tree_depth.into(),
+			..self
+		}
+	}
+}
+
+/// Something a client may want to use, which not every server supports.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Feature {
+	/// An endpoint.
+	Endpoint(Endpoint),
+	/// `Range` requests when downloading files.
+	Ranges,
+	/// Resumable uploads.
+	Uploads,
+	/// Watching for changes.
+	Watch,
+	/// Downloading directories as archives of the given format.
+	Archive(ArchiveFormat),
+	/// Hashing files with the given algorithm.
+	Hash(HashAlgorithm),
+	/// Authenticating with the given scheme.
+	Auth(AuthScheme),
+}
+
+impl fmt::Display for Feature {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Feature::Endpoint(endpoint) => write!(f, "the `{}` endpoint", endpoint.as_str()),
+			Feature::Ranges => f.write_str("range requests"),
+			Feature::Uploads => f.write_str("resumable uploads"),
+			Feature::Watch => f.write_str("watching for changes"),
+			Feature::Archive(format) => write!(f, "{} archives", format.extension()),
+			Feature::Hash(algorithm) => write!(f, "{} hashes", algorithm.digest_name()),
+			Feature::Auth(scheme) => write!(f, "{} authentication", scheme.as_str()),
+		}
+	}
+}
+
+/// The error returned when a client requires a [`Feature`] the server doesn't support.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Unsupported(pub Feature);
+
+impl fmt::Display for Unsupported {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "the server does not support {}", self.0)
+	}
+}
+
+impl std::error::Error for Unsupported {}
+
+/// Describes what a server supports, so clients can avoid requests that are bound to fail.
+///
+/// Returned by `GET /.well-known/xfp`. Clients should fetch it once when connecting,
+/// and gate every optional feature on [`supports`](Self::supports) or [`require`](Self::require)
+/// rather than inspecting the fields themselves.
+/// Entries from newer versions of the protocol are either skipped or deserialized as `Unknown`,
+/// so they never make the descriptor invalid.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Capabilities {
+	version: String,
+	#[serde(default)]
+	endpoints: Vec<Endpoint>,
+	#[serde(default)]
+	features: Features,
+	#[serde(default)]
+	limits: Limits,
+	#[serde(default)]
+	auth: Vec<AuthScheme>,
+}
+
+impl Default for Capabilities {
+	#[inline]
+	fn default() -> Self {
+		Self {
+			version: PROTOCOL_VERSION.to_string(),
+			endpoints: Vec::new(),
+			features: Features::default(),
+			limits: Limits::default(),
+			auth: Vec::new(),
+		}
+	}
+}
+
+impl Capabilities {
+	/// The version of the protocol the server implements, as `major.minor`.
+	///
+	/// Represented as a string in JSON, with the key `version`.
+	#[inline]
+	pub fn version(&self) -> &str {
+		&self.version
+	}
+
+	/// Duplicates this capabilities object,
+	/// setting the protocol version to `version`.
+	#[inline]
+	pub fn with_version(self, version: impl ToString) -> Self {
+		Self {
+			version: version.to_string(),
+			..self
+		}
+	}
+
+	/// Returns the endpoints the server supports.
+	///
+	/// Represented as an array of strings in JSON, with the key `endpoints`.
+	#[inline]
+	pub fn endpoints(&self) -> &[Endpoint] {
+		&self.endpoints
+	}
+
+	/// Duplicates this capabilities object,
+	/// setting the supported endpoints to `endpoints`.
+	#[inline]
+	pub fn with_endpoints(self, endpoints: Vec<Endpoint>) -> Self {
+		Self { endpoints, ..self }
+	}
+
+	/// Returns the optional features the server supports.
+	///
+	/// Represented as an object in JSON, with the key `features`.
+	#[inline]
+	pub fn features(&self) -> &Features {
+		&self.features
+	}
+
+	/// Duplicates this capabilities object,
+	/// setting the optional features to `features`.
+	#[inline]
+	pub fn with_features(self, features: Features) -> Self {
+		Self { features, ..self }
+	}
+
+	/// Returns the limits the server places on requests.
+	///
+	/// Represented as an object in JSON, with the key `limits`.
+	#[inline]
+	pub fn limits(&self) -> &Limits {
+		&self.limits
+	}
+
+	/// Duplicates this capabilities object,
+	/// setting the limits to `limits`.
+	#[inline]
+	pub fn with_limits(self, limits: Limits) -> Self {
+		Self { limits, ..self }
+	}
+
+	/// Returns the authentication schemes the server accepts.
+	///
+	/// Represented as an array of strings in JSON, with the key `auth`.
+	#[inline]
+	pub fn auth(&self) -> &[AuthScheme] {
+		&self.auth
+	}
+
+	/// Duplicates this capabilities object,
+	/// setting the accepted authentication schemes to `auth`.
+	#[inline]
+	pub fn with_auth(self, auth: Vec<AuthScheme>) -> Self {
+		Self { auth, ..self }
+	}
+
+	/// Returns the major version of the protocol the server implements,
+	/// or `None` if the version is malformed.
+	pub fn major_version(&self) -> Option<u32> {
+		self.version.split('.').next()?.parse().ok()
+	}
+
+	/// Returns if a client built against this crate can talk to the server at all,
+	/// which requires the major versions to match.
+	pub fn is_compatible(&self) -> bool {
+		let ours = PROTOCOL_VERSION
+			.split('.')
+			.next()
+			.and_then(|major| major.parse().ok());
+		self.major_version().is_some() && self.major_version() == ours
+	}
+
+	/// Returns if the server supports `feature`.
+	///
+	/// This is how clients should decide whether to use an optional feature,
+	/// such as falling back to a plain upload when resumable uploads aren't supported.
+	/// The `Unknown` endpoint and authentication scheme are never supported,
+	/// even if the server listed something this crate doesn't know about.
+	pub fn supports(&self, feature: Feature) -> bool {
+		match feature {
+			Feature::Endpoint(Endpoint::Unknown) | Feature::Auth(AuthScheme::Unknown) => false,
+			Feature::Endpoint(endpoint) => self.endpoints.contains(&endpoint),
+			Feature::Ranges => self.features.ranges,
+			Feature::Uploads => self.features.uploads,
+			Feature::Watch => self.features.watch,
+			Feature::Archive(format) => self.features.archives.contains(&format),
+			Feature::Hash(algorithm) => self.features.hashes.contains(&algorithm),
+			Feature::Auth(scheme) => self.auth.contains(&scheme),
+		}
+	}
+
+	/// Checks the server supports `feature` before a client relies on it,
+	/// returning an [`Unsupported`] error if it doesn't.
+	///
+	/// Clients should call this before any request which can't work without the feature,
+	/// so users get a clear error rather than whatever the server returns for an unknown endpoint.
+	pub fn require(&self, feature: Feature) -> Result<(), Unsupported> {
+		if self.supports(feature) {
+			Ok(())
+		} else {
+			Err(Unsupported(feature))
+		}
+	}
+}
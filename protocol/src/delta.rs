@@ -0,0 +1,413 @@
+use crate::encoding::{deserialize_base64, serialize_base64};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr, PickFirst};
+use std::{
+	collections::{HashMap, VecDeque},
+	io::{self, BufReader, Read, Seek, SeekFrom, Write},
+	mem,
+};
+
+/// The block size used for signatures if the client doesn't ask for a specific one.
+pub const DEFAULT_BLOCK_SIZE: u32 = 4096;
+
+/// The largest block size signatures can be computed with, as each block is read into memory whole.
+pub const MAX_BLOCK_SIZE: u32 = 1024 * 1024;
+
+/// The signature of a single block of a file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BlockSignature {
+	weak: u32,
+	strong: String,
+}
+
+impl BlockSignature {
+	/// The rolling checksum of this block, used to cheaply find candidate matches.
+	///
+	/// Represented as a number in JSON, with the key `weak`.
+	#[inline]
+	pub fn weak(&self) -> u32 {
+		self.weak
+	}
+
+	/// The first 128 bits of the BLAKE3 hash of this block, as a lowercase hexadecimal string,
+	/// used to confirm a candidate match.
+	///
+	/// Represented as a string in JSON, with the key `strong`.
+	#[inline]
+	pub fn strong(&self) -> &str {
+		&self.strong
+	}
+}
+
+/// The block signatures of a whole file, which a client uses to find out
+/// which parts of the file it already has.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Signature {
+	block_size: u32,
+	#[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+	#[cfg_attr(feature = "schema", schemars(with = "crate::schema::Size"))]
+	size: u64,
+	blocks: Vec<BlockSignature>,
+}
+
+impl Signature {
+	/// Computes the signature of everything read from `reader`, split into blocks of `block_size` bytes.
+	/// The last block may be shorter.
+	///
+	/// Fails with [`io::ErrorKind::InvalidInput`] if `block_size` is larger than [`MAX_BLOCK_SIZE`].
+	pub fn from_reader<R: Read>(mut reader: R, block_size: u32) -> io::Result<Self> {
+		check_block_size(block_size)?;
+		let block_size = block_size.max(1);
+		let mut buffer = vec![0; block_size as usize];
+		let mut blocks = Vec::new();
+		let mut size = 0;
+		loop {
+			let len = read_full(&mut reader, &mut buffer)?;
+			if len == 0 {
+				break;
+			}
+			size += len as u64;
+			blocks.push(BlockSignature {
+				weak: RollingChecksum::new(&buffer[..len]).digest(),
+				strong: strong_hash(&buffer[..len]),
+			});
+			if len < buffer.len() {
+				break;
+			}
+		}
+		Ok(Self {
+			block_size,
+			size,
+			blocks,
+		})
+	}
+
+	/// The size of each block, in bytes.
+	///
+	/// Represented as a number in JSON, with the key `block_size`.
+	#[inline]
+	pub fn block_size(&self) -> u32 {
+		self.block_size
+	}
+
+	/// The size of the whole file, in bytes.
+	///
+	/// Represented as a number in JSON, with the key `size`.
+	#[inline]
+	pub fn size(&self) -> u64 {
+		self.size
+	}
+
+	/// The signatures of each block, in order.
+	///
+	/// Represented as an array of [`BlockSignature`] objects in JSON, with the key `blocks`.
+	#[inline]
+	pub fn blocks(&self) -> &[BlockSignature] {
+		&self.blocks
+	}
+}
+
+/// A single instruction for rebuilding a file out of an old version of it.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum DeltaOp {
+	/// Copy `count` consecutive blocks from the old file, starting at block index `block`.
+	///
+	/// Represented as an object with `op` set to `copy` in JSON.
+	Copy {
+		/// The index of the first block to copy.
+		#[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+		#[cfg_attr(feature = "schema", schemars(with = "crate::schema::Size"))]
+		block: u64,
+		/// The amount of consecutive blocks to copy.
+		#[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+		#[cfg_attr(feature = "schema", schemars(with = "crate::schema::Size"))]
+		count: u64,
+	},
+	/// Write new data which isn't in the old file.
+	///
+	/// Represented as an object with `op` set to `data` in JSON, with the data base64-encoded.
+	Data {
+		/// The data to write.
+		#[serde(
+			serialize_with = "serialize_base64",
+			deserialize_with = "deserialize_base64"
+		)]
+		#[cfg_attr(feature = "schema", schemars(with = "crate::schema::Base64"))]
+		data: Vec<u8>,
+	},
+}
+
+/// The difference between an old version of a file, described by its [`Signature`],
+/// and a new version of it.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Delta {
+	block_size: u32,
+	#[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+	#[cfg_attr(feature = "schema", schemars(with = "crate::schema::Size"))]
+	size: u64,
+	ops: Vec<DeltaOp>,
+}
+
+impl Delta {
+	/// Computes the delta which turns the file described by `signature` into everything read from `reader`,
+	/// using rsync's rolling checksum algorithm.
+	///
+	/// Fails with [`io::ErrorKind::InvalidInput`] if the signature has a block size of 0,
+	/// or one larger than [`MAX_BLOCK_SIZE`].
+	pub fn compute<R: Read>(signature: &Signature, reader: R) -> io::Result<Self> {
+		if signature.block_size == 0 {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				"the signature has a block size of 0",
+			));
+		}
+		check_block_size(signature.block_size)?;
+		let block_size = signature.block_size as usize;
+		let mut index = HashMap::<u32, Vec<usize>>::new();
+		for (idx, block) in signature.blocks.iter().enumerate() {
+			// Short blocks can only ever be matched at the very end, so they're not worth indexing.
+			let full = (idx as u64 + 1) * block_size as u64 <= signature.size;
+			if full {
+				index.entry(block.weak).or_default().push(idx);
+			}
+		}
+		let mut bytes = BufReader::new(reader).bytes();
+		let mut ops = Vec::new();
+		let mut literal = Vec::new();
+		let mut window = VecDeque::new();
+		let mut checksum = fill_window(&mut bytes, &mut window, block_size)?
+			.then(|| RollingChecksum::new(window.make_contiguous()));
+		while let Some(rolling) = checksum.as_mut() {
+			let matched = index.get(&rolling.digest()).and_then(|candidates| {
+				let strong = strong_hash(window.make_contiguous());
+				candidates
+					.iter()
+					.copied()
+					.find(|idx| signature.blocks[*idx].strong == strong)
+			});
+			if let Some(block) = matched {
+				if !literal.is_empty() {
+					ops.push(DeltaOp::Data {
+						data: mem::take(&mut literal),
+					});
+				}
+				push_copy(&mut ops, block as u64);
+				window.clear();
+				checksum = fill_window(&mut bytes, &mut window, block_size)?
+					.then(|| RollingChecksum::new(window.make_contiguous()));
+			} else if let Some(byte) = bytes.next().transpose()? {
+				let out = window.pop_front().expect("the window is full");
+				window.push_back(byte);
+				literal.push(out);
+				rolling.roll(out, byte);
+			} else {
+				break;
+			}
+		}
+		literal.extend(window);
+		if !literal.is_empty() {
+			ops.push(DeltaOp::Data { data: literal });
+		}
+		let mut delta = Self {
+			block_size: signature.block_size,
+			size: 0,
+			ops,
+		};
+		delta.size = delta.copied_len() + delta.literal_len();
+		Ok(delta)
+	}
+
+	/// The block size of the signature this delta was computed against.
+	///
+	/// Represented as a number in JSON, with the key `block_size`.
+	#[inline]
+	pub fn block_size(&self) -> u32 {
+		self.block_size
+	}
+
+	/// The size of the rebuilt file, in bytes.
+	///
+	/// Represented as a number in JSON, with the key `size`.
+	#[inline]
+	pub fn size(&self) -> u64 {
+		self.size
+	}
+
+	/// The instructions for rebuilding the file, in order.
+	///
+	/// Represented as an array of [`DeltaOp`] objects in JSON, with the key `ops`.
+	#[inline]
+	pub fn ops(&self) -> &[DeltaOp] {
+		&self.ops
+	}
+
+	/// The amount of new data in this delta, which is what actually has to be transferred.
+	pub fn literal_len(&self) -> u64 {
+		self.ops
+			.iter()
+			.map(|op| match op {
+				DeltaOp::Data { data } => data.len() as u64,
+				DeltaOp::Copy { .. } => 0,
+			})
+			.sum()
+	}
+
+	fn copied_len(&self) -> u64 {
+		self.ops
+			.iter()
+			.map(|op| match op {
+				DeltaOp::Copy { count, .. } => count * self.block_size as u64,
+				DeltaOp::Data { .. } => 0,
+			})
+			.sum()
+	}
+
+	/// Rebuilds the new file into `output`, reading copied blocks from the old file in `base`.
+	///
+	/// Fails with [`io::ErrorKind::InvalidData`] if the delta refers to blocks the old file doesn't have,
+	/// or if the result doesn't have the expected size.
+	pub fn apply<B: Read + Seek, W: Write>(&self, mut base: B, mut output: W) -> io::Result<()> {
+		let block_size = self.block_size as u64;
+		let mut written = 0;
+		for op in &self.ops {
+			match op {
+				DeltaOp::Copy { block, count } => {
+					let len = count
+						.checked_mul(block_size)
+						.ok_or_else(|| invalid_data("copy is too large"))?;
+					let start = block.checked_mul(block_size).ok_or_else(|| {
+						invalid_data("copy refers to blocks past the end of the file")
+					})?;
+					base.seek(SeekFrom::Start(start))?;
+					let copied = io::copy(&mut (&mut base).take(len), &mut output)?;
+					if copied != len {
+						return Err(invalid_data(
+							"copy refers to blocks past the end of the file",
+						));
+					}
+					written += copied;
+				}
+				DeltaOp::Data { data } => {
+					output.write_all(data)?;
+					written += data.len() as u64;
+				}
+			}
+		}
+		if written != self.size {
+			return Err(invalid_data("rebuilt file has the wrong size"));
+		}
+		output.flush()
+	}
+}
+
+fn push_copy(ops: &mut Vec<DeltaOp>, block: u64) {
+	if let Some(DeltaOp::Copy {
+		block: start,
+		count,
+	}) = ops.last_mut()
+	{
+		if *start + *count == block {
+			*count += 1;
+			return;
+		}
+	}
+	ops.push(DeltaOp::Copy { block, count: 1 });
+}
+
+fn check_block_size(block_size: u32) -> io::Result<()> {
+	if block_size > MAX_BLOCK_SIZE {
+		return Err(io::Error::new(
+			io::ErrorKind::InvalidInput,
+			format!("block size is larger than {} bytes", MAX_BLOCK_SIZE),
+		));
+	}
+	Ok(())
+}
+
+/// Reads from `bytes` until `window` holds `block_size` bytes, returning `false` if it ran out first.
+fn fill_window<I: Iterator<Item = io::Result<u8>>>(
+	bytes: &mut I,
+	window: &mut VecDeque<u8>,
+	block_size: usize,
+) -> io::Result<bool> {
+	while window.len() < block_size {
+		match bytes.next().transpose()? {
+			Some(byte) => window.push_back(byte),
+			None => return Ok(false),
+		}
+	}
+	Ok(true)
+}
+
+/// rsync's rolling checksum, which can be updated in constant time as the window moves by one byte.
+///
+/// This is the [`weak`](BlockSignature::weak) checksum of each block in a [`Signature`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct RollingChecksum {
+	a: u32,
+	b: u32,
+	len: u32,
+}
+
+impl RollingChecksum {
+	/// Computes the checksum of a whole block.
+	pub fn new(block: &[u8]) -> Self {
+		let len = block.len() as u32;
+		let (a, b) = block
+			.iter()
+			.enumerate()
+			.fold((0u32, 0u32), |(a, b), (idx, byte)| {
+				(
+					a.wrapping_add(*byte as u32),
+					b.wrapping_add((len - idx as u32).wrapping_mul(*byte as u32)),
+				)
+			});
+		Self { a, b, len }
+	}
+
+	/// Moves the window forward by one byte, removing `out` from its start and adding `ins` to its end.
+	pub fn roll(&mut self, out: u8, ins: u8) {
+		self.a = self.a.wrapping_sub(out as u32).wrapping_add(ins as u32);
+		self.b = self
+			.b
+			.wrapping_sub(self.len.wrapping_mul(out as u32))
+			.wrapping_add(self.a);
+	}
+
+	/// The current value of the checksum.
+	pub fn digest(&self) -> u32 {
+		(self.a & 0xffff) | (self.b << 16)
+	}
+}
+
+fn strong_hash(block: &[u8]) -> String {
+	blake3::hash(block).as_bytes()[..16]
+		.iter()
+		.map(|byte| format!("{:02x}", byte))
+		.collect()
+}
+
+fn read_full<R: Read>(reader: &mut R, buffer: &mut [u8]) -> io::Result<usize> {
+	let mut len = 0;
+	while len < buffer.len() {
+		match reader.read(&mut buffer[len..]) {
+			Ok(0) => break,
+			Ok(read) => len += read,
+			Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+			Err(err) => return Err(err),
+		}
+	}
+	Ok(len)
+}
+
+fn invalid_data(message: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -1,11 +1,27 @@
+mod ndjson;
+mod page;
+mod query;
+mod tree;
+
+pub(crate) use self::tree::{read_children, DirKey};
+pub use self::{
+	ndjson::{write_entry, write_truncated, DirectoryEntryReader, NDJSON_CONTENT_TYPE},
+	page::DirectoryListingPage,
+	query::{DirectoryListQuery, InvalidQuery, SortKey, SortOrder},
+	tree::TreeQuery,
+};
 use crate::{file::FileType, metadata::Metadata};
 use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
 
 pub type DirectoryListing = Vec<DirectoryListEntry>;
 
+#[skip_serializing_none]
 #[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct DirectoryListEntry {
 	name: String,
+	path: Option<String>,
 	#[serde(rename = "type")]
 	file_type: FileType,
 	metadata: Metadata,
@@ -30,6 +46,27 @@ impl DirectoryListEntry {
 		}
 	}
 
+	/// The path of this file object, relative to the directory being listed,
+	/// using `/` as the separator.
+	///
+	/// This is only present in [tree listings](crate::api::get_tree).
+	///
+	/// Represented as a string in JSON, with the key `path`.
+	#[inline]
+	pub fn path(&self) -> Option<&str> {
+		self.path.as_deref()
+	}
+
+	/// Duplicates this directory entry object,
+	/// updating the path with the specified string.
+	#[inline]
+	pub fn with_path(self, path: impl Into<Option<String>>) -> Self {
+		Self {
+			path: path.into(),
+			..self
+		}
+	}
+
 	/// The type of this file object.
 	///
 	/// Represented as a string of either `file`, `directory`, or `symlink` in JSON, with the key `type`.
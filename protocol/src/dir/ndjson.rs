@@ -0,0 +1,107 @@
+use super::DirectoryListEntry;
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, Write};
+
+/// The media type of a streamed directory listing,
+/// to be used in the `Accept` and `Content-Type` headers.
+pub const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// Reads a streamed directory listing one entry at a time,
+/// without ever holding more than a single line in memory.
+///
+/// Blank lines are skipped. A [truncation marker](write_truncated) ends the listing,
+/// after which [`is_truncated`](Self::is_truncated) returns `true`.
+/// A marker which isn't exactly `{"truncated":true}` is an [`InvalidData`](io::ErrorKind::InvalidData) error,
+/// rather than being mistaken for the end of a complete listing.
+#[derive(Debug)]
+pub struct DirectoryEntryReader<R> {
+	reader: R,
+	line: String,
+	truncated: bool,
+}
+
+impl<R: BufRead> DirectoryEntryReader<R> {
+	/// Creates a new reader over a streamed directory listing.
+	#[inline]
+	pub fn new(reader: R) -> Self {
+		Self {
+			reader,
+			line: String::new(),
+			truncated: false,
+		}
+	}
+
+	/// Whether the listing ended with a truncation marker,
+	/// meaning the server stopped before listing everything.
+	/// This is only meaningful once the reader has been exhausted.
+	#[inline]
+	pub fn is_truncated(&self) -> bool {
+		self.truncated
+	}
+
+	/// Consumes this reader, returning the underlying reader.
+	#[inline]
+	pub fn into_inner(self) -> R {
+		self.reader
+	}
+}
+
+impl<R: BufRead> Iterator for DirectoryEntryReader<R> {
+	type Item = io::Result<DirectoryListEntry>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.truncated {
+			return None;
+		}
+		loop {
+			self.line.clear();
+			match self.reader.read_line(&mut self.line) {
+				Ok(0) => return None,
+				Ok(_) if self.line.trim().is_empty() => continue,
+				Ok(_) => {
+					return match serde_json::from_str(&self.line) {
+						Ok(Line::Truncated(TruncationMarker { truncated: true })) => {
+							self.truncated = true;
+							None
+						}
+						Ok(Line::Truncated(_)) => Some(Err(io::Error::new(
+							io::ErrorKind::InvalidData,
+							"truncation marker is not `true`",
+						))),
+						Ok(Line::Entry(entry)) => Some(Ok(*entry)),
+						Err(err) => Some(Err(err.into())),
+					}
+				}
+				Err(err) => return Some(Err(err)),
+			}
+		}
+	}
+}
+
+/// The last line of a streamed listing which was cut short, `{"truncated":true}`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TruncationMarker {
+	truncated: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Line {
+	Truncated(TruncationMarker),
+	Entry(Box<DirectoryListEntry>),
+}
+
+/// Writes a single entry of a streamed directory listing,
+/// as one line of JSON.
+pub fn write_entry<W: Write>(mut writer: W, entry: &DirectoryListEntry) -> io::Result<()> {
+	serde_json::to_writer(&mut writer, entry)?;
+	writer.write_all(b"\n")
+}
+
+/// Ends a streamed directory listing which was cut short, such as by a [tree listing](crate::api::get_tree)'s `limit`,
+/// by writing the line `{"truncated":true}`.
+pub fn write_truncated<W: Write>(mut writer: W) -> io::Result<()> {
+	serde_json::to_writer(&mut writer, &TruncationMarker { truncated: true })?;
+	writer.write_all(b"\n")
+}
@@ -0,0 +1,147 @@
+use super::{
+	query::{InvalidQuery, SortPosition},
+	DirectoryListEntry, DirectoryListQuery,
+};
+use crate::file::FileType;
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+use std::{cmp::Ordering, fmt::Write};
+
+/// A single page of a directory listing.
+#[skip_serializing_none]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DirectoryListingPage {
+	entries: Vec<DirectoryListEntry>,
+	next_cursor: Option<String>,
+}
+
+impl DirectoryListingPage {
+	/// Builds a page out of a full directory listing,
+	/// filtering and sorting it according to the query first.
+	///
+	/// Cursors encode the position of the last entry in the page, rather than an index,
+	/// so that they stay valid even if entries are added or removed between requests.
+	///
+	/// Fails with [`InvalidQuery::Limit`] if the limit is 0.
+	pub fn paginate(
+		entries: Vec<DirectoryListEntry>,
+		query: &DirectoryListQuery,
+	) -> Result<Self, InvalidQuery> {
+		if query.limit() == Some(0) {
+			return Err(InvalidQuery::Limit);
+		}
+		let mut entries = query.apply(entries)?;
+		let tag = query.ordering_tag();
+		if let Some(cursor) = query.cursor() {
+			let after = decode_cursor(cursor, &tag).ok_or(InvalidQuery::Cursor)?;
+			let start = entries.partition_point(|entry| {
+				query.compare(&SortPosition::of(entry), &after) != Ordering::Greater
+			});
+			entries.drain(..start);
+		}
+		// A limit too large for this platform can't be reached anyway.
+		let limit = query
+			.limit()
+			.map(|limit| usize::try_from(limit).unwrap_or(usize::MAX));
+		let next_cursor = match limit {
+			Some(limit) if limit < entries.len() => {
+				entries.truncate(limit);
+				entries
+					.last()
+					.map(|entry| encode_cursor(&tag, &SortPosition::of(entry)))
+			}
+			_ => None,
+		};
+		Ok(Self {
+			entries,
+			next_cursor,
+		})
+	}
+
+	/// The entries in this page.
+	///
+	/// Represented as an array of [`DirectoryListEntry`] objects in JSON, with the key `entries`.
+	#[inline]
+	pub fn entries(&self) -> &[DirectoryListEntry] {
+		&self.entries
+	}
+
+	/// Duplicates this page object,
+	/// updating the entries with the specified value.
+	#[inline]
+	pub fn with_entries(self, entries: Vec<DirectoryListEntry>) -> Self {
+		Self { entries, ..self }
+	}
+
+	/// The cursor to pass to get the next page, if there is one.
+	/// If this is `None`, this is the last page.
+	///
+	/// Represented as a string in JSON, with the key `next_cursor`.
+	#[inline]
+	pub fn next_cursor(&self) -> Option<&str> {
+		self.next_cursor.as_deref()
+	}
+
+	/// Duplicates this page object,
+	/// updating the next cursor with the specified value.
+	#[inline]
+	pub fn with_next_cursor(self, next_cursor: impl Into<Option<String>>) -> Self {
+		Self {
+			next_cursor: next_cursor.into(),
+			..self
+		}
+	}
+
+	/// Consumes this page, returning its entries.
+	#[inline]
+	pub fn into_entries(self) -> Vec<DirectoryListEntry> {
+		self.entries
+	}
+}
+
+const CURSOR_SEPARATOR: char = '\u{1f}';
+
+fn encode_cursor(tag: &str, position: &SortPosition) -> String {
+	let raw = [
+		tag.to_string(),
+		position.file_type.to_string(),
+		position.size.to_string(),
+		position
+			.updated
+			.map(|updated| updated.to_string())
+			.unwrap_or_default(),
+		position.name.clone(),
+	]
+	.join(&CURSOR_SEPARATOR.to_string());
+	raw.bytes()
+		.fold(String::with_capacity(raw.len() * 2), |mut out, byte| {
+			let _ = write!(out, "{:02x}", byte);
+			out
+		})
+}
+
+fn decode_cursor(cursor: &str, tag: &str) -> Option<SortPosition> {
+	let bytes = (0..cursor.len())
+		.step_by(2)
+		.map(|idx| u8::from_str_radix(cursor.get(idx..idx + 2)?, 16).ok())
+		.collect::<Option<Vec<u8>>>()?;
+	let raw = String::from_utf8(bytes).ok()?;
+	let mut parts = raw.splitn(5, CURSOR_SEPARATOR);
+	if parts.next()? != tag {
+		return None;
+	}
+	let file_type = parts.next()?.parse::<FileType>().ok()?;
+	let size = parts.next()?.parse::<u64>().ok()?;
+	let updated = match parts.next()? {
+		"" => None,
+		updated => Some(updated.parse::<i128>().ok()?),
+	};
+	let name = parts.next()?.to_string();
+	Some(SortPosition {
+		name,
+		file_type,
+		size,
+		updated,
+	})
+}
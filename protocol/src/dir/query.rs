@@ -0,0 +1,415 @@
+use super::DirectoryListEntry;
+use crate::file::FileType;
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+use std::{cmp::Ordering, fmt};
+
+/// The key a directory listing is sorted by.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+	/// Sort by the name of each entry.
+	///
+	/// Represented as the string `name` in the query string.
+	Name,
+	/// Sort by the size of each entry, then by name.
+	///
+	/// Represented as the string `size` in the query string.
+	Size,
+	/// Sort by the time each entry was last updated, then by name.
+	/// Entries without an updated time come first.
+	///
+	/// Represented as the string `updated` in the query string.
+	Updated,
+	/// Sort by the type of each entry, then by name.
+	/// Directories come first, then files, then symlinks.
+	///
+	/// Represented as the string `type` in the query string.
+	Type,
+}
+
+impl SortKey {
+	fn as_str(&self) -> &'static str {
+		match self {
+			SortKey::Name => "name",
+			SortKey::Size => "size",
+			SortKey::Updated => "updated",
+			SortKey::Type => "type",
+		}
+	}
+}
+
+/// The direction a directory listing is sorted in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+	/// Smallest first.
+	///
+	/// Represented as the string `asc` in the query string.
+	Asc,
+	/// Largest first.
+	///
+	/// Represented as the string `desc` in the query string.
+	Desc,
+}
+
+impl Default for SortOrder {
+	#[inline]
+	fn default() -> Self {
+		SortOrder::Asc
+	}
+}
+
+/// The error returned when a [`DirectoryListQuery`] can't be applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvalidQuery {
+	/// The cursor was malformed, or was created for a listing with different parameters.
+	Cursor,
+	/// The glob pattern could not be parsed.
+	Glob(String),
+	/// The limit was 0, so no page could ever make progress.
+	Limit,
+}
+
+impl fmt::Display for InvalidQuery {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			InvalidQuery::Cursor => f.write_str("invalid cursor"),
+			InvalidQuery::Glob(err) => write!(f, "invalid glob pattern: {}", err),
+			InvalidQuery::Limit => f.write_str("limit must be at least 1"),
+		}
+	}
+}
+
+impl std::error::Error for InvalidQuery {}
+
+/// The query parameters accepted by a directory listing.
+///
+/// Filters and sorting are applied before pagination.
+/// If neither `limit` nor `cursor` is present, the server returns a plain
+/// [`DirectoryListing`](super::DirectoryListing) instead of a [`DirectoryListingPage`](super::DirectoryListingPage).
+#[skip_serializing_none]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DirectoryListQuery {
+	limit: Option<u64>,
+	cursor: Option<String>,
+	sort: Option<SortKey>,
+	order: Option<SortOrder>,
+	natural: Option<bool>,
+	#[serde(rename = "type")]
+	file_type: Option<FileType>,
+	glob: Option<String>,
+	hidden: Option<bool>,
+}
+
+impl DirectoryListQuery {
+	/// The maximum amount of entries to return in a single page, which must be at least 1.
+	///
+	/// Represented as a number in the query string, with the key `limit`.
+	#[inline]
+	pub fn limit(&self) -> Option<u64> {
+		self.limit
+	}
+
+	/// Duplicates this query object,
+	/// updating the limit with the specified value.
+	#[inline]
+	pub fn with_limit(self, limit: impl Into<Option<u64>>) -> Self {
+		Self {
+			limit: limit.into(),
+			..self
+		}
+	}
+
+	/// The opaque cursor returned as `next_cursor` by the previous page, if any.
+	///
+	/// Represented as a string in the query string, with the key `cursor`.
+	#[inline]
+	pub fn cursor(&self) -> Option<&str> {
+		self.cursor.as_deref()
+	}
+
+	/// Duplicates this query object,
+	/// updating the cursor with the specified value.
+	#[inline]
+	pub fn with_cursor(self, cursor: impl Into<Option<String>>) -> Self {
+		Self {
+			cursor: cursor.into(),
+			..self
+		}
+	}
+
+	/// The key to sort the listing by.
+	///
+	/// If this is `None`, unpaginated listings are returned in the order they are read by the server,
+	/// and paginated listings are sorted by name.
+	///
+	/// Represented as a string in the query string, with the key `sort`. See [`SortKey`].
+	#[inline]
+	pub fn sort(&self) -> Option<SortKey> {
+		self.sort
+	}
+
+	/// Duplicates this query object,
+	/// updating the sort key with the specified value.
+	#[inline]
+	pub fn with_sort(self, sort: impl Into<Option<SortKey>>) -> Self {
+		Self {
+			sort: sort.into(),
+			..self
+		}
+	}
+
+	/// The direction to sort the listing in. Defaults to ascending.
+	///
+	/// Represented as either `asc` or `desc` in the query string, with the key `order`.
+	#[inline]
+	pub fn order(&self) -> SortOrder {
+		self.order.unwrap_or_default()
+	}
+
+	/// Duplicates this query object,
+	/// updating the sort direction with the specified value.
+	#[inline]
+	pub fn with_order(self, order: impl Into<Option<SortOrder>>) -> Self {
+		Self {
+			order: order.into(),
+			..self
+		}
+	}
+
+	/// Whether names are compared "naturally": case-insensitively,
+	/// with runs of digits compared by their numeric value, so `file2` comes before `File10`.
+	/// Otherwise, names are compared byte-by-byte. Defaults to `false`.
+	///
+	/// Represented as a bool in the query string, with the key `natural`.
+	#[inline]
+	pub fn natural(&self) -> bool {
+		self.natural.unwrap_or(false)
+	}
+
+	/// Duplicates this query object,
+	/// updating the natural ordering flag with the specified value.
+	#[inline]
+	pub fn with_natural(self, natural: impl Into<Option<bool>>) -> Self {
+		Self {
+			natural: natural.into(),
+			..self
+		}
+	}
+
+	/// Only return entries of this type, if any.
+	///
+	/// Represented as a string in the query string, with the key `type`. See [`FileType`].
+	#[inline]
+	pub fn file_type(&self) -> Option<FileType> {
+		self.file_type
+	}
+
+	/// Duplicates this query object,
+	/// updating the type filter with the specified value.
+	#[inline]
+	pub fn with_file_type(self, file_type: impl Into<Option<FileType>>) -> Self {
+		Self {
+			file_type: file_type.into(),
+			..self
+		}
+	}
+
+	/// Only return entries whose name matches this glob pattern, if any.
+	/// Supports `*`, `?` and `[...]` character classes.
+	///
+	/// Represented as a string in the query string, with the key `glob`.
+	#[inline]
+	pub fn glob(&self) -> Option<&str> {
+		self.glob.as_deref()
+	}
+
+	/// Duplicates this query object,
+	/// updating the glob filter with the specified value.
+	#[inline]
+	pub fn with_glob(self, glob: impl Into<Option<String>>) -> Self {
+		Self {
+			glob: glob.into(),
+			..self
+		}
+	}
+
+	/// Whether hidden entries, whose names start with a `.`, are included. Defaults to `true`.
+	///
+	/// Represented as a bool in the query string, with the key `hidden`.
+	#[inline]
+	pub fn hidden(&self) -> bool {
+		self.hidden.unwrap_or(true)
+	}
+
+	/// Duplicates this query object,
+	/// updating the hidden entry flag with the specified value.
+	#[inline]
+	pub fn with_hidden(self, hidden: impl Into<Option<bool>>) -> Self {
+		Self {
+			hidden: hidden.into(),
+			..self
+		}
+	}
+
+	/// Returns if this query asks for a paginated listing,
+	/// meaning the response should be a [`DirectoryListingPage`](super::DirectoryListingPage).
+	#[inline]
+	pub fn is_paginated(&self) -> bool {
+		self.limit.is_some() || self.cursor.is_some()
+	}
+
+	/// Returns if this query can be answered by streaming entries as they are read,
+	/// meaning it doesn't ask for sorting or pagination.
+	#[inline]
+	pub fn is_streamable(&self) -> bool {
+		self.sort.is_none() && !self.is_paginated()
+	}
+
+	/// Builds a predicate which returns if an entry passes the filters in this query.
+	pub fn filter(&self) -> Result<impl Fn(&DirectoryListEntry) -> bool + '_, InvalidQuery> {
+		let glob = self
+			.glob()
+			.map(Pattern::new)
+			.transpose()
+			.map_err(|err| InvalidQuery::Glob(err.to_string()))?;
+		let hidden = self.hidden();
+		Ok(move |entry: &DirectoryListEntry| {
+			(hidden || !entry.name().starts_with('.'))
+				&& self.file_type.is_none_or(|ty| entry.file_type() == ty)
+				&& glob.as_ref().is_none_or(|glob| glob.matches(entry.name()))
+		})
+	}
+
+	/// Filters and sorts a directory listing according to this query.
+	///
+	/// This does not apply pagination, see [`DirectoryListingPage::paginate`](super::DirectoryListingPage::paginate) for that.
+	pub fn apply(
+		&self,
+		mut entries: Vec<DirectoryListEntry>,
+	) -> Result<Vec<DirectoryListEntry>, InvalidQuery> {
+		let filter = self.filter()?;
+		entries.retain(|entry| filter(entry));
+		if !self.is_streamable() {
+			entries
+				.sort_unstable_by(|a, b| self.compare(&SortPosition::of(a), &SortPosition::of(b)));
+		}
+		Ok(entries)
+	}
+
+	/// The sort key used for this listing, taking pagination into account.
+	pub(super) fn effective_sort(&self) -> SortKey {
+		self.sort.unwrap_or(SortKey::Name)
+	}
+
+	pub(super) fn compare(&self, a: &SortPosition, b: &SortPosition) -> Ordering {
+		let by_key = match self.effective_sort() {
+			SortKey::Name => Ordering::Equal,
+			SortKey::Size => a.size.cmp(&b.size),
+			SortKey::Updated => a.updated.cmp(&b.updated),
+			SortKey::Type => type_rank(a.file_type).cmp(&type_rank(b.file_type)),
+		};
+		let ordering = by_key
+			.then_with(|| {
+				if self.natural() {
+					natural_cmp(&a.name, &b.name)
+				} else {
+					Ordering::Equal
+				}
+			})
+			.then_with(|| a.name.cmp(&b.name));
+		match self.order() {
+			SortOrder::Asc => ordering,
+			SortOrder::Desc => ordering.reverse(),
+		}
+	}
+
+	/// A string identifying the parameters that affect the order of the listing,
+	/// so that cursors can't be reused across differently-sorted listings.
+	pub(super) fn ordering_tag(&self) -> String {
+		format!(
+			"{}-{}-{}",
+			self.effective_sort().as_str(),
+			match self.order() {
+				SortOrder::Asc => "asc",
+				SortOrder::Desc => "desc",
+			},
+			self.natural()
+		)
+	}
+}
+
+/// The parts of a [`DirectoryListEntry`] that it can be sorted by.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct SortPosition {
+	pub(super) name: String,
+	pub(super) file_type: FileType,
+	pub(super) size: u64,
+	pub(super) updated: Option<i128>,
+}
+
+impl SortPosition {
+	pub(super) fn of(entry: &DirectoryListEntry) -> Self {
+		Self {
+			name: entry.name().to_string(),
+			file_type: entry.file_type(),
+			size: entry.metadata().size(),
+			updated: entry
+				.metadata()
+				.updated()
+				.map(|updated| updated.unix_timestamp_nanos()),
+		}
+	}
+}
+
+fn type_rank(file_type: FileType) -> u8 {
+	match file_type {
+		FileType::Directory => 0,
+		FileType::File => 1,
+		FileType::Symlink => 2,
+	}
+}
+
+/// Compares two names case-insensitively, treating runs of digits as numbers.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+	let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+	loop {
+		match (a.peek().copied(), b.peek().copied()) {
+			(None, None) => return Ordering::Equal,
+			(None, Some(_)) => return Ordering::Less,
+			(Some(_), None) => return Ordering::Greater,
+			(Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+				let x = take_number(&mut a);
+				let y = take_number(&mut b);
+				let ordering = x.len().cmp(&y.len()).then_with(|| x.cmp(&y));
+				if ordering != Ordering::Equal {
+					return ordering;
+				}
+			}
+			(Some(x), Some(y)) => {
+				let ordering = x.to_lowercase().cmp(y.to_lowercase());
+				if ordering != Ordering::Equal {
+					return ordering;
+				}
+				a.next();
+				b.next();
+			}
+		}
+	}
+}
+
+/// Consumes a run of digits, returning them without leading zeros.
+fn take_number(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+	let mut number = String::new();
+	while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+		if !(number.is_empty() && digit == '0') {
+			number.push(digit);
+		}
+	}
+	number
+}
@@ -0,0 +1,216 @@
+use super::{write_entry, write_truncated, DirectoryListEntry};
+use crate::{
+	file::FileType,
+	metadata::{Metadata, NameCache},
+};
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+use std::{
+	fs::{self, DirEntry},
+	io::{self, Write},
+	path::Path,
+};
+
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+#[cfg(not(unix))]
+use std::path::PathBuf;
+
+/// The query parameters accepted by a recursive tree listing.
+#[skip_serializing_none]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TreeQuery {
+	depth: Option<u32>,
+	limit: Option<u64>,
+	follow_symlinks: Option<bool>,
+}
+
+impl TreeQuery {
+	/// The maximum depth to descend to, if any.
+	/// A depth of `1` only lists the direct children of the requested directory,
+	/// just like [`get_dir`](crate::api::get_dir) does.
+	///
+	/// Represented as a number in the query string, with the key `depth`.
+	#[inline]
+	pub fn depth(&self) -> Option<u32> {
+		self.depth
+	}
+
+	/// Duplicates this query object,
+	/// updating the maximum depth with the specified value.
+	#[inline]
+	pub fn with_depth(self, depth: impl Into<Option<u32>>) -> Self {
+		Self {
+			depth: depth.into(),
+			..self
+		}
+	}
+
+	/// The maximum amount of entries to return, if any.
+	/// A listing cut short by this limit ends with a [truncation marker](crate::dir::write_truncated).
+	///
+	/// Represented as a number in the query string, with the key `limit`.
+	#[inline]
+	pub fn limit(&self) -> Option<u64> {
+		self.limit
+	}
+
+	/// Duplicates this query object,
+	/// updating the entry limit with the specified value.
+	#[inline]
+	pub fn with_limit(self, limit: impl Into<Option<u64>>) -> Self {
+		Self {
+			limit: limit.into(),
+			..self
+		}
+	}
+
+	/// Whether symlinks to directories are descended into. Defaults to `false`.
+	///
+	/// Represented as a bool in the query string, with the key `follow_symlinks`.
+	#[inline]
+	pub fn follow_symlinks(&self) -> bool {
+		self.follow_symlinks.unwrap_or(false)
+	}
+
+	/// Duplicates this query object,
+	/// updating the symlink following flag with the specified value.
+	#[inline]
+	pub fn with_follow_symlinks(self, follow_symlinks: impl Into<Option<bool>>) -> Self {
+		Self {
+			follow_symlinks: follow_symlinks.into(),
+			..self
+		}
+	}
+
+	/// Lists the tree at `root` as described by this query, writing it to `writer` as a
+	/// [streamed tree listing](crate::api::get_tree#response), returning the amount of entries written.
+	///
+	/// Entries are listed depth-first, in name order. If `limit` is reached with entries left,
+	/// the listing ends with a [truncation marker](write_truncated). Directories already on the current path,
+	/// identified by device and inode number, are never descended into again, so symlink loops are listed only once.
+	///
+	/// An error is returned if `root` can't be read. Once the listing has started,
+	/// unreadable subdirectories are listed but not descended into, and entries whose metadata can't be read,
+	/// such as broken symlinks, are listed with empty metadata.
+	/// Owner and group names are looked up through `names`.
+	pub fn write_tree<P: AsRef<Path>, W: Write>(
+		&self,
+		root: P,
+		names: &NameCache,
+		writer: W,
+	) -> io::Result<u64> {
+		let root = root.as_ref();
+		let mut walk = TreeWalk {
+			query: self,
+			names,
+			writer,
+			written: 0,
+			ancestors: vec![DirKey::new(root, &fs::metadata(root)?)?],
+		};
+		walk.list(read_children(root)?, "", 1)?;
+		Ok(walk.written)
+	}
+}
+
+/// Identifies a directory, so it can be recognised when reached again through a symlink.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct DirKey {
+	#[cfg(unix)]
+	device: u64,
+	#[cfg(unix)]
+	inode: u64,
+	#[cfg(not(unix))]
+	path: PathBuf,
+}
+
+impl DirKey {
+	#[cfg_attr(unix, allow(unused_variables))]
+	pub(crate) fn new(path: &Path, metadata: &fs::Metadata) -> io::Result<Self> {
+		Ok(Self {
+			#[cfg(unix)]
+			device: metadata.dev(),
+			#[cfg(unix)]
+			inode: metadata.ino(),
+			#[cfg(not(unix))]
+			path: path.canonicalize()?,
+		})
+	}
+}
+
+struct TreeWalk<'a, W> {
+	query: &'a TreeQuery,
+	names: &'a NameCache,
+	writer: W,
+	written: u64,
+	/// The directories on the current path, starting with the root.
+	ancestors: Vec<DirKey>,
+}
+
+impl<W: Write> TreeWalk<'_, W> {
+	/// Lists `children`, which are at `depth` and inside the directory at `relative`, and their contents in turn.
+	/// Returns `false` once the limit has been reached, and the listing must stop.
+	fn list(&mut self, children: Vec<DirEntry>, relative: &str, depth: u32) -> io::Result<bool> {
+		for child in children {
+			if self.query.limit.is_some_and(|limit| self.written >= limit) {
+				write_truncated(&mut self.writer)?;
+				return Ok(false);
+			}
+			let Ok(file_type) = child.file_type().map(FileType::from) else {
+				continue;
+			};
+			let name = child.file_name().to_string_lossy().into_owned();
+			let path = if relative.is_empty() {
+				name.clone()
+			} else {
+				format!("{}/{}", relative, name)
+			};
+			let metadata = Metadata::from_path_cached(child.path(), self.names).unwrap_or_default();
+			write_entry(
+				&mut self.writer,
+				&DirectoryListEntry::default()
+					.with_name(name)
+					.with_path(path.clone())
+					.with_file_type(file_type)
+					.with_metadata(metadata),
+			)?;
+			self.written += 1;
+			if self.query.depth.is_some_and(|max| depth >= max) {
+				continue;
+			}
+			let target = match file_type {
+				FileType::Directory => child.metadata(),
+				FileType::Symlink if self.query.follow_symlinks() => fs::metadata(child.path()),
+				_ => continue,
+			};
+			let key = match target {
+				Ok(target) if target.is_dir() => DirKey::new(&child.path(), &target),
+				_ => continue,
+			};
+			let Ok(key) = key else {
+				continue;
+			};
+			if self.ancestors.contains(&key) {
+				continue;
+			}
+			let Ok(grandchildren) = read_children(&child.path()) else {
+				continue;
+			};
+			self.ancestors.push(key);
+			let more = self.list(grandchildren, &path, depth + 1)?;
+			self.ancestors.pop();
+			if !more {
+				return Ok(false);
+			}
+		}
+		Ok(true)
+	}
+}
+
+/// Reads the contents of `dir`, in name order.
+pub(crate) fn read_children(dir: &Path) -> io::Result<Vec<DirEntry>> {
+	let mut children = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+	children.sort_unstable_by_key(|child| child.file_name());
+	Ok(children)
+}
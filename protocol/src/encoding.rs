@@ -0,0 +1,600 @@
+//! Serde helpers for encodings shared between protocol types.
+
+use crate::format::{Format, NativeTimestamps};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ciborium::{tag::Required, value::Value};
+use serde::{
+	de::{value::EnumAccessDeserializer, EnumAccess, SeqAccess, Visitor},
+	ser::{
+		SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple,
+		SerializeTupleStruct, SerializeTupleVariant,
+	},
+	Deserialize, Deserializer, Serialize, Serializer,
+};
+use serde_with::{DeserializeAs, SerializeAs};
+use std::{collections::BTreeMap, fmt};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+/// Serializes bytes as a base64 string.
+pub(crate) fn serialize_base64<S: Serializer>(
+	data: &[u8],
+	serializer: S,
+) -> Result<S::Ok, S::Error> {
+	serializer.serialize_str(&STANDARD.encode(data))
+}
+
+/// Deserializes bytes from a base64 string.
+pub(crate) fn deserialize_base64<'de, D: Deserializer<'de>>(
+	deserializer: D,
+) -> Result<Vec<u8>, D::Error> {
+	let encoded = String::deserialize(deserializer)?;
+	STANDARD.decode(encoded).map_err(serde::de::Error::custom)
+}
+
+/// Serializes an optional map of bytes as an object of base64 strings.
+pub(crate) fn serialize_base64_map<S: Serializer>(
+	map: &Option<BTreeMap<String, Vec<u8>>>,
+	serializer: S,
+) -> Result<S::Ok, S::Error> {
+	match map {
+		Some(map) => {
+			serializer.collect_map(map.iter().map(|(key, value)| (key, STANDARD.encode(value))))
+		}
+		None => serializer.serialize_none(),
+	}
+}
+
+/// Deserializes an optional map of bytes from an object of base64 strings.
+pub(crate) fn deserialize_base64_map<'de, D: Deserializer<'de>>(
+	deserializer: D,
+) -> Result<Option<BTreeMap<String, Vec<u8>>>, D::Error> {
+	Option::<BTreeMap<String, String>>::deserialize(deserializer)?
+		.map(|map| {
+			map.into_iter()
+				.map(|(key, value)| {
+					Ok((
+						key,
+						STANDARD.decode(value).map_err(serde::de::Error::custom)?,
+					))
+				})
+				.collect()
+		})
+		.transpose()
+}
+
+/// The MessagePack extension type for timestamps.
+const MSGPACK_TIMESTAMP: i8 = -1;
+
+/// The CBOR tag for RFC 3339 date/time strings.
+const CBOR_DATE_TIME: u64 = 0;
+
+/// The CBOR tag for date/times as seconds since the Unix epoch.
+const CBOR_EPOCH: u64 = 1;
+
+/// The name of the newtype struct binary serializers see timestamps wrapped in,
+/// so a [`NativeSerializer`] can recognise them.
+const TIMESTAMP_STRUCT: &str = "$xfp::Timestamp";
+
+/// A `serde_with` adapter for timestamps.
+///
+/// Timestamps are always serialized as RFC 3339 strings, unless going through a [`NativeSerializer`],
+/// such as with [`NativeTimestamps`]. Binary serializers see them wrapped in a newtype struct,
+/// which they encode as just the string, so a [`NativeSerializer`] can tell them apart from other strings.
+///
+/// When deserializing binary formats, RFC 3339 strings, CBOR date/times (tagged or not),
+/// and MessagePack timestamps are all accepted, whichever format is in use.
+pub(crate) struct Timestamp;
+
+impl SerializeAs<OffsetDateTime> for Timestamp {
+	fn serialize_as<S: Serializer>(
+		time: &OffsetDateTime,
+		serializer: S,
+	) -> Result<S::Ok, S::Error> {
+		if serializer.is_human_readable() {
+			return Rfc3339::serialize_as(time, serializer);
+		}
+		let formatted = time.format(&Rfc3339).map_err(serde::ser::Error::custom)?;
+		serializer.serialize_newtype_struct(TIMESTAMP_STRUCT, &formatted)
+	}
+}
+
+impl<'de> DeserializeAs<'de, OffsetDateTime> for Timestamp {
+	fn deserialize_as<D: Deserializer<'de>>(deserializer: D) -> Result<OffsetDateTime, D::Error> {
+		if deserializer.is_human_readable() {
+			return Rfc3339::deserialize_as(deserializer);
+		}
+		deserializer.deserialize_any(TimestampVisitor)
+	}
+}
+
+struct TimestampVisitor;
+
+impl<'de> Visitor<'de> for TimestampVisitor {
+	type Value = OffsetDateTime;
+
+	fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str("an RFC 3339 string, a CBOR date/time, or a MessagePack timestamp")
+	}
+
+	fn visit_str<E: serde::de::Error>(self, text: &str) -> Result<Self::Value, E> {
+		OffsetDateTime::parse(text, &Rfc3339).map_err(E::custom)
+	}
+
+	fn visit_i64<E: serde::de::Error>(self, seconds: i64) -> Result<Self::Value, E> {
+		cbor_timestamp(Value::Integer(seconds.into())).map_err(E::custom)
+	}
+
+	fn visit_u64<E: serde::de::Error>(self, seconds: u64) -> Result<Self::Value, E> {
+		cbor_timestamp(Value::Integer(seconds.into())).map_err(E::custom)
+	}
+
+	fn visit_f64<E: serde::de::Error>(self, seconds: f64) -> Result<Self::Value, E> {
+		cbor_timestamp(Value::Float(seconds)).map_err(E::custom)
+	}
+
+	/// `ciborium` presents tagged values as enums.
+	fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+		match Value::deserialize(EnumAccessDeserializer::new(data))? {
+			Value::Tag(CBOR_DATE_TIME, value) | Value::Tag(CBOR_EPOCH, value) => {
+				cbor_timestamp(*value)
+			}
+			_ => Err("expected a CBOR date/time".to_string()),
+		}
+		.map_err(serde::de::Error::custom)
+	}
+
+	/// `rmp_serde` presents extension types as newtype structs.
+	fn visit_newtype_struct<D: Deserializer<'de>>(
+		self,
+		deserializer: D,
+	) -> Result<Self::Value, D::Error> {
+		MsgpackTimestampVisitor.visit_newtype_struct(deserializer)
+	}
+}
+
+/// Encodes a timestamp wrapped by [`Timestamp`] natively for `format`.
+fn native_timestamp<S: Serializer, T: Serialize + ?Sized>(
+	serializer: S,
+	format: Format,
+	value: &T,
+) -> Result<S::Ok, S::Error> {
+	let formatted = match Value::serialized(value) {
+		Ok(Value::Text(formatted)) => formatted,
+		_ => return Err(serde::ser::Error::custom("invalid timestamp")),
+	};
+	match format {
+		Format::Json => serializer.serialize_str(&formatted),
+		Format::Cbor => Required::<String, CBOR_DATE_TIME>(formatted).serialize(serializer),
+		Format::MessagePack => {
+			let time =
+				OffsetDateTime::parse(&formatted, &Rfc3339).map_err(serde::ser::Error::custom)?;
+			serializer.serialize_newtype_struct(
+				rmp_serde::MSGPACK_EXT_STRUCT_NAME,
+				&(MSGPACK_TIMESTAMP, RawBytes(&msgpack_timestamp(&time))),
+			)
+		}
+	}
+}
+
+/// A serializer which forwards everything to `inner`, except timestamps, which it encodes natively for `format`.
+///
+/// It also stands in for each of `inner`'s compound serializers, so timestamps nested anywhere are found.
+pub(crate) struct NativeSerializer<S> {
+	inner: S,
+	format: Format,
+}
+
+impl<S> NativeSerializer<S> {
+	pub(crate) fn new(inner: S, format: Format) -> Self {
+		Self { inner, format }
+	}
+
+	fn wrap<'a, T: ?Sized>(&self, value: &'a T) -> NativeTimestamps<'a, T> {
+		NativeTimestamps::new(self.format, value)
+	}
+}
+
+macro_rules! forward {
+	($($method:ident($type:ty)),* $(,)?) => {
+		$(
+			#[inline]
+			fn $method(self, value: $type) -> Result<Self::Ok, Self::Error> {
+				self.inner.$method(value)
+			}
+		)*
+	};
+}
+
+impl<S: Serializer> Serializer for NativeSerializer<S> {
+	type Ok = S::Ok;
+	type Error = S::Error;
+	type SerializeSeq = NativeSerializer<S::SerializeSeq>;
+	type SerializeTuple = NativeSerializer<S::SerializeTuple>;
+	type SerializeTupleStruct = NativeSerializer<S::SerializeTupleStruct>;
+	type SerializeTupleVariant = NativeSerializer<S::SerializeTupleVariant>;
+	type SerializeMap = NativeSerializer<S::SerializeMap>;
+	type SerializeStruct = NativeSerializer<S::SerializeStruct>;
+	type SerializeStructVariant = NativeSerializer<S::SerializeStructVariant>;
+
+	forward!(
+		serialize_bool(bool),
+		serialize_i8(i8),
+		serialize_i16(i16),
+		serialize_i32(i32),
+		serialize_i64(i64),
+		serialize_i128(i128),
+		serialize_u8(u8),
+		serialize_u16(u16),
+		serialize_u32(u32),
+		serialize_u64(u64),
+		serialize_u128(u128),
+		serialize_f32(f32),
+		serialize_f64(f64),
+		serialize_char(char),
+		serialize_str(&str),
+		serialize_bytes(&[u8]),
+		serialize_unit_struct(&'static str),
+	);
+
+	#[inline]
+	fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+		self.inner.serialize_none()
+	}
+
+	fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Self::Ok, Self::Error> {
+		let value = self.wrap(value);
+		self.inner.serialize_some(&value)
+	}
+
+	#[inline]
+	fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+		self.inner.serialize_unit()
+	}
+
+	fn serialize_unit_variant(
+		self,
+		name: &'static str,
+		variant_index: u32,
+		variant: &'static str,
+	) -> Result<Self::Ok, Self::Error> {
+		self.inner
+			.serialize_unit_variant(name, variant_index, variant)
+	}
+
+	fn serialize_newtype_struct<T: Serialize + ?Sized>(
+		self,
+		name: &'static str,
+		value: &T,
+	) -> Result<Self::Ok, Self::Error> {
+		if name == TIMESTAMP_STRUCT {
+			return native_timestamp(self.inner, self.format, value);
+		}
+		let value = self.wrap(value);
+		self.inner.serialize_newtype_struct(name, &value)
+	}
+
+	fn serialize_newtype_variant<T: Serialize + ?Sized>(
+		self,
+		name: &'static str,
+		variant_index: u32,
+		variant: &'static str,
+		value: &T,
+	) -> Result<Self::Ok, Self::Error> {
+		let value = self.wrap(value);
+		self.inner
+			.serialize_newtype_variant(name, variant_index, variant, &value)
+	}
+
+	fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+		Ok(NativeSerializer::new(
+			self.inner.serialize_seq(len)?,
+			self.format,
+		))
+	}
+
+	fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+		Ok(NativeSerializer::new(
+			self.inner.serialize_tuple(len)?,
+			self.format,
+		))
+	}
+
+	fn serialize_tuple_struct(
+		self,
+		name: &'static str,
+		len: usize,
+	) -> Result<Self::SerializeTupleStruct, Self::Error> {
+		Ok(NativeSerializer::new(
+			self.inner.serialize_tuple_struct(name, len)?,
+			self.format,
+		))
+	}
+
+	fn serialize_tuple_variant(
+		self,
+		name: &'static str,
+		variant_index: u32,
+		variant: &'static str,
+		len: usize,
+	) -> Result<Self::SerializeTupleVariant, Self::Error> {
+		Ok(NativeSerializer::new(
+			self.inner
+				.serialize_tuple_variant(name, variant_index, variant, len)?,
+			self.format,
+		))
+	}
+
+	fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+		Ok(NativeSerializer::new(
+			self.inner.serialize_map(len)?,
+			self.format,
+		))
+	}
+
+	fn serialize_struct(
+		self,
+		name: &'static str,
+		len: usize,
+	) -> Result<Self::SerializeStruct, Self::Error> {
+		Ok(NativeSerializer::new(
+			self.inner.serialize_struct(name, len)?,
+			self.format,
+		))
+	}
+
+	fn serialize_struct_variant(
+		self,
+		name: &'static str,
+		variant_index: u32,
+		variant: &'static str,
+		len: usize,
+	) -> Result<Self::SerializeStructVariant, Self::Error> {
+		Ok(NativeSerializer::new(
+			self.inner
+				.serialize_struct_variant(name, variant_index, variant, len)?,
+			self.format,
+		))
+	}
+
+	#[inline]
+	fn is_human_readable(&self) -> bool {
+		self.inner.is_human_readable()
+	}
+}
+
+impl<S: SerializeSeq> SerializeSeq for NativeSerializer<S> {
+	type Ok = S::Ok;
+	type Error = S::Error;
+
+	fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), S::Error> {
+		let value = self.wrap(value);
+		self.inner.serialize_element(&value)
+	}
+
+	fn end(self) -> Result<S::Ok, S::Error> {
+		self.inner.end()
+	}
+}
+
+impl<S: SerializeTuple> SerializeTuple for NativeSerializer<S> {
+	type Ok = S::Ok;
+	type Error = S::Error;
+
+	fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), S::Error> {
+		let value = self.wrap(value);
+		self.inner.serialize_element(&value)
+	}
+
+	fn end(self) -> Result<S::Ok, S::Error> {
+		self.inner.end()
+	}
+}
+
+impl<S: SerializeTupleStruct> SerializeTupleStruct for NativeSerializer<S> {
+	type Ok = S::Ok;
+	type Error = S::Error;
+
+	fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), S::Error> {
+		let value = self.wrap(value);
+		self.inner.serialize_field(&value)
+	}
+
+	fn end(self) -> Result<S::Ok, S::Error> {
+		self.inner.end()
+	}
+}
+
+impl<S: SerializeTupleVariant> SerializeTupleVariant for NativeSerializer<S> {
+	type Ok = S::Ok;
+	type Error = S::Error;
+
+	fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), S::Error> {
+		let value = self.wrap(value);
+		self.inner.serialize_field(&value)
+	}
+
+	fn end(self) -> Result<S::Ok, S::Error> {
+		self.inner.end()
+	}
+}
+
+impl<S: SerializeMap> SerializeMap for NativeSerializer<S> {
+	type Ok = S::Ok;
+	type Error = S::Error;
+
+	fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), S::Error> {
+		let key = self.wrap(key);
+		self.inner.serialize_key(&key)
+	}
+
+	fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), S::Error> {
+		let value = self.wrap(value);
+		self.inner.serialize_value(&value)
+	}
+
+	fn end(self) -> Result<S::Ok, S::Error> {
+		self.inner.end()
+	}
+}
+
+impl<S: SerializeStruct> SerializeStruct for NativeSerializer<S> {
+	type Ok = S::Ok;
+	type Error = S::Error;
+
+	fn serialize_field<T: Serialize + ?Sized>(
+		&mut self,
+		key: &'static str,
+		value: &T,
+	) -> Result<(), S::Error> {
+		let value = self.wrap(value);
+		self.inner.serialize_field(key, &value)
+	}
+
+	fn skip_field(&mut self, key: &'static str) -> Result<(), S::Error> {
+		self.inner.skip_field(key)
+	}
+
+	fn end(self) -> Result<S::Ok, S::Error> {
+		self.inner.end()
+	}
+}
+
+impl<S: SerializeStructVariant> SerializeStructVariant for NativeSerializer<S> {
+	type Ok = S::Ok;
+	type Error = S::Error;
+
+	fn serialize_field<T: Serialize + ?Sized>(
+		&mut self,
+		key: &'static str,
+		value: &T,
+	) -> Result<(), S::Error> {
+		let value = self.wrap(value);
+		self.inner.serialize_field(key, &value)
+	}
+
+	fn skip_field(&mut self, key: &'static str) -> Result<(), S::Error> {
+		self.inner.skip_field(key)
+	}
+
+	fn end(self) -> Result<S::Ok, S::Error> {
+		self.inner.end()
+	}
+}
+
+fn cbor_timestamp(value: Value) -> Result<OffsetDateTime, String> {
+	match value {
+		Value::Text(text) => OffsetDateTime::parse(&text, &Rfc3339).map_err(|err| err.to_string()),
+		Value::Integer(seconds) => i64::try_from(seconds)
+			.map_err(|err| err.to_string())
+			.and_then(|seconds| {
+				OffsetDateTime::from_unix_timestamp(seconds).map_err(|err| err.to_string())
+			}),
+		// Casting saturates, so values too large for a timestamp still fail below, but NaN would silently become the epoch.
+		Value::Float(seconds) if seconds.is_finite() => {
+			OffsetDateTime::from_unix_timestamp_nanos((seconds * 1e9) as i128)
+				.map_err(|err| err.to_string())
+		}
+		Value::Float(_) => Err("CBOR date/time is not a finite number".to_string()),
+		_ => Err("expected a CBOR date/time".to_string()),
+	}
+}
+
+/// Encodes a timestamp in the smallest of MessagePack's timestamp 32, 64 and 96 formats that can hold it.
+fn msgpack_timestamp(time: &OffsetDateTime) -> Vec<u8> {
+	let seconds = time.unix_timestamp();
+	let nanos = time.nanosecond();
+	if nanos == 0 && u32::try_from(seconds).is_ok() {
+		(seconds as u32).to_be_bytes().to_vec()
+	} else if (0..1 << 34).contains(&seconds) {
+		((u64::from(nanos) << 34) | seconds as u64)
+			.to_be_bytes()
+			.to_vec()
+	} else {
+		let mut data = nanos.to_be_bytes().to_vec();
+		data.extend_from_slice(&seconds.to_be_bytes());
+		data
+	}
+}
+
+struct RawBytes<'a>(&'a [u8]);
+
+impl Serialize for RawBytes<'_> {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_bytes(self.0)
+	}
+}
+
+struct MsgpackTimestampVisitor;
+
+impl<'de> Visitor<'de> for MsgpackTimestampVisitor {
+	type Value = OffsetDateTime;
+
+	fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str("a MessagePack timestamp")
+	}
+
+	fn visit_newtype_struct<D: Deserializer<'de>>(
+		self,
+		deserializer: D,
+	) -> Result<Self::Value, D::Error> {
+		deserializer.deserialize_tuple(2, self)
+	}
+
+	fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+		let invalid = || serde::de::Error::custom("invalid MessagePack timestamp");
+		let kind: i8 = seq.next_element()?.ok_or_else(invalid)?;
+		let data: ByteBuf = seq.next_element()?.ok_or_else(invalid)?;
+		if kind != MSGPACK_TIMESTAMP {
+			return Err(invalid());
+		}
+		let (seconds, nanos) = match data.0.len() {
+			4 => (
+				i64::from(u32::from_be_bytes(data.0[..].try_into().unwrap())),
+				0,
+			),
+			8 => {
+				let value = u64::from_be_bytes(data.0[..].try_into().unwrap());
+				((value & ((1 << 34) - 1)) as i64, (value >> 34) as u32)
+			}
+			12 => (
+				i64::from_be_bytes(data.0[4..].try_into().unwrap()),
+				u32::from_be_bytes(data.0[..4].try_into().unwrap()),
+			),
+			_ => return Err(invalid()),
+		};
+		OffsetDateTime::from_unix_timestamp_nanos(
+			i128::from(seconds) * 1_000_000_000 + i128::from(nanos),
+		)
+		.map_err(serde::de::Error::custom)
+	}
+}
+
+struct ByteBuf(Vec<u8>);
+
+impl<'de> Deserialize<'de> for ByteBuf {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		struct BytesVisitor;
+
+		impl<'de> Visitor<'de> for BytesVisitor {
+			type Value = ByteBuf;
+
+			fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+				f.write_str("bytes")
+			}
+
+			fn visit_bytes<E: serde::de::Error>(self, bytes: &[u8]) -> Result<Self::Value, E> {
+				Ok(ByteBuf(bytes.to_vec()))
+			}
+
+			fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+				let mut bytes = Vec::new();
+				while let Some(byte) = seq.next_element()? {
+					bytes.push(byte);
+				}
+				Ok(ByteBuf(bytes))
+			}
+		}
+
+		deserializer.deserialize_bytes(BytesVisitor)
+	}
+}
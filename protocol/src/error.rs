@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+/// The JSON object returned alongside every error status code.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ErrorResponse {
+	description: String,
+}
+
+impl ErrorResponse {
+	/// Creates an error response with the given description.
+	#[inline]
+	pub fn new(description: impl ToString) -> Self {
+		Self {
+			description: description.to_string(),
+		}
+	}
+
+	/// A human-readable description of what went wrong,
+	/// in more detail than just the status code.
+	///
+	/// Represented as a string in JSON, with the key `description`.
+	#[inline]
+	pub fn description(&self) -> &str {
+		&self.description
+	}
+
+	/// Duplicates this error response,
+	/// setting the description to `description`.
+	#[inline]
+	pub fn with_description(self, description: impl ToString) -> Self {
+		Self {
+			description: description.to_string(),
+		}
+	}
+}
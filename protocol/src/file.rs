@@ -1,7 +1,14 @@
 use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// The HTTP header used to report the [`FileType`] of the object at the requested path.
+///
+/// Its value is the same string that the [`FileType`] is represented as in JSON.
+pub const TYPE_HEADER: &str = "X-XFP-Type";
 
 /// The type of thing an object is.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "lowercase")]
 pub enum FileType {
 	/// This object is a file.
@@ -18,9 +25,68 @@ pub enum FileType {
 	Symlink,
 }
 
+impl FileType {
+	/// The string this file type is represented as,
+	/// both in JSON and in the [`X-XFP-Type`](TYPE_HEADER) header.
+	#[inline]
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			FileType::File => "file",
+			FileType::Directory => "directory",
+			FileType::Symlink => "symlink",
+		}
+	}
+}
+
 impl Default for FileType {
 	#[inline]
 	fn default() -> Self {
 		FileType::File
 	}
 }
+
+impl fmt::Display for FileType {
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(self.as_str())
+	}
+}
+
+/// The error returned when parsing an unknown [`FileType`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownFileType(pub String);
+
+impl fmt::Display for UnknownFileType {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "unknown file type '{}'", self.0)
+	}
+}
+
+impl std::error::Error for UnknownFileType {}
+
+impl FromStr for FileType {
+	type Err = UnknownFileType;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"file" => Ok(FileType::File),
+			"directory" => Ok(FileType::Directory),
+			"symlink" => Ok(FileType::Symlink),
+			_ => Err(UnknownFileType(s.to_string())),
+		}
+	}
+}
+
+impl From<std::fs::FileType> for FileType {
+	/// Converts a file type read from the local filesystem, without following symlinks.
+	/// Anything that isn't a directory or symlink, such as a device or socket, is treated as a file.
+	fn from(file_type: std::fs::FileType) -> Self {
+		if file_type.is_symlink() {
+			FileType::Symlink
+		} else if file_type.is_dir() {
+			FileType::Directory
+		} else {
+			FileType::File
+		}
+	}
+}
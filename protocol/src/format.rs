@@ -0,0 +1,306 @@
+use crate::{dir::NDJSON_CONTENT_TYPE, encoding::NativeSerializer};
+use serde::{de::DeserializeOwned, Serialize, Serializer};
+use std::fmt;
+
+/// The value of the `Content-Type` header for CBOR bodies.
+pub const CBOR_CONTENT_TYPE: &str = "application/cbor";
+
+/// The value of the `Content-Type` header for MessagePack bodies.
+pub const MSGPACK_CONTENT_TYPE: &str = "application/msgpack";
+
+/// The value of the `Content-Type` header for JSON bodies.
+pub const JSON_CONTENT_TYPE: &str = "application/json";
+
+/// Every media type MessagePack is known by.
+const MSGPACK_MEDIA_TYPES: &[&str] = &[
+	MSGPACK_CONTENT_TYPE,
+	"application/x-msgpack",
+	"application/vnd.msgpack",
+];
+
+/// The error returned when a body can't be encoded or decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatError {
+	/// The value could not be encoded.
+	Encode(String),
+	/// The body could not be decoded.
+	Decode(String),
+}
+
+impl fmt::Display for FormatError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			FormatError::Encode(err) => write!(f, "failed to encode body: {}", err),
+			FormatError::Decode(err) => write!(f, "failed to decode body: {}", err),
+		}
+	}
+}
+
+impl std::error::Error for FormatError {}
+
+/// A format a response body can be encoded in, chosen by the client with the `Accept` header.
+///
+/// Every format uses the same serde types. [`encode`](Self::encode) encodes timestamps natively in the binary formats:
+/// CBOR as an RFC 3339 string with tag 0, and MessagePack with the timestamp extension type.
+/// Serializing with `ciborium` or `rmp_serde` directly encodes them as plain RFC 3339 strings,
+/// unless the value is wrapped in [`NativeTimestamps`]. Either form is accepted when decoding, however it's done.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Format {
+	/// JSON, with a `Content-Type` of `application/json`.
+	Json,
+	/// CBOR, with a `Content-Type` of `application/cbor`.
+	Cbor,
+	/// MessagePack, with a `Content-Type` of `application/msgpack`.
+	MessagePack,
+}
+
+impl Default for Format {
+	#[inline]
+	fn default() -> Self {
+		Format::Json
+	}
+}
+
+impl Format {
+	/// The value of the `Content-Type` header for bodies of this format.
+	#[inline]
+	pub fn content_type(&self) -> &'static str {
+		match self {
+			Format::Json => JSON_CONTENT_TYPE,
+			Format::Cbor => CBOR_CONTENT_TYPE,
+			Format::MessagePack => MSGPACK_CONTENT_TYPE,
+		}
+	}
+
+	/// Returns the format with the given media type, ignoring any parameters,
+	/// or `None` if it isn't one of the supported formats.
+	///
+	/// Clients should use this on the `Content-Type` of a response to pick how to [`decode`](Self::decode) it.
+	pub fn from_content_type(content_type: &str) -> Option<Self> {
+		let media_type = content_type.split(';').next().unwrap_or_default().trim();
+		let is = |name: &str| media_type.eq_ignore_ascii_case(name);
+		if is(JSON_CONTENT_TYPE) {
+			Some(Format::Json)
+		} else if is(CBOR_CONTENT_TYPE) {
+			Some(Format::Cbor)
+		} else if MSGPACK_MEDIA_TYPES.iter().any(|name| is(name)) {
+			Some(Format::MessagePack)
+		} else {
+			None
+		}
+	}
+
+	/// Picks the format to respond with, given the value of the request's `Accept` header.
+	///
+	/// Each format gets the quality value of the most specific range matching it,
+	/// so `application/json;q=0, */*` rules out JSON but accepts the others.
+	/// The format with the highest quality value wins, or the one whose range is listed first on ties,
+	/// with wildcards preferring JSON. Ranges with an invalid quality value are ignored.
+	///
+	/// Returns the default format if the header is empty, and `None` if the client accepts none of the formats,
+	/// in which case the server should respond with `406 Not Acceptable`.
+	/// Directory listings can also be streamed, see [`ListingFormat::negotiate`].
+	pub fn negotiate(accept: &str) -> Option<Self> {
+		negotiate(
+			accept,
+			&[
+				(Format::Json, &[JSON_CONTENT_TYPE]),
+				(Format::Cbor, &[CBOR_CONTENT_TYPE]),
+				(Format::MessagePack, MSGPACK_MEDIA_TYPES),
+			],
+		)
+	}
+
+	/// Encodes `value` in this format, with timestamps encoded natively.
+	pub fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, FormatError> {
+		let encode = |err: &dyn fmt::Display| FormatError::Encode(err.to_string());
+		let value = NativeTimestamps::new(*self, value);
+		match self {
+			Format::Json => serde_json::to_vec(&value).map_err(|err| encode(&err)),
+			Format::Cbor => {
+				let mut body = Vec::new();
+				ciborium::ser::into_writer(&value, &mut body).map_err(|err| encode(&err))?;
+				Ok(body)
+			}
+			Format::MessagePack => rmp_serde::to_vec_named(&value).map_err(|err| encode(&err)),
+		}
+	}
+
+	/// Decodes a body of this format.
+	pub fn decode<T: DeserializeOwned>(&self, body: &[u8]) -> Result<T, FormatError> {
+		let decode = |err: &dyn fmt::Display| FormatError::Decode(err.to_string());
+		match self {
+			Format::Json => serde_json::from_slice(body).map_err(|err| decode(&err)),
+			Format::Cbor => ciborium::de::from_reader(body).map_err(|err| decode(&err)),
+			Format::MessagePack => rmp_serde::from_slice(body).map_err(|err| decode(&err)),
+		}
+	}
+}
+
+/// Serializes a value with its timestamps encoded natively for a binary format:
+/// as RFC 3339 strings with tag 0 in CBOR, and with the timestamp extension type in MessagePack.
+///
+/// [`Format::encode`] already does this. It's only needed when serializing with `ciborium` or `rmp_serde` directly,
+/// such as to stream a body, and the format must be the one actually being serialized to.
+/// Timestamps are plain RFC 3339 strings without it, and for [`Format::Json`].
+#[derive(Debug)]
+pub struct NativeTimestamps<'a, T: ?Sized> {
+	format: Format,
+	value: &'a T,
+}
+
+impl<'a, T: ?Sized> NativeTimestamps<'a, T> {
+	/// Wraps `value`, to be serialized in `format`.
+	#[inline]
+	pub fn new(format: Format, value: &'a T) -> Self {
+		Self { format, value }
+	}
+}
+
+impl<T: Serialize + ?Sized> Serialize for NativeTimestamps<'_, T> {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		self.value
+			.serialize(NativeSerializer::new(serializer, self.format))
+	}
+}
+
+/// How a [directory listing](crate::api::get_dir) is sent, chosen by the client with the `Accept` header.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ListingFormat {
+	/// The whole listing in a single body of the given format.
+	Body(Format),
+	/// One entry per line of newline-delimited JSON, with a `Content-Type` of `application/x-ndjson`.
+	/// See [streaming](crate::api::get_dir#streaming).
+	Ndjson,
+}
+
+impl Default for ListingFormat {
+	#[inline]
+	fn default() -> Self {
+		ListingFormat::Body(Format::default())
+	}
+}
+
+impl ListingFormat {
+	/// The value of the `Content-Type` header for listings sent this way.
+	#[inline]
+	pub fn content_type(&self) -> &'static str {
+		match self {
+			ListingFormat::Body(format) => format.content_type(),
+			ListingFormat::Ndjson => NDJSON_CONTENT_TYPE,
+		}
+	}
+
+	/// Picks how to send a listing, given the value of the request's `Accept` header.
+	///
+	/// This works like [`Format::negotiate`], with streaming as one more candidate.
+	/// Wildcards prefer JSON bodies, so streaming has to be asked for by name.
+	pub fn negotiate(accept: &str) -> Option<Self> {
+		negotiate(
+			accept,
+			&[
+				(ListingFormat::Body(Format::Json), &[JSON_CONTENT_TYPE]),
+				(ListingFormat::Body(Format::Cbor), &[CBOR_CONTENT_TYPE]),
+				(
+					ListingFormat::Body(Format::MessagePack),
+					MSGPACK_MEDIA_TYPES,
+				),
+				(ListingFormat::Ndjson, &[NDJSON_CONTENT_TYPE]),
+			],
+		)
+	}
+}
+
+/// A media range from an `Accept` header, such as `application/*;q=0.5`.
+struct MediaRange<'a> {
+	media_type: &'a str,
+	quality: f32,
+}
+
+impl<'a> MediaRange<'a> {
+	/// Parses one comma-separated element of an `Accept` header,
+	/// returning `None` if it's empty or its quality value is invalid.
+	fn parse(range: &'a str) -> Option<Self> {
+		let mut parts = range.split(';');
+		let media_type = parts.next().unwrap_or_default().trim();
+		if media_type.is_empty() {
+			return None;
+		}
+		let mut quality = 1.0;
+		for param in parts {
+			match param.split_once('=') {
+				Some((key, value)) if key.trim().eq_ignore_ascii_case("q") => {
+					quality = parse_quality(value.trim())?;
+				}
+				_ => {}
+			}
+		}
+		Some(Self {
+			media_type,
+			quality,
+		})
+	}
+
+	/// How specifically this range matches `media_type`: 2 for the exact type, 1 for `type/*`, and 0 for `*/*`.
+	/// Returns `None` if it doesn't match.
+	fn specificity(&self, media_type: &str) -> Option<u8> {
+		if self.media_type == "*/*" {
+			return Some(0);
+		}
+		let (main_type, subtype) = self.media_type.split_once('/')?;
+		if subtype == "*" {
+			let (candidate_type, _) = media_type.split_once('/')?;
+			return main_type.eq_ignore_ascii_case(candidate_type).then_some(1);
+		}
+		self.media_type
+			.eq_ignore_ascii_case(media_type)
+			.then_some(2)
+	}
+}
+
+/// Parses a quality value, which has at most three decimal places and is between 0 and 1.
+fn parse_quality(value: &str) -> Option<f32> {
+	let (whole, fraction) = value.split_once('.').unwrap_or((value, ""));
+	let valid = matches!(whole, "0" | "1")
+		&& fraction.len() <= 3
+		&& fraction.bytes().all(|digit| digit.is_ascii_digit());
+	let quality = value.parse::<f32>().ok().filter(|_| valid)?;
+	(quality <= 1.0).then_some(quality)
+}
+
+/// Picks the candidate with the highest quality value in `accept`, as described in [`Format::negotiate`].
+/// Candidates are listed with every media type they're known by, in order of preference for wildcards.
+fn negotiate<T: Copy>(accept: &str, candidates: &[(T, &[&str])]) -> Option<T> {
+	if accept.trim().is_empty() {
+		return candidates.first().map(|(candidate, _)| *candidate);
+	}
+	let ranges = accept
+		.split(',')
+		.filter_map(MediaRange::parse)
+		.collect::<Vec<_>>();
+	let mut best: Option<(T, f32, usize)> = None;
+	for (candidate, media_types) in candidates {
+		// The most specific matching range decides, with earlier ranges winning between equally specific ones.
+		let matched = ranges
+			.iter()
+			.enumerate()
+			.filter_map(|(index, range)| {
+				let specificity = media_types
+					.iter()
+					.filter_map(|media_type| range.specificity(media_type))
+					.max()?;
+				Some((specificity, index, range.quality))
+			})
+			.min_by_key(|(specificity, index, _)| (u8::MAX - specificity, *index));
+		let Some((_, index, quality)) = matched else {
+			continue;
+		};
+		let better = best.is_none_or(|(_, best_quality, best_index)| {
+			quality > best_quality || (quality >= best_quality && index < best_index)
+		});
+		if quality > 0.0 && better {
+			best = Some((*candidate, quality, index));
+		}
+	}
+	best.map(|(candidate, ..)| candidate)
+}
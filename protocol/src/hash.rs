@@ -0,0 +1,226 @@
+mod cache;
+
+pub use self::cache::HashCache;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use md5::Md5;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+	fmt::{self, Write as _},
+	fs::File,
+	io::{self, Read},
+	path::Path,
+};
+
+/// A hashing algorithm supported by xfp.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+	/// SHA-256.
+	///
+	/// Represented as the string `sha256` in JSON, and `sha-256` in digest headers.
+	Sha256,
+	/// BLAKE3, with the default 256-bit output.
+	///
+	/// Represented as the string `blake3` in JSON and in digest headers.
+	Blake3,
+	/// MD5. This is not secure, and should only be used for compatibility.
+	///
+	/// Represented as the string `md5` in JSON and in digest headers.
+	Md5,
+}
+
+impl HashAlgorithm {
+	/// The name of this algorithm in the `Repr-Digest` and `Want-Repr-Digest` headers.
+	#[inline]
+	pub fn digest_name(&self) -> &'static str {
+		match self {
+			HashAlgorithm::Sha256 => "sha-256",
+			HashAlgorithm::Blake3 => "blake3",
+			HashAlgorithm::Md5 => "md5",
+		}
+	}
+
+	/// The length of this algorithm's digests, in bytes.
+	#[inline]
+	pub fn digest_len(&self) -> usize {
+		match self {
+			HashAlgorithm::Sha256 | HashAlgorithm::Blake3 => 32,
+			HashAlgorithm::Md5 => 16,
+		}
+	}
+
+	/// Picks the algorithm the client prefers the most out of a `Want-Repr-Digest` header,
+	/// ignoring any algorithms which aren't supported.
+	///
+	/// Returns `None` if no supported algorithm has a non-zero preference.
+	pub fn from_want_digest(header: &str) -> Option<Self> {
+		header
+			.split(',')
+			.filter_map(|item| {
+				let (name, weight) = item.split_once('=')?;
+				let algorithm = match name.trim().to_ascii_lowercase().as_str() {
+					"sha-256" => HashAlgorithm::Sha256,
+					"blake3" => HashAlgorithm::Blake3,
+					"md5" => HashAlgorithm::Md5,
+					_ => return None,
+				};
+				let weight = weight
+					.trim()
+					.parse::<u8>()
+					.ok()
+					.filter(|weight| *weight > 0)?;
+				Some((weight, algorithm))
+			})
+			.max_by_key(|(weight, _)| *weight)
+			.map(|(_, algorithm)| algorithm)
+	}
+}
+
+impl Default for HashAlgorithm {
+	#[inline]
+	fn default() -> Self {
+		HashAlgorithm::Sha256
+	}
+}
+
+/// The query parameters accepted by the hash endpoint.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct HashQuery {
+	#[serde(default)]
+	algo: Option<HashAlgorithm>,
+}
+
+impl HashQuery {
+	/// The algorithm to hash the file with. Defaults to [`HashAlgorithm::Sha256`].
+	///
+	/// Represented as either `sha256`, `blake3`, or `md5` in the query string, with the key `algo`.
+	#[inline]
+	pub fn algo(&self) -> HashAlgorithm {
+		self.algo.unwrap_or_default()
+	}
+
+	/// Duplicates this query object,
+	/// updating the algorithm with the specified value.
+	#[inline]
+	pub fn with_algo(self, algo: impl Into<Option<HashAlgorithm>>) -> Self {
+		Self { algo: algo.into() }
+	}
+}
+
+/// The hash of a file's contents.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FileHash {
+	algorithm: HashAlgorithm,
+	hash: String,
+}
+
+impl FileHash {
+	/// Hashes everything read from `reader` with the given algorithm.
+	pub fn from_reader<R: Read>(mut reader: R, algorithm: HashAlgorithm) -> io::Result<Self> {
+		let mut buffer = vec![0; 64 * 1024];
+		let digest = match algorithm {
+			HashAlgorithm::Sha256 => hash_with(&mut reader, &mut buffer, Sha256::new())?,
+			HashAlgorithm::Md5 => hash_with(&mut reader, &mut buffer, Md5::new())?,
+			HashAlgorithm::Blake3 => {
+				let mut hasher = blake3::Hasher::new();
+				read_chunks(&mut reader, &mut buffer, |chunk| {
+					hasher.update(chunk);
+				})?;
+				hasher.finalize().as_bytes().to_vec()
+			}
+		};
+		let hash = digest.iter().fold(String::new(), |mut out, byte| {
+			let _ = write!(out, "{:02x}", byte);
+			out
+		});
+		Ok(Self { algorithm, hash })
+	}
+
+	/// Hashes the contents of the file at `path` with the given algorithm.
+	#[inline]
+	pub fn from_path<P: AsRef<Path>>(path: P, algorithm: HashAlgorithm) -> io::Result<Self> {
+		Self::from_reader(File::open(path)?, algorithm)
+	}
+
+	/// The algorithm used to create this hash.
+	///
+	/// Represented as a string in JSON, with the key `algorithm`. See [`HashAlgorithm`].
+	#[inline]
+	pub fn algorithm(&self) -> HashAlgorithm {
+		self.algorithm
+	}
+
+	/// The hash itself, as a lowercase hexadecimal string.
+	///
+	/// Represented as a string in JSON, with the key `hash`.
+	#[inline]
+	pub fn hash(&self) -> &str {
+		&self.hash
+	}
+
+	/// Formats this hash as the value of a `Repr-Digest` header,
+	/// such as `sha-256=:RK/0qy18MlBSVnWgjwz6lZEWjP/lF5HF9bvEF8FabDg=:`.
+	///
+	/// Fails if the hash isn't a hexadecimal digest of the right length for its algorithm,
+	/// which can only happen for hashes that were deserialized rather than computed.
+	pub fn repr_digest(&self) -> Result<String, InvalidHash> {
+		if self.hash.len() != self.algorithm.digest_len() * 2 {
+			return Err(InvalidHash);
+		}
+		let bytes = (0..self.hash.len())
+			.step_by(2)
+			.map(|idx| {
+				let pair = self.hash.get(idx..idx + 2).ok_or(InvalidHash)?;
+				if !pair.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+					return Err(InvalidHash);
+				}
+				u8::from_str_radix(pair, 16).map_err(|_| InvalidHash)
+			})
+			.collect::<Result<Vec<u8>, _>>()?;
+		Ok(format!(
+			"{}=:{}:",
+			self.algorithm.digest_name(),
+			STANDARD.encode(bytes)
+		))
+	}
+}
+
+/// The error returned when formatting a [`FileHash`] whose hash isn't a valid digest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidHash;
+
+impl fmt::Display for InvalidHash {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("invalid hash digest")
+	}
+}
+
+impl std::error::Error for InvalidHash {}
+
+fn hash_with<R: Read, D: Digest>(
+	reader: &mut R,
+	buffer: &mut [u8],
+	mut digest: D,
+) -> io::Result<Vec<u8>> {
+	read_chunks(reader, buffer, |chunk| digest.update(chunk))?;
+	Ok(digest.finalize().to_vec())
+}
+
+fn read_chunks<R: Read>(
+	reader: &mut R,
+	buffer: &mut [u8],
+	mut f: impl FnMut(&[u8]),
+) -> io::Result<()> {
+	loop {
+		match reader.read(buffer) {
+			Ok(0) => return Ok(()),
+			Ok(len) => f(&buffer[..len]),
+			Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+			Err(err) => return Err(err),
+		}
+	}
+}
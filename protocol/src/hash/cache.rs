@@ -0,0 +1,134 @@
+use super::{FileHash, HashAlgorithm};
+use std::{
+	collections::HashMap,
+	fs::{File, Metadata},
+	io,
+	path::Path,
+	sync::Mutex,
+	time::SystemTime,
+};
+
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+#[cfg(not(unix))]
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+	#[cfg(unix)]
+	device: u64,
+	#[cfg(unix)]
+	inode: u64,
+	#[cfg(not(unix))]
+	path: PathBuf,
+	modified: Option<SystemTime>,
+	size: u64,
+	algorithm: HashAlgorithm,
+}
+
+impl CacheKey {
+	#[cfg_attr(unix, allow(unused_variables))]
+	fn new(path: &Path, metadata: &Metadata, algorithm: HashAlgorithm) -> io::Result<Self> {
+		Ok(Self {
+			#[cfg(unix)]
+			device: metadata.dev(),
+			#[cfg(unix)]
+			inode: metadata.ino(),
+			#[cfg(not(unix))]
+			path: path.canonicalize()?,
+			modified: metadata.modified().ok(),
+			size: metadata.len(),
+			algorithm,
+		})
+	}
+}
+
+#[derive(Debug)]
+struct Entries {
+	hashes: HashMap<CacheKey, (u64, FileHash)>,
+	clock: u64,
+}
+
+/// A cache of file hashes, so that repeatedly requesting the hash of a large file
+/// doesn't require reading the whole file every time.
+///
+/// Entries are keyed by the file's identity (its device and inode on Unix-like platforms),
+/// modification time and size, so a file that changes is always rehashed.
+/// A file that changes while it's being hashed isn't cached at all.
+/// Once full, the least recently used hash is evicted first.
+#[derive(Debug)]
+pub struct HashCache {
+	capacity: usize,
+	entries: Mutex<Entries>,
+}
+
+impl HashCache {
+	/// Creates a new cache, holding at most `capacity` hashes.
+	pub fn new(capacity: usize) -> Self {
+		Self {
+			capacity,
+			entries: Mutex::new(Entries {
+				hashes: HashMap::new(),
+				clock: 0,
+			}),
+		}
+	}
+
+	/// Returns the hash of the file at `path`,
+	/// either from the cache, or by hashing it and caching the result.
+	pub fn hash<P: AsRef<Path>>(&self, path: P, algorithm: HashAlgorithm) -> io::Result<FileHash> {
+		let path = path.as_ref();
+		let mut file = File::open(path)?;
+		let key = CacheKey::new(path, &file.metadata()?, algorithm)?;
+		if let Some(hash) = self.lookup(&key) {
+			return Ok(hash);
+		}
+		let hash = FileHash::from_reader(&mut file, algorithm)?;
+		// Files without a modification time can change without us noticing,
+		// and files that changed while being read may have been hashed halfway through a write.
+		if key.modified.is_some()
+			&& self.capacity > 0
+			&& CacheKey::new(path, &file.metadata()?, algorithm)? == key
+		{
+			self.insert(key, hash.clone());
+		}
+		Ok(hash)
+	}
+
+	/// Removes every hash from the cache.
+	pub fn clear(&self) {
+		self.lock().hashes.clear();
+	}
+
+	fn lookup(&self, key: &CacheKey) -> Option<FileHash> {
+		let mut entries = self.lock();
+		entries.clock += 1;
+		let clock = entries.clock;
+		let (used, hash) = entries.hashes.get_mut(key)?;
+		*used = clock;
+		Some(hash.clone())
+	}
+
+	fn insert(&self, key: CacheKey, hash: FileHash) {
+		let mut entries = self.lock();
+		if entries.hashes.len() >= self.capacity && !entries.hashes.contains_key(&key) {
+			let oldest = entries
+				.hashes
+				.iter()
+				.min_by_key(|(_, (used, _))| *used)
+				.map(|(key, _)| key.clone());
+			if let Some(oldest) = oldest {
+				entries.hashes.remove(&oldest);
+			}
+		}
+		entries.clock += 1;
+		let clock = entries.clock;
+		entries.hashes.insert(key, (clock, hash));
+	}
+
+	fn lock(&self) -> std::sync::MutexGuard<'_, Entries> {
+		self.entries
+			.lock()
+			.unwrap_or_else(|poisoned| poisoned.into_inner())
+	}
+}
@@ -0,0 +1,244 @@
+mod store;
+
+pub use self::store::Journal;
+use crate::watch::WatchEvent;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, skip_serializing_none, DisplayFromStr, PickFirst};
+use std::{fmt, str::FromStr};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+/// A position in a mount's change journal, given to clients so they can ask
+/// for everything that changed after it.
+///
+/// Tokens are opaque to clients. They contain the identifier of the journal they came from,
+/// so a token from a journal which has since been recreated is never mistaken for a valid one.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SyncToken {
+	journal: u64,
+	seq: u64,
+}
+
+impl SyncToken {
+	/// Creates a token for the given sequence number in the given journal.
+	#[inline]
+	pub fn new(journal: u64, seq: u64) -> Self {
+		Self { journal, seq }
+	}
+
+	/// The identifier of the journal this token came from.
+	#[inline]
+	pub fn journal(&self) -> u64 {
+		self.journal
+	}
+
+	/// The sequence number of the last change the client has seen.
+	#[inline]
+	pub fn seq(&self) -> u64 {
+		self.seq
+	}
+}
+
+impl fmt::Display for SyncToken {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{:016x}{:016x}", self.journal, self.seq)
+	}
+}
+
+/// The error returned when parsing an invalid [`SyncToken`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidSyncToken;
+
+impl fmt::Display for InvalidSyncToken {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("invalid sync token")
+	}
+}
+
+impl std::error::Error for InvalidSyncToken {}
+
+impl FromStr for SyncToken {
+	type Err = InvalidSyncToken;
+
+	/// Only accepts exactly 32 lowercase hex digits, as formatted by [`Display`](fmt::Display),
+	/// so every token has exactly one string representation.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		if s.len() != 32
+			|| !s
+				.bytes()
+				.all(|byte| matches!(byte, b'0'..=b'9' | b'a'..=b'f'))
+		{
+			return Err(InvalidSyncToken);
+		}
+		let journal = s.get(..16).ok_or(InvalidSyncToken)?;
+		let seq = s.get(16..).ok_or(InvalidSyncToken)?;
+		Ok(Self {
+			journal: u64::from_str_radix(journal, 16).map_err(|_| InvalidSyncToken)?,
+			seq: u64::from_str_radix(seq, 16).map_err(|_| InvalidSyncToken)?,
+		})
+	}
+}
+
+/// A single change recorded in a mount's change journal.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct JournalEntry {
+	#[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+	#[cfg_attr(feature = "schema", schemars(with = "crate::schema::Size"))]
+	seq: u64,
+	#[serde_as(as = "Rfc3339")]
+	#[cfg_attr(feature = "schema", schemars(with = "crate::schema::DateTime"))]
+	time: OffsetDateTime,
+	#[serde(flatten)]
+	event: WatchEvent,
+}
+
+impl JournalEntry {
+	/// Creates a new journal entry.
+	#[inline]
+	pub fn new(seq: u64, time: OffsetDateTime, event: WatchEvent) -> Self {
+		Self { seq, time, event }
+	}
+
+	/// The sequence number of this change. Sequence numbers increase monotonically within a journal.
+	///
+	/// Represented as a number in JSON, with the key `seq`.
+	#[inline]
+	pub fn seq(&self) -> u64 {
+		self.seq
+	}
+
+	/// The time this change was made.
+	///
+	/// Represented as a string in RFC 3339 format in JSON, with the key `time`.
+	#[inline]
+	pub fn time(&self) -> OffsetDateTime {
+		self.time
+	}
+
+	/// The change itself, with its path relative to the root of the mount.
+	///
+	/// Its fields are represented in JSON directly in the journal entry object.
+	#[inline]
+	pub fn event(&self) -> &WatchEvent {
+		&self.event
+	}
+}
+
+/// The query parameters accepted by the changes endpoint.
+#[skip_serializing_none]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ChangesQuery {
+	since: Option<String>,
+	limit: Option<u64>,
+}
+
+impl ChangesQuery {
+	/// The token returned by the previous request, if any.
+	/// If this is `None`, no changes are returned, only a token for the current position.
+	///
+	/// Represented as a string in the query string, with the key `since`.
+	#[inline]
+	pub fn since(&self) -> Option<&str> {
+		self.since.as_deref()
+	}
+
+	/// Duplicates this query object,
+	/// updating the token with the specified value.
+	#[inline]
+	pub fn with_since(self, since: impl Into<Option<String>>) -> Self {
+		Self {
+			since: since.into(),
+			..self
+		}
+	}
+
+	/// The maximum amount of changes to return, if any.
+	///
+	/// Represented as a number in the query string, with the key `limit`.
+	#[inline]
+	pub fn limit(&self) -> Option<u64> {
+		self.limit
+	}
+
+	/// Duplicates this query object,
+	/// updating the limit with the specified value.
+	#[inline]
+	pub fn with_limit(self, limit: impl Into<Option<u64>>) -> Self {
+		Self {
+			limit: limit.into(),
+			..self
+		}
+	}
+}
+
+/// The changes made to a mount since a [`SyncToken`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ChangeSet {
+	changes: Vec<JournalEntry>,
+	token: String,
+	#[serde(default)]
+	more: bool,
+	#[serde(default)]
+	resync_required: bool,
+}
+
+impl ChangeSet {
+	/// Creates a change set, containing the given changes and the token to continue from.
+	#[inline]
+	pub fn new(changes: Vec<JournalEntry>, token: SyncToken, more: bool) -> Self {
+		Self {
+			changes,
+			token: token.to_string(),
+			more,
+			resync_required: false,
+		}
+	}
+
+	/// Creates a change set telling the client that the changes since its token are no longer known,
+	/// and it must resynchronize by listing the mount again, then continue from `token`.
+	#[inline]
+	pub fn resync(token: SyncToken) -> Self {
+		Self {
+			changes: Vec::new(),
+			token: token.to_string(),
+			more: false,
+			resync_required: true,
+		}
+	}
+
+	/// The changes, ordered by sequence number.
+	///
+	/// Represented as an array of [`JournalEntry`] objects in JSON, with the key `changes`.
+	#[inline]
+	pub fn changes(&self) -> &[JournalEntry] {
+		&self.changes
+	}
+
+	/// The token to pass as `since` in the next request.
+	///
+	/// Represented as a string in JSON, with the key `token`.
+	#[inline]
+	pub fn token(&self) -> &str {
+		&self.token
+	}
+
+	/// Whether there are more changes after these, which weren't returned because of the `limit`.
+	///
+	/// Represented as a bool in JSON, with the key `more`.
+	#[inline]
+	pub fn more(&self) -> bool {
+		self.more
+	}
+
+	/// Whether the journal no longer has the changes since the requested token,
+	/// meaning the client must list the mount again from scratch.
+	///
+	/// Represented as a bool in JSON, with the key `resync_required`.
+	#[inline]
+	pub fn resync_required(&self) -> bool {
+		self.resync_required
+	}
+}
@@ -0,0 +1,253 @@
+use super::{ChangeSet, JournalEntry, SyncToken};
+use crate::{random::random_bytes, watch::WatchEvent};
+use serde::{Deserialize, Serialize};
+use std::{
+	fs::{self, File, OpenOptions},
+	io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
+	path::{Path, PathBuf},
+	sync::{Mutex, MutexGuard},
+};
+use time::OffsetDateTime;
+
+/// The first line of a journal file.
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+	journal: String,
+	first_seq: u64,
+}
+
+#[derive(Debug)]
+struct State {
+	file: File,
+	first_seq: u64,
+	next_seq: u64,
+	/// The sequence number of every entry in the file, with the offset its line starts at.
+	offsets: Vec<(u64, u64)>,
+}
+
+/// A mount's change journal, persisted to a local file.
+///
+/// The file holds a header line with the journal's identifier,
+/// followed by one [`JournalEntry`] JSON object per line, so appending a change never rewrites it.
+/// Servers should keep one journal per mount, and [`append`](Self::append) every mutation made through xfp to it.
+/// A line left incomplete by a crash is discarded when the journal is next opened.
+#[derive(Debug)]
+pub struct Journal {
+	path: PathBuf,
+	id: u64,
+	state: Mutex<State>,
+}
+
+impl Journal {
+	/// Opens the journal at `path`, creating a new, empty journal if the file doesn't exist.
+	pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+		let path = path.into();
+		if !path.exists() {
+			// A random identifier means tokens from a recreated journal are never mistaken for valid ones.
+			let header = Header {
+				journal: format!("{:016x}", u64::from_le_bytes(random_bytes()?)),
+				first_seq: 1,
+			};
+			write_atomically(&path, &header, &[])?;
+		}
+		let mut file = OpenOptions::new().read(true).append(true).open(&path)?;
+		let mut contents = Vec::new();
+		file.read_to_end(&mut contents)?;
+		// Anything after the last newline was never completely written.
+		let complete = contents
+			.iter()
+			.rposition(|byte| *byte == b'\n')
+			.map_or(0, |idx| idx + 1);
+		if complete < contents.len() {
+			file.set_len(complete as u64)?;
+			file.seek(SeekFrom::End(0))?;
+		}
+		let mut lines = contents[..complete].split(|byte| *byte == b'\n');
+		let header_line = lines
+			.next()
+			.filter(|line| !line.is_empty())
+			.ok_or_else(|| invalid_data("the journal has no header"))?;
+		let header = serde_json::from_slice::<Header>(header_line).map_err(invalid_data)?;
+		let id = parse_id(&header.journal)?;
+		let mut next_seq = header.first_seq;
+		let mut offsets = Vec::new();
+		let mut offset = header_line.len() as u64 + 1;
+		for line in lines {
+			if !line.is_empty() {
+				let entry = serde_json::from_slice::<JournalEntry>(line).map_err(invalid_data)?;
+				offsets.push((entry.seq(), offset));
+				next_seq = entry.seq() + 1;
+			}
+			offset += line.len() as u64 + 1;
+		}
+		Ok(Self {
+			path,
+			id,
+			state: Mutex::new(State {
+				file,
+				first_seq: header.first_seq,
+				next_seq,
+				offsets,
+			}),
+		})
+	}
+
+	/// The identifier of this journal, which is part of every [`SyncToken`] it hands out.
+	#[inline]
+	pub fn id(&self) -> u64 {
+		self.id
+	}
+
+	/// The token for the current end of the journal.
+	pub fn token(&self) -> SyncToken {
+		SyncToken::new(self.id, self.lock().next_seq - 1)
+	}
+
+	/// Records a change, with its path relative to the root of the mount,
+	/// returning the new entry. The entry is flushed to disk before this returns.
+	///
+	/// If writing the entry fails, the file is cut back to where it ended,
+	/// so a partly written line can't end up in the middle of later entries.
+	pub fn append(&self, event: WatchEvent) -> io::Result<JournalEntry> {
+		let mut state = self.lock();
+		let entry = JournalEntry::new(state.next_seq, OffsetDateTime::now_utc(), event);
+		let mut line = serde_json::to_vec(&entry)?;
+		line.push(b'\n');
+		let offset = state.file.metadata()?.len();
+		let written = state
+			.file
+			.write_all(&line)
+			.and_then(|_| state.file.sync_data());
+		if let Err(err) = written {
+			state.file.set_len(offset)?;
+			return Err(err);
+		}
+		state.next_seq += 1;
+		state.offsets.push((entry.seq(), offset));
+		Ok(entry)
+	}
+
+	/// Returns up to `limit` changes made after `since`, as a response to [`get_changes`](crate::api::get_changes).
+	///
+	/// If `since` is `None`, no changes are returned, only the token for the current end of the journal.
+	/// If `since` came from another journal, or the changes after it were [truncated](Self::truncate),
+	/// the client is told to resynchronize. A `limit` of 0 is treated as 1, so clients always make progress.
+	pub fn changes(&self, since: Option<SyncToken>, limit: Option<u64>) -> io::Result<ChangeSet> {
+		let state = self.lock();
+		let last = state.next_seq - 1;
+		let since = match since {
+			None => {
+				return Ok(ChangeSet::new(
+					Vec::new(),
+					SyncToken::new(self.id, last),
+					false,
+				))
+			}
+			Some(since) => since,
+		};
+		if since.journal() != self.id || since.seq() > last || since.seq() + 1 < state.first_seq {
+			return Ok(ChangeSet::resync(SyncToken::new(self.id, last)));
+		}
+		let limit = limit.map_or(usize::MAX, |limit| {
+			usize::try_from(limit.max(1)).unwrap_or(usize::MAX)
+		});
+		// Only the changes being returned are read, starting straight from the first of them.
+		let start = state
+			.offsets
+			.partition_point(|(seq, _)| *seq <= since.seq());
+		let after = &state.offsets[start..];
+		let more = after.len() > limit;
+		let mut changes = Vec::new();
+		if let Some((_, offset)) = after.first() {
+			let mut file = File::open(&self.path)?;
+			file.seek(SeekFrom::Start(*offset))?;
+			for line in BufReader::new(file).lines().take(after.len().min(limit)) {
+				changes.push(serde_json::from_str(&line?).map_err(invalid_data)?);
+			}
+		}
+		let seq = changes.last().map_or(since.seq(), JournalEntry::seq);
+		Ok(ChangeSet::new(changes, SyncToken::new(self.id, seq), more))
+	}
+
+	/// Removes every change made before `before`, returning how many were removed.
+	/// Clients with a token from before the earliest remaining change will have to resynchronize.
+	///
+	/// The journal is rewritten next to its current location and moved into place,
+	/// so a crash never loses the changes being kept.
+	pub fn truncate(&self, before: OffsetDateTime) -> io::Result<usize> {
+		let mut state = self.lock();
+		let mut kept = Vec::new();
+		let mut removed = 0;
+		for entry in self.entries()? {
+			let entry = entry?;
+			if entry.time() < before {
+				removed += 1;
+			} else {
+				kept.push(entry);
+			}
+		}
+		if removed == 0 {
+			return Ok(0);
+		}
+		let header = Header {
+			journal: format!("{:016x}", self.id),
+			first_seq: kept.first().map_or(state.next_seq, JournalEntry::seq),
+		};
+		state.offsets = write_atomically(&self.path, &header, &kept)?;
+		state.file = OpenOptions::new()
+			.read(true)
+			.append(true)
+			.open(&self.path)?;
+		state.first_seq = header.first_seq;
+		Ok(removed)
+	}
+
+	fn entries(&self) -> io::Result<impl Iterator<Item = io::Result<JournalEntry>>> {
+		let lines = BufReader::new(File::open(&self.path)?).lines().skip(1);
+		Ok(lines.filter_map(|line| match line {
+			Ok(line) if line.is_empty() => None,
+			Ok(line) => Some(serde_json::from_str(&line).map_err(invalid_data)),
+			Err(err) => Some(Err(err)),
+		}))
+	}
+
+	fn lock(&self) -> MutexGuard<'_, State> {
+		self.state
+			.lock()
+			.unwrap_or_else(|poisoned| poisoned.into_inner())
+	}
+}
+
+/// Writes a whole journal file, returning the sequence number and offset of each entry.
+fn write_atomically(
+	path: &Path,
+	header: &Header,
+	entries: &[JournalEntry],
+) -> io::Result<Vec<(u64, u64)>> {
+	let mut contents = serde_json::to_vec(header)?;
+	contents.push(b'\n');
+	let mut offsets = Vec::with_capacity(entries.len());
+	for entry in entries {
+		offsets.push((entry.seq(), contents.len() as u64));
+		serde_json::to_writer(&mut contents, entry)?;
+		contents.push(b'\n');
+	}
+	let mut temporary = path.as_os_str().to_owned();
+	temporary.push(".tmp");
+	let mut file = File::create(&temporary)?;
+	file.write_all(&contents)?;
+	file.sync_all()?;
+	fs::rename(temporary, path)?;
+	Ok(offsets)
+}
+
+fn parse_id(id: &str) -> io::Result<u64> {
+	if id.len() != 16 || !id.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+		return Err(invalid_data("the journal has an invalid identifier"));
+	}
+	u64::from_str_radix(id, 16).map_err(invalid_data)
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(err: E) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, err)
+}
@@ -1,8 +1,36 @@
 /// Documentation for the Xenon File Protocol itself.
 pub mod api;
+/// Protocol details related to directory archives.
+pub mod archive;
+/// Protocol details related to capability negotiation.
+pub mod capabilities;
+/// Protocol details related to delta transfers.
+pub mod delta;
 /// Protocol details related to directory listings.
 pub mod dir;
+mod encoding;
+/// Protocol details related to error responses.
+pub mod error;
 /// Protocol details related to file information.
 pub mod file;
+/// Protocol details related to response body formats.
+pub mod format;
+/// Protocol details related to file hashes.
+pub mod hash;
+/// Protocol details related to the change journal.
+pub mod journal;
 /// Protocol details related to file metadata.
 pub mod metadata;
+/// An OpenAPI description of the protocol, generated from its types.
+#[cfg(feature = "openapi")]
+pub mod openapi;
+mod random;
+/// JSON Schemas for the protocol's types.
+#[cfg(feature = "schema")]
+pub mod schema;
+/// Protocol details related to resumable uploads.
+pub mod upload;
+/// Protocol details related to change notifications.
+pub mod watch;
+/// Protocol details related to partial writes.
+pub mod write;
@@ -1,26 +1,113 @@
+mod acl;
+mod mime;
+mod names;
+mod patch;
 mod permissions;
+mod principal;
+mod times;
+mod xattr;
 
-pub use self::permissions::{FileGroup, FileOwner, FilePermissions};
+pub use self::{
+	acl::{Access, AclEntry, AclTag},
+	mime::{sniff, MimeDetector, DEFAULT_MIME},
+	names::NameCache,
+	patch::{MetadataPatch, MetadataPatchError, PermissionsPatch, PrincipalChange},
+	permissions::{FileGroup, FileOwner, FilePermissions},
+	principal::PrincipalKind,
+	xattr::{Xattr, XattrError, XattrPolicy, XattrQuery},
+};
+use crate::encoding::{deserialize_base64_map, serialize_base64_map, Timestamp};
 use serde::{Deserialize, Serialize};
-use serde_with::{serde_as, skip_serializing_none, DisplayFromStr};
-use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use serde_with::{serde_as, skip_serializing_none, DisplayFromStr, PickFirst};
+use std::{collections::BTreeMap, io, path::Path};
+use time::OffsetDateTime;
 
+/// The metadata of a file, directory, or symlink.
+///
+/// Timestamps are RFC 3339 strings in JSON. In CBOR and MessagePack they're encoded natively by
+/// [`Format::encode`](crate::format::Format::encode) and [`NativeTimestamps`](crate::format::NativeTimestamps),
+/// and as plain RFC 3339 strings otherwise. Both forms are accepted when deserializing either format.
 #[serde_as]
 #[skip_serializing_none]
 #[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(
+	feature = "schema",
+	schemars(description = "The metadata of a file, directory, or symlink.")
+)]
 pub struct Metadata {
-	#[serde_as(as = "Option<Rfc3339>")]
+	#[serde_as(as = "Option<Timestamp>")]
+	#[serde(default)]
+	#[cfg_attr(feature = "schema", schemars(with = "Option<crate::schema::DateTime>"))]
 	created: Option<OffsetDateTime>,
-	#[serde_as(as = "Option<Rfc3339>")]
+	#[serde_as(as = "Option<Timestamp>")]
+	#[serde(default)]
+	#[cfg_attr(feature = "schema", schemars(with = "Option<crate::schema::DateTime>"))]
 	updated: Option<OffsetDateTime>,
-	#[serde_as(as = "Option<Rfc3339>")]
+	#[serde_as(as = "Option<Timestamp>")]
+	#[serde(default)]
+	#[cfg_attr(feature = "schema", schemars(with = "Option<crate::schema::DateTime>"))]
 	accessed: Option<OffsetDateTime>,
+	#[serde_as(as = "Option<Timestamp>")]
+	#[serde(default)]
+	#[cfg_attr(feature = "schema", schemars(with = "Option<crate::schema::DateTime>"))]
+	changed: Option<OffsetDateTime>,
 	permissions: FilePermissions,
-	#[serde_as(as = "DisplayFromStr")]
+	#[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+	#[cfg_attr(feature = "schema", schemars(with = "crate::schema::Size"))]
 	size: u64,
+	mime: Option<String>,
+	#[serde(
+		default,
+		serialize_with = "serialize_base64_map",
+		deserialize_with = "deserialize_base64_map"
+	)]
+	#[cfg_attr(
+		feature = "schema",
+		schemars(with = "Option<BTreeMap<String, crate::schema::Base64>>")
+	)]
+	xattrs: Option<BTreeMap<String, Vec<u8>>>,
 }
 
 impl Metadata {
+	/// Reads the metadata of the file at `path`, following symlinks.
+	///
+	/// On Linux, the creation time is read with `statx`, and is `None` if the filesystem doesn't record it.
+	/// Extended attributes and the media type are not filled in, as they are comparatively expensive to read.
+	pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+		Self::read_path(path.as_ref(), None)
+	}
+
+	/// Reads the metadata of the file at `path`, following symlinks,
+	/// looking up the owner and group names through `names`.
+	///
+	/// This should be preferred over [`from_path`](Self::from_path) when reading many files,
+	/// such as for a directory listing.
+	pub fn from_path_cached<P: AsRef<Path>>(path: P, names: &NameCache) -> io::Result<Self> {
+		Self::read_path(path.as_ref(), Some(names))
+	}
+
+	fn read_path(path: &Path, names: Option<&NameCache>) -> io::Result<Self> {
+		let metadata = path.metadata()?;
+		#[cfg(target_os = "linux")]
+		let created = times::birth_time(path);
+		#[cfg(not(target_os = "linux"))]
+		let created = metadata.created().ok().map(OffsetDateTime::from);
+		Ok(Self {
+			created,
+			updated: metadata.modified().ok().map(OffsetDateTime::from),
+			accessed: metadata.accessed().ok().map(OffsetDateTime::from),
+			changed: times::change_time(&metadata),
+			permissions: match names {
+				Some(names) => FilePermissions::from_path_cached(path, names)?,
+				None => FilePermissions::from_path(path)?,
+			},
+			size: metadata.len(),
+			mime: None,
+			xattrs: None,
+		})
+	}
+
 	/// The time the file was created.
 	///
 	/// Represented as a string in RFC 3339 format in JSON, with the key `created`.
@@ -78,6 +165,25 @@ impl Metadata {
 		}
 	}
 
+	/// The time the file's metadata (such as its permissions or owner) or contents last changed.
+	/// This is the Unix `ctime`, and is always `None` on Windows.
+	///
+	/// Represented as a string in RFC 3339 format in JSON, with the key `changed`.
+	#[inline]
+	pub fn changed(&self) -> Option<OffsetDateTime> {
+		self.changed
+	}
+
+	/// Duplicates this metadata object,
+	/// updating the changed time with the specified time.
+	#[inline]
+	pub fn with_changed(self, changed: impl Into<Option<OffsetDateTime>>) -> Self {
+		Self {
+			changed: changed.into(),
+			..self
+		}
+	}
+
 	/// The permissions of this file.
 	/// This is always present.
 	///
@@ -100,6 +206,8 @@ impl Metadata {
 	/// The file's size in bytes.
 	///
 	/// Represented as a number in JSON, with the key `size`.
+	/// Version 0.1 of the protocol represented it as a string instead,
+	/// so strings containing a number are also accepted.
 	#[inline]
 	pub fn size(&self) -> u64 {
 		self.size
@@ -111,4 +219,42 @@ impl Metadata {
 	pub fn with_size(self, size: u64) -> Self {
 		Self { size, ..self }
 	}
+
+	/// The media type of the file's contents, such as `text/plain`, if it could be detected.
+	/// This is never present for directories.
+	///
+	/// Represented as a string in JSON, with the key `mime`.
+	#[inline]
+	pub fn mime(&self) -> Option<&str> {
+		self.mime.as_deref()
+	}
+
+	/// Duplicates this metadata object,
+	/// updating the media type with the specified value.
+	#[inline]
+	pub fn with_mime(self, mime: impl Into<Option<String>>) -> Self {
+		Self {
+			mime: mime.into(),
+			..self
+		}
+	}
+
+	/// The extended attributes of the file, mapping their full name (such as `user.provenance`) to their value.
+	/// This is only present if they were requested, see [`XattrQuery`].
+	///
+	/// Represented as an object in JSON, with the key `xattrs`, where each value is a base64-encoded string.
+	#[inline]
+	pub fn xattrs(&self) -> Option<&BTreeMap<String, Vec<u8>>> {
+		self.xattrs.as_ref()
+	}
+
+	/// Duplicates this metadata object,
+	/// updating the extended attributes with the specified value.
+	#[inline]
+	pub fn with_xattrs(self, xattrs: impl Into<Option<BTreeMap<String, Vec<u8>>>>) -> Self {
+		Self {
+			xattrs: xattrs.into(),
+			..self
+		}
+	}
 }
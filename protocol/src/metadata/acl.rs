@@ -0,0 +1,165 @@
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+/// The name of the extended attribute holding a file's POSIX access ACL on Linux.
+#[cfg(unix)]
+pub(super) const ACL_XATTR: &str = "system.posix_acl_access";
+
+/// The kind of principal a POSIX ACL entry applies to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum AclTag {
+	/// The owner of the file.
+	///
+	/// Represented as the string `user_obj` in JSON.
+	UserObj,
+	/// A specific user, identified by the entry's `id`.
+	///
+	/// Represented as the string `user` in JSON.
+	User,
+	/// The group of the file.
+	///
+	/// Represented as the string `group_obj` in JSON.
+	GroupObj,
+	/// A specific group, identified by the entry's `id`.
+	///
+	/// Represented as the string `group` in JSON.
+	Group,
+	/// The maximum permissions granted by any `user`, `group_obj` or `group` entry.
+	///
+	/// Represented as the string `mask` in JSON.
+	Mask,
+	/// Everyone else.
+	///
+	/// Represented as the string `other` in JSON.
+	Other,
+}
+
+/// A single entry of a POSIX access control list.
+#[skip_serializing_none]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AclEntry {
+	tag: AclTag,
+	id: Option<u32>,
+	read: bool,
+	write: bool,
+	execute: bool,
+}
+
+/// A kind of access to a file.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Access {
+	/// Reading the file, or listing the directory.
+	Read,
+	/// Writing the file, or creating and removing entries in the directory.
+	Write,
+	/// Executing the file, or traversing the directory.
+	Execute,
+}
+
+impl AclEntry {
+	/// Creates a new ACL entry.
+	/// `id` should only be present for [`AclTag::User`] and [`AclTag::Group`] entries.
+	pub fn new(
+		tag: AclTag,
+		id: impl Into<Option<u32>>,
+		read: bool,
+		write: bool,
+		execute: bool,
+	) -> Self {
+		Self {
+			tag,
+			id: id.into(),
+			read,
+			write,
+			execute,
+		}
+	}
+
+	/// The kind of principal this entry applies to.
+	///
+	/// Represented as a string in JSON, with the key `tag`. See [`AclTag`].
+	#[inline]
+	pub fn tag(&self) -> AclTag {
+		self.tag
+	}
+
+	/// The uid or gid of the principal this entry applies to,
+	/// for `user` and `group` entries.
+	///
+	/// Represented as a number in JSON, with the key `id`.
+	#[inline]
+	pub fn id(&self) -> Option<u32> {
+		self.id
+	}
+
+	/// Returns if this entry grants read access.
+	///
+	/// Represented as a bool in JSON, with the key `read`.
+	#[inline]
+	pub fn read(&self) -> bool {
+		self.read
+	}
+
+	/// Returns if this entry grants write access.
+	///
+	/// Represented as a bool in JSON, with the key `write`.
+	#[inline]
+	pub fn write(&self) -> bool {
+		self.write
+	}
+
+	/// Returns if this entry grants execute access.
+	///
+	/// Represented as a bool in JSON, with the key `execute`.
+	#[inline]
+	pub fn execute(&self) -> bool {
+		self.execute
+	}
+
+	/// Returns if this entry grants the given access.
+	#[inline]
+	pub fn grants(&self, access: Access) -> bool {
+		match access {
+			Access::Read => self.read,
+			Access::Write => self.write,
+			Access::Execute => self.execute,
+		}
+	}
+
+	/// Parses the binary format Linux uses for the `system.posix_acl_access` extended attribute.
+	/// Returns `None` if the value is truncated, has an unknown version, or has an entry with an unknown tag.
+	pub fn parse_xattr(value: &[u8]) -> Option<Vec<AclEntry>> {
+		const VERSION: u32 = 2;
+		let (header, entries) = value.split_at_checked(4)?;
+		if u32::from_le_bytes(header.try_into().ok()?) != VERSION || entries.len() % 8 != 0 {
+			return None;
+		}
+		entries
+			.chunks_exact(8)
+			.map(|entry| {
+				let tag = u16::from_le_bytes([entry[0], entry[1]]);
+				let perm = u16::from_le_bytes([entry[2], entry[3]]);
+				let id = u32::from_le_bytes([entry[4], entry[5], entry[6], entry[7]]);
+				let (tag, id) = match tag {
+					0x01 => (AclTag::UserObj, None),
+					0x02 => (AclTag::User, Some(id)),
+					0x04 => (AclTag::GroupObj, None),
+					0x08 => (AclTag::Group, Some(id)),
+					0x10 => (AclTag::Mask, None),
+					0x20 => (AclTag::Other, None),
+					_ => return None,
+				};
+				Some(AclEntry::new(
+					tag,
+					id,
+					perm & 4 != 0,
+					perm & 2 != 0,
+					perm & 1 != 0,
+				))
+			})
+			.collect()
+	}
+}
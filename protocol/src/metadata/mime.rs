@@ -0,0 +1,105 @@
+use std::{collections::HashMap, fs::File, io::Read, path::Path};
+
+/// The media type served for files whose type couldn't be detected.
+pub const DEFAULT_MIME: &str = "application/octet-stream";
+
+/// Well-known signatures at the start of files, and the media type they indicate.
+const MAGIC: &[(&[u8], &str)] = &[
+	(b"\x89PNG\r\n\x1a\n", "image/png"),
+	(b"\xff\xd8\xff", "image/jpeg"),
+	(b"GIF87a", "image/gif"),
+	(b"GIF89a", "image/gif"),
+	(b"%PDF-", "application/pdf"),
+	(b"PK\x03\x04", "application/zip"),
+	(b"\x1f\x8b", "application/gzip"),
+	(b"BZh", "application/x-bzip2"),
+	(b"\xfd7zXZ\x00", "application/x-xz"),
+	(b"\x28\xb5\x2f\xfd", "application/zstd"),
+	(b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+	(b"\x7fELF", "application/x-elf"),
+	(b"OggS", "application/ogg"),
+	(b"fLaC", "audio/flac"),
+	(b"ID3", "audio/mpeg"),
+	(b"\x1a\x45\xdf\xa3", "video/webm"),
+	(b"wOFF", "font/woff"),
+	(b"wOF2", "font/woff2"),
+];
+
+/// Detects the media type of files, to be reported as [`Metadata::mime`](super::Metadata::mime)
+/// and served as the `Content-Type` of downloads.
+///
+/// Detection first checks the per-mount overrides, then the file extension,
+/// and finally, if sniffing is enabled, the first few bytes of the file.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MimeDetector {
+	overrides: HashMap<String, String>,
+	sniff: bool,
+}
+
+impl MimeDetector {
+	/// Creates a new detector, without any overrides, and with sniffing disabled.
+	#[inline]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Duplicates this detector,
+	/// adding an override mapping the (case-insensitive) extension `extension` to `mime`.
+	pub fn with_override(mut self, extension: impl AsRef<str>, mime: impl ToString) -> Self {
+		self.overrides.insert(
+			extension
+				.as_ref()
+				.trim_start_matches('.')
+				.to_ascii_lowercase(),
+			mime.to_string(),
+		);
+		self
+	}
+
+	/// Duplicates this detector,
+	/// updating whether the contents of files with an unknown extension are sniffed.
+	#[inline]
+	pub fn with_sniffing(self, sniff: bool) -> Self {
+		Self { sniff, ..self }
+	}
+
+	/// Returns if the contents of files with an unknown extension are sniffed.
+	#[inline]
+	pub fn sniffing(&self) -> bool {
+		self.sniff
+	}
+
+	/// Detects the media type of the file at `path`.
+	///
+	/// Returns `None` if it couldn't be detected, in which case
+	/// [`DEFAULT_MIME`] should be used as the `Content-Type`.
+	pub fn detect<P: AsRef<Path>>(&self, path: P) -> Option<String> {
+		let path = path.as_ref();
+		if let Some(extension) = path.extension().and_then(|extension| extension.to_str()) {
+			if let Some(mime) = self.overrides.get(&extension.to_ascii_lowercase()) {
+				return Some(mime.clone());
+			}
+			if let Some(mime) = mime_guess::from_ext(extension).first_raw() {
+				return Some(mime.to_string());
+			}
+		}
+		if !self.sniff {
+			return None;
+		}
+		let mut header = [0; 16];
+		let len = File::open(path).ok()?.read(&mut header).ok()?;
+		sniff(&header[..len]).map(str::to_string)
+	}
+}
+
+/// Detects the media type of a file from the first few bytes of its contents.
+pub fn sniff(header: &[u8]) -> Option<&'static str> {
+	MAGIC
+		.iter()
+		.find(|(magic, _)| header.starts_with(magic))
+		.map(|(_, mime)| *mime)
+		.or_else(|| {
+			(header.len() >= 12 && &header[..4] == b"RIFF" && &header[8..12] == b"WEBP")
+				.then_some("image/webp")
+		})
+}
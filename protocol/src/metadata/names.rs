@@ -0,0 +1,175 @@
+use std::{
+	collections::HashMap,
+	hash::Hash,
+	sync::{Mutex, MutexGuard},
+	time::{Duration, Instant},
+};
+
+#[cfg(unix)]
+use nix::unistd::{Gid, Group, Uid, User};
+
+#[cfg(windows)]
+use windows_permissions::{wrappers::LookupAccountSid, Sid};
+
+#[derive(Debug)]
+struct Expiring<K, V> {
+	entries: Mutex<HashMap<K, (V, Instant)>>,
+}
+
+impl<K: Eq + Hash, V: Clone> Expiring<K, V> {
+	fn new() -> Self {
+		Self {
+			entries: Mutex::new(HashMap::new()),
+		}
+	}
+
+	fn get_or_insert_with(&self, key: K, ttl: Duration, lookup: impl FnOnce() -> V) -> V {
+		let now = Instant::now();
+		if let Some((value, expires)) = self.lock().get(&key) {
+			if *expires > now {
+				return value.clone();
+			}
+		}
+		// The lock isn't held during the lookup, as it may block on the network.
+		let value = lookup();
+		let mut entries = self.lock();
+		entries.retain(|_, (_, expires)| *expires > now);
+		entries.insert(key, (value.clone(), now + ttl));
+		value
+	}
+
+	fn clear(&self) {
+		self.lock().clear();
+	}
+
+	fn lock(&self) -> MutexGuard<'_, HashMap<K, (V, Instant)>> {
+		self.entries
+			.lock()
+			.unwrap_or_else(|poisoned| poisoned.into_inner())
+	}
+}
+
+/// A cache of user and group names, so that reading the permissions of every file
+/// in a large directory doesn't require looking up the same owner and group
+/// (possibly over the network, through NSS or LDAP) for every entry.
+///
+/// Entries expire after a fixed time to live, so renamed users and groups are eventually picked up.
+/// Failed lookups are cached too. A single cache is meant to be shared across requests.
+#[derive(Debug)]
+pub struct NameCache {
+	ttl: Duration,
+	#[cfg(unix)]
+	users: Expiring<u32, Option<String>>,
+	#[cfg(unix)]
+	groups: Expiring<u32, Option<String>>,
+	#[cfg(unix)]
+	lookup_user: fn(u32) -> Option<String>,
+	#[cfg(unix)]
+	lookup_group: fn(u32) -> Option<String>,
+	#[cfg(windows)]
+	accounts: Expiring<String, (String, String)>,
+}
+
+impl NameCache {
+	/// Creates a new cache, keeping each name for `ttl`.
+	pub fn new(ttl: Duration) -> Self {
+		Self {
+			ttl,
+			#[cfg(unix)]
+			users: Expiring::new(),
+			#[cfg(unix)]
+			groups: Expiring::new(),
+			#[cfg(unix)]
+			lookup_user,
+			#[cfg(unix)]
+			lookup_group,
+			#[cfg(windows)]
+			accounts: Expiring::new(),
+		}
+	}
+
+	/// Duplicates this cache,
+	/// updating the functions used to look up user and group names with the specified values,
+	/// for servers which resolve names from somewhere other than the system's user database.
+	#[cfg(unix)]
+	pub fn with_lookups(
+		self,
+		lookup_user: fn(u32) -> Option<String>,
+		lookup_group: fn(u32) -> Option<String>,
+	) -> Self {
+		Self {
+			lookup_user,
+			lookup_group,
+			..self
+		}
+	}
+
+	/// Returns how long each name is kept for.
+	#[inline]
+	pub fn ttl(&self) -> Duration {
+		self.ttl
+	}
+
+	/// Returns the name of the user with the unique identifier `uid`,
+	/// or `None` if there is no such user.
+	#[cfg(unix)]
+	pub fn user_name(&self, uid: u32) -> Option<String> {
+		self.users
+			.get_or_insert_with(uid, self.ttl, || (self.lookup_user)(uid))
+	}
+
+	/// Returns the name of the group with the unique identifier `gid`,
+	/// or `None` if there is no such group.
+	#[cfg(unix)]
+	pub fn group_name(&self, gid: u32) -> Option<String> {
+		self.groups
+			.get_or_insert_with(gid, self.ttl, || (self.lookup_group)(gid))
+	}
+
+	/// Returns the domain and name of the account with the security identifier `sid`,
+	/// both of which are empty if the account can't be found.
+	#[cfg(windows)]
+	pub fn account_name(&self, sid: &Sid) -> (String, String) {
+		self.accounts
+			.get_or_insert_with(sid.to_string(), self.ttl, || lookup_account(sid))
+	}
+
+	/// Removes every name from the cache.
+	pub fn clear(&self) {
+		#[cfg(unix)]
+		{
+			self.users.clear();
+			self.groups.clear();
+		}
+		#[cfg(windows)]
+		self.accounts.clear();
+	}
+}
+
+#[cfg(unix)]
+pub(super) fn lookup_user(uid: u32) -> Option<String> {
+	User::from_uid(Uid::from_raw(uid))
+		.ok()
+		.flatten()
+		.map(|user| user.name)
+}
+
+#[cfg(unix)]
+pub(super) fn lookup_group(gid: u32) -> Option<String> {
+	Group::from_gid(Gid::from_raw(gid))
+		.ok()
+		.flatten()
+		.map(|group| group.name)
+}
+
+#[cfg(windows)]
+pub(super) fn lookup_account(sid: &Sid) -> (String, String) {
+	let (domain, name) = LookupAccountSid(sid).unwrap_or_default();
+	(
+		domain
+			.into_string()
+			.unwrap_or_else(|osstr| osstr.to_string_lossy().into_owned()),
+		name.into_string()
+			.unwrap_or_else(|osstr| osstr.to_string_lossy().into_owned()),
+	)
+}
@@ -0,0 +1,395 @@
+use super::{FileGroup, FileOwner};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, skip_serializing_none};
+use std::{fmt, io, path::Path};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+#[cfg(unix)]
+use nix::unistd::{Group, User};
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+#[cfg(not(unix))]
+use std::{
+	fs::{File, FileTimes},
+	time::SystemTime,
+};
+
+/// A reference to a user or group to change ownership to,
+/// either by its full [`FileOwner`]/[`FileGroup`] object, or just by its name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "schema", schemars(rename = "{T}Change"))]
+#[serde(untagged)]
+pub enum PrincipalChange<T> {
+	/// The user or group, identified by its unique identifier.
+	Id(T),
+	/// The user or group, identified only by its visible name,
+	/// which is looked up by the server.
+	Name {
+		/// The user or group's visible name.
+		name: String,
+	},
+}
+
+/// The permission-related fields of a [`MetadataPatch`].
+#[skip_serializing_none]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct PermissionsPatch {
+	mode: Option<u32>,
+	owner: Option<PrincipalChange<FileOwner>>,
+	group: Option<PrincipalChange<FileGroup>>,
+}
+
+impl PermissionsPatch {
+	/// The new Unix mode bits, if they should be changed.
+	///
+	/// Represented as a number in JSON, with the key `mode`.
+	#[inline]
+	pub fn mode(&self) -> Option<u32> {
+		self.mode
+	}
+
+	/// Duplicates this permissions patch,
+	/// setting the new mode bits to `mode`.
+	#[inline]
+	pub fn with_mode(self, mode: impl Into<Option<u32>>) -> Self {
+		Self {
+			mode: mode.into(),
+			..self
+		}
+	}
+
+	/// The new owner, if it should be changed.
+	///
+	/// Represented as either a [`FileOwner`] object, or an object with just a `name`, in JSON, with the key `owner`.
+	#[inline]
+	pub fn owner(&self) -> Option<&PrincipalChange<FileOwner>> {
+		self.owner.as_ref()
+	}
+
+	/// Duplicates this permissions patch,
+	/// setting the new owner to `owner`.
+	#[inline]
+	pub fn with_owner(self, owner: impl Into<Option<PrincipalChange<FileOwner>>>) -> Self {
+		Self {
+			owner: owner.into(),
+			..self
+		}
+	}
+
+	/// The new group, if it should be changed.
+	///
+	/// Represented as either a [`FileGroup`] object, or an object with just a `name`, in JSON, with the key `group`.
+	#[inline]
+	pub fn group(&self) -> Option<&PrincipalChange<FileGroup>> {
+		self.group.as_ref()
+	}
+
+	/// Duplicates this permissions patch,
+	/// setting the new group to `group`.
+	#[inline]
+	pub fn with_group(self, group: impl Into<Option<PrincipalChange<FileGroup>>>) -> Self {
+		Self {
+			group: group.into(),
+			..self
+		}
+	}
+}
+
+/// A partial [`Metadata`](super::Metadata) object, describing the changes to make to a file's metadata.
+///
+/// Fields which are absent are left unchanged.
+#[serde_as]
+#[skip_serializing_none]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct MetadataPatch {
+	#[serde_as(as = "Option<Rfc3339>")]
+	#[serde(default)]
+	#[cfg_attr(feature = "schema", schemars(with = "Option<crate::schema::DateTime>"))]
+	updated: Option<OffsetDateTime>,
+	#[serde_as(as = "Option<Rfc3339>")]
+	#[serde(default)]
+	#[cfg_attr(feature = "schema", schemars(with = "Option<crate::schema::DateTime>"))]
+	accessed: Option<OffsetDateTime>,
+	permissions: Option<PermissionsPatch>,
+}
+
+impl MetadataPatch {
+	/// The new time the file was last updated, if it should be changed.
+	///
+	/// Represented as a string in RFC 3339 format in JSON, with the key `updated`.
+	#[inline]
+	pub fn updated(&self) -> Option<OffsetDateTime> {
+		self.updated
+	}
+
+	/// Duplicates this metadata patch,
+	/// setting the new updated time to `updated`.
+	#[inline]
+	pub fn with_updated(self, updated: impl Into<Option<OffsetDateTime>>) -> Self {
+		Self {
+			updated: updated.into(),
+			..self
+		}
+	}
+
+	/// The new time the file was last accessed, if it should be changed.
+	///
+	/// Represented as a string in RFC 3339 format in JSON, with the key `accessed`.
+	#[inline]
+	pub fn accessed(&self) -> Option<OffsetDateTime> {
+		self.accessed
+	}
+
+	/// Duplicates this metadata patch,
+	/// setting the new accessed time to `accessed`.
+	#[inline]
+	pub fn with_accessed(self, accessed: impl Into<Option<OffsetDateTime>>) -> Self {
+		Self {
+			accessed: accessed.into(),
+			..self
+		}
+	}
+
+	/// The changes to the file's permissions, if any.
+	///
+	/// Represented as an object in JSON, with the key `permissions`.
+	#[inline]
+	pub fn permissions(&self) -> Option<&PermissionsPatch> {
+		self.permissions.as_ref()
+	}
+
+	/// Duplicates this metadata patch,
+	/// setting the permission changes to `permissions`.
+	#[inline]
+	pub fn with_permissions(self, permissions: impl Into<Option<PermissionsPatch>>) -> Self {
+		Self {
+			permissions: permissions.into(),
+			..self
+		}
+	}
+
+	/// Applies this patch to the file at `path` on the local filesystem.
+	///
+	/// Every field is checked before anything is changed,
+	/// so a patch with an unsupported field, invalid mode, or unknown user doesn't partially apply.
+	///
+	/// The ownership is changed first, as it's the change most likely to be refused,
+	/// followed by the mode and then the times. If a later change fails,
+	/// the ownership and mode are restored to what they were before returning the error.
+	/// Restoring them is best-effort: if that fails too, the original error is still returned.
+	pub fn apply_to_path<P: AsRef<Path>>(&self, path: P) -> Result<(), MetadataPatchError> {
+		let path = path.as_ref();
+		let permissions = self.permissions.clone().unwrap_or_default();
+		#[cfg(unix)]
+		let (uid, gid) = {
+			if let Some(mode) = permissions.mode.filter(|mode| mode & !0o7777 != 0) {
+				return Err(MetadataPatchError::InvalidMode(mode));
+			}
+			(
+				permissions.owner.as_ref().map(resolve_owner).transpose()?,
+				permissions.group.as_ref().map(resolve_group).transpose()?,
+			)
+		};
+		#[cfg(not(unix))]
+		{
+			if permissions.mode.is_some() {
+				return Err(MetadataPatchError::Unsupported("permissions.mode"));
+			}
+			if permissions.owner.is_some() {
+				return Err(MetadataPatchError::Unsupported("permissions.owner"));
+			}
+			if permissions.group.is_some() {
+				return Err(MetadataPatchError::Unsupported("permissions.group"));
+			}
+		}
+
+		#[cfg(unix)]
+		{
+			use std::os::unix::fs::MetadataExt;
+
+			let original = std::fs::metadata(path)?;
+			let restore = |err: io::Error| {
+				let _ = std::os::unix::fs::chown(path, Some(original.uid()), Some(original.gid()));
+				let _ = std::fs::set_permissions(path, original.permissions());
+				MetadataPatchError::Io(err)
+			};
+			if uid.is_some() || gid.is_some() {
+				std::os::unix::fs::chown(path, uid, gid)?;
+			}
+			// Changing the owner may clear the setuid and setgid bits, so the mode is set afterwards.
+			if let Some(mode) = permissions.mode {
+				std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+					.map_err(restore)?;
+			}
+			self.set_times(path, &original).map_err(restore)?;
+		}
+		#[cfg(not(unix))]
+		self.set_times(path)?;
+		Ok(())
+	}
+
+	// `utimensat` works on the path, so the file doesn't have to be readable or writable,
+	// only owned by the current user. Times that aren't being changed are kept as they were.
+	#[cfg(unix)]
+	fn set_times(&self, path: &Path, original: &std::fs::Metadata) -> io::Result<()> {
+		use nix::sys::stat::{utimensat, UtimensatFlags};
+		use std::os::unix::fs::MetadataExt;
+
+		if self.updated.is_none() && self.accessed.is_none() {
+			return Ok(());
+		}
+		let from_time =
+			|time: OffsetDateTime| timespec(time.unix_timestamp(), i64::from(time.nanosecond()));
+		let accessed = match self.accessed {
+			Some(accessed) => from_time(accessed)?,
+			None => timespec(original.atime(), original.atime_nsec())?,
+		};
+		let updated = match self.updated {
+			Some(updated) => from_time(updated)?,
+			None => timespec(original.mtime(), original.mtime_nsec())?,
+		};
+		utimensat(
+			None,
+			path,
+			&accessed,
+			&updated,
+			UtimensatFlags::FollowSymlink,
+		)?;
+		Ok(())
+	}
+
+	// Setting times only needs `FILE_WRITE_ATTRIBUTES`, which is granted even for read-only files,
+	// and backup semantics are needed to open directories at all.
+	#[cfg(not(unix))]
+	fn set_times(&self, path: &Path) -> io::Result<()> {
+		if self.updated.is_none() && self.accessed.is_none() {
+			return Ok(());
+		}
+		let mut times = FileTimes::new();
+		if let Some(updated) = self.updated {
+			times = times.set_modified(SystemTime::from(updated));
+		}
+		if let Some(accessed) = self.accessed {
+			times = times.set_accessed(SystemTime::from(accessed));
+		}
+		let mut options = File::options();
+		#[cfg(windows)]
+		{
+			use std::os::windows::fs::OpenOptionsExt;
+
+			const FILE_WRITE_ATTRIBUTES: u32 = 0x0100;
+			const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+			options
+				.access_mode(FILE_WRITE_ATTRIBUTES)
+				.custom_flags(FILE_FLAG_BACKUP_SEMANTICS);
+		}
+		#[cfg(not(windows))]
+		options.write(true);
+		options.open(path)?.set_times(times)
+	}
+}
+
+#[cfg(unix)]
+fn timespec(seconds: i64, nanoseconds: i64) -> io::Result<nix::sys::time::TimeSpec> {
+	use nix::sys::time::{TimeSpec, TimeValLike};
+
+	// `TimeSpec` panics outside of this range, and the seconds also have to fit in a `time_t`.
+	const MAX_SECONDS: i64 = i64::MAX / 1_000_000_000 - 1;
+	if !(-MAX_SECONDS..=MAX_SECONDS).contains(&seconds)
+		|| (std::mem::size_of::<nix::libc::time_t>() < 8 && i32::try_from(seconds).is_err())
+	{
+		return Err(io::Error::new(
+			io::ErrorKind::InvalidInput,
+			"time is out of range for this platform",
+		));
+	}
+	let mut timespec = TimeSpec::seconds(seconds);
+	timespec.as_mut().tv_nsec = nanoseconds as _;
+	Ok(timespec)
+}
+
+#[cfg(unix)]
+fn resolve_owner(owner: &PrincipalChange<FileOwner>) -> Result<u32, MetadataPatchError> {
+	match owner {
+		PrincipalChange::Id(FileOwner::Unix { uid, .. }) => Ok(*uid),
+		PrincipalChange::Id(FileOwner::Windows { .. }) => {
+			Err(MetadataPatchError::Unsupported("permissions.owner.sid"))
+		}
+		PrincipalChange::Name { name } => User::from_name(name)
+			.ok()
+			.flatten()
+			.map(|user| user.uid.as_raw())
+			.ok_or_else(|| MetadataPatchError::UnknownOwner(name.clone())),
+	}
+}
+
+#[cfg(unix)]
+fn resolve_group(group: &PrincipalChange<FileGroup>) -> Result<u32, MetadataPatchError> {
+	match group {
+		PrincipalChange::Id(FileGroup::Unix { gid, .. }) => Ok(*gid),
+		PrincipalChange::Id(FileGroup::Windows { .. }) => {
+			Err(MetadataPatchError::Unsupported("permissions.group.sid"))
+		}
+		PrincipalChange::Name { name } => Group::from_name(name)
+			.ok()
+			.flatten()
+			.map(|group| group.gid.as_raw())
+			.ok_or_else(|| MetadataPatchError::UnknownGroup(name.clone())),
+	}
+}
+
+/// The error returned when a [`MetadataPatch`] can't be applied.
+#[derive(Debug)]
+pub enum MetadataPatchError {
+	/// The backend can't change this field. Contains the path of the field in the JSON object,
+	/// such as `permissions.mode`.
+	Unsupported(&'static str),
+	/// No user with this name exists.
+	UnknownOwner(String),
+	/// No group with this name exists.
+	UnknownGroup(String),
+	/// The mode has bits set other than the permission, setuid, setgid and sticky bits (`0o7777`).
+	InvalidMode(u32),
+	/// Changing the metadata failed.
+	Io(io::Error),
+}
+
+impl fmt::Display for MetadataPatchError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			MetadataPatchError::Unsupported(field) => {
+				write!(f, "the field '{}' can't be changed on this backend", field)
+			}
+			MetadataPatchError::UnknownOwner(name) => write!(f, "unknown user '{}'", name),
+			MetadataPatchError::UnknownGroup(name) => write!(f, "unknown group '{}'", name),
+			MetadataPatchError::InvalidMode(mode) => write!(
+				f,
+				"invalid mode {:o}: only the bits in 7777 can be set, found {:o}",
+				mode,
+				mode & !0o7777
+			),
+			MetadataPatchError::Io(err) => write!(f, "failed to change metadata: {}", err),
+		}
+	}
+}
+
+impl std::error::Error for MetadataPatchError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			MetadataPatchError::Io(err) => Some(err),
+			_ => None,
+		}
+	}
+}
+
+impl From<io::Error> for MetadataPatchError {
+	#[inline]
+	fn from(err: io::Error) -> Self {
+		MetadataPatchError::Io(err)
+	}
+}
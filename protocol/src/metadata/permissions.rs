@@ -1,23 +1,31 @@
+use super::{
+	acl::{Access, AclEntry, AclTag},
+	names::{self, NameCache},
+	principal::PrincipalFields,
+};
 use faccess::PathExt;
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 use std::{io, path::Path};
 
-#[cfg(unix)]
-use nix::unistd::{Gid, Group, Uid, User};
 #[cfg(unix)]
 use std::os::unix::fs::MetadataExt;
 
 #[cfg(windows)]
 use windows_permissions::{
 	constants::{SeObjectType, SecurityInformation},
-	wrappers::{GetNamedSecurityInfo, LookupAccountSid},
+	wrappers::GetNamedSecurityInfo,
+	Sid,
 };
 
 /// Describes the user that owns a file, including their unique identifier
 /// and visible name.
+///
+/// Serialized with a `kind` field of either `unix` or `windows`.
+/// Objects without a `kind` field are also accepted, in which case the kind
+/// is inferred from whether a `uid` or a `sid` is present.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
-#[serde(untagged)]
+#[serde(tag = "kind", rename_all = "lowercase", try_from = "PrincipalFields")]
 pub enum FileOwner {
 	Unix {
 		/// The user's unique identifier.
@@ -40,8 +48,12 @@ pub enum FileOwner {
 
 /// Describes the group that owns a file, including their unique identifier
 /// and visible name.
+///
+/// Serialized with a `kind` field of either `unix` or `windows`.
+/// Objects without a `kind` field are also accepted, in which case the kind
+/// is inferred from whether a `gid` or a `sid` is present.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
-#[serde(untagged)]
+#[serde(tag = "kind", rename_all = "lowercase", try_from = "PrincipalFields")]
 pub enum FileGroup {
 	Unix {
 		/// The group's unique identifier.
@@ -64,82 +76,97 @@ pub enum FileGroup {
 
 #[skip_serializing_none]
 #[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "kebab-case")]
 pub struct FilePermissions {
 	read: bool,
 	write: bool,
 	execute: bool,
+	mode: Option<u32>,
 	owner: Option<FileOwner>,
 	group: Option<FileGroup>,
+	acl: Option<Vec<AclEntry>>,
 }
 
 impl FilePermissions {
-	#[cfg(unix)]
+	/// Reads the permissions of the file at `path`, following symlinks.
+	///
+	/// The owner and group names are looked up every time.
+	/// When reading the permissions of many files, use [`from_path_cached`](Self::from_path_cached) instead.
 	pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
-		let path = path.as_ref();
+		Self::read_path(path.as_ref(), None)
+	}
+
+	/// Reads the permissions of the file at `path`, following symlinks,
+	/// looking up the owner and group names through `names`.
+	pub fn from_path_cached<P: AsRef<Path>>(path: P, names: &NameCache) -> io::Result<Self> {
+		Self::read_path(path.as_ref(), Some(names))
+	}
+
+	#[cfg(unix)]
+	fn read_path(path: &Path, names: Option<&NameCache>) -> io::Result<Self> {
 		let metadata = path.metadata()?;
-		let uid = metadata.uid();
-		let owner = User::from_uid(Uid::from_raw(uid))
-			.ok()
-			.flatten()
-			.map(|user| FileOwner::Unix {
-				uid: user.uid.as_raw(),
-				name: user.name,
-			});
-		let group = Group::from_gid(Gid::from_raw(metadata.gid()))
-			.ok()
-			.flatten()
-			.map(|group| FileGroup::Unix {
-				gid: group.gid.as_raw(),
-				name: group.name,
-			});
+		let (uid, gid) = (metadata.uid(), metadata.gid());
+		let (user_name, group_name) = match names {
+			Some(names) => (names.user_name(uid), names.group_name(gid)),
+			None => (names::lookup_user(uid), names::lookup_group(gid)),
+		};
 		Ok(Self {
 			read: path.readable(),
 			write: path.writable(),
 			execute: path.executable(),
-			owner,
-			group,
+			mode: Some(metadata.mode() & 0o7777),
+			// The ids are known even if they have no name, such as for files from another system.
+			owner: Some(FileOwner::Unix {
+				uid,
+				name: user_name.unwrap_or_default(),
+			}),
+			group: Some(FileGroup::Unix {
+				gid,
+				name: group_name.unwrap_or_default(),
+			}),
+			acl: xattr::get(path, super::acl::ACL_XATTR)
+				.ok()
+				.flatten()
+				.and_then(|acl| AclEntry::parse_xattr(&acl)),
 		})
 	}
 
 	#[cfg(windows)]
-	pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
-		let path = path.as_ref();
+	fn read_path(path: &Path, names: Option<&NameCache>) -> io::Result<Self> {
 		let info = GetNamedSecurityInfo(
 			path,
 			SeObjectType::SE_FILE_OBJECT,
 			SecurityInformation::Group | SecurityInformation::Owner,
 		)?;
+		let lookup = |sid: &Sid| match names {
+			Some(names) => names.account_name(sid),
+			None => names::lookup_account(sid),
+		};
 		let owner = info.owner().map(|sid| {
-			let (domain, name) = LookupAccountSid(sid).unwrap_or_default();
+			let (domain, name) = lookup(sid);
 			FileOwner::Windows {
 				sid: sid.to_string(),
-				domain: domain
-					.into_string()
-					.unwrap_or_else(|osstr| osstr.to_string_lossy().into_owned()),
-				name: name
-					.into_string()
-					.unwrap_or_else(|osstr| osstr.to_string_lossy().into_owned()),
+				domain,
+				name,
 			}
 		});
 		let group = info.group().map(|sid| {
-			let (domain, name) = LookupAccountSid(sid).unwrap_or_default();
+			let (domain, name) = lookup(sid);
 			FileGroup::Windows {
 				sid: sid.to_string(),
-				domain: domain
-					.into_string()
-					.unwrap_or_else(|osstr| osstr.to_string_lossy().into_owned()),
-				name: name
-					.into_string()
-					.unwrap_or_else(|osstr| osstr.to_string_lossy().into_owned()),
+				domain,
+				name,
 			}
 		});
 		Ok(Self {
 			read: path.readable(),
 			write: path.writable(),
 			execute: path.executable(),
+			mode: None,
 			owner,
 			group,
+			acl: None,
 		})
 	}
 
@@ -190,6 +217,27 @@ impl FilePermissions {
 		Self { execute, ..self }
 	}
 
+	/// Returns the Unix mode bits of this file, if any,
+	/// including the setuid, setgid and sticky bits, but not the file type.
+	///
+	/// This is always `None` on Windows.
+	///
+	/// Represented as a number in JSON, with the key `mode`.
+	#[inline]
+	pub fn mode(&self) -> Option<u32> {
+		self.mode
+	}
+
+	/// Duplicates this permissions object,
+	/// setting the mode bits to `mode`.
+	#[inline]
+	pub fn with_mode(self, mode: impl Into<Option<u32>>) -> Self {
+		Self {
+			mode: mode.into(),
+			..self
+		}
+	}
+
 	/// Returns the owner of this file, if any.
 	///
 	/// Represented as an object in JSON, with the key `owner`.
@@ -225,4 +273,81 @@ impl FilePermissions {
 			..self
 		}
 	}
+
+	/// Returns the POSIX access control list of this file, if it has one
+	/// beyond what its mode bits already describe.
+	///
+	/// Represented as an array of [`AclEntry`] objects in JSON, with the key `acl`.
+	#[inline]
+	pub fn acl(&self) -> Option<&[AclEntry]> {
+		self.acl.as_deref()
+	}
+
+	/// Duplicates this permissions object,
+	/// setting the access control list to `acl`.
+	#[inline]
+	pub fn with_acl(self, acl: impl Into<Option<Vec<AclEntry>>>) -> Self {
+		Self {
+			acl: acl.into(),
+			..self
+		}
+	}
+
+	/// Returns if the Unix user `uid`, who is a member of the groups `gids`, is granted `access` to this file,
+	/// following the POSIX ACL access check algorithm if the file has an ACL, or the mode bits otherwise.
+	///
+	/// The superuser is not special-cased. Returns `None` if neither an ACL nor mode bits are known,
+	/// such as on Windows.
+	pub fn allows(&self, uid: u32, gids: &[u32], access: Access) -> Option<bool> {
+		let owner = match self.owner {
+			Some(FileOwner::Unix { uid, .. }) => Some(uid),
+			_ => None,
+		};
+		let group = match self.group {
+			Some(FileGroup::Unix { gid, .. }) => Some(gid),
+			_ => None,
+		};
+		if let Some(acl) = &self.acl {
+			let find = |tag: AclTag, id: Option<u32>| {
+				acl.iter()
+					.find(|entry| entry.tag() == tag && (id.is_none() || entry.id() == id))
+			};
+			let masked = |entry: &AclEntry| {
+				entry.grants(access)
+					&& find(AclTag::Mask, None).is_none_or(|mask| mask.grants(access))
+			};
+			if owner == Some(uid) {
+				return Some(find(AclTag::UserObj, None).is_some_and(|entry| entry.grants(access)));
+			}
+			if let Some(entry) = find(AclTag::User, Some(uid)) {
+				return Some(masked(entry));
+			}
+			let mut groups = acl
+				.iter()
+				.filter(|entry| match entry.tag() {
+					AclTag::GroupObj => group.is_some_and(|gid| gids.contains(&gid)),
+					AclTag::Group => entry.id().is_some_and(|gid| gids.contains(&gid)),
+					_ => false,
+				})
+				.peekable();
+			if groups.peek().is_some() {
+				return Some(groups.any(masked));
+			}
+			return Some(find(AclTag::Other, None).is_some_and(|entry| entry.grants(access)));
+		}
+		let mode = self.mode?;
+		let bit = match access {
+			Access::Read => 4,
+			Access::Write => 2,
+			Access::Execute => 1,
+		};
+		let shift = if owner == Some(uid) {
+			6
+		} else if group.is_some_and(|gid| gids.contains(&gid)) {
+			3
+		} else {
+			0
+		};
+		Some(mode >> shift & bit != 0)
+	}
 }
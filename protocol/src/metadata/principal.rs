@@ -0,0 +1,292 @@
+use super::{FileGroup, FileOwner};
+use serde::{Deserialize, Serialize};
+
+/// The kind of platform a [`FileOwner`] or [`FileGroup`] comes from.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum PrincipalKind {
+	/// A Unix-like platform, where principals are identified by a numeric id.
+	///
+	/// Represented as the string `unix` in JSON.
+	Unix,
+	/// Windows, where principals are identified by a security identifier (SID).
+	///
+	/// Represented as the string `windows` in JSON.
+	Windows,
+}
+
+/// Every field a [`FileOwner`] or [`FileGroup`] may have,
+/// so they can be validated with precise errors instead of an untagged enum's
+/// "data did not match any variant".
+///
+/// Unknown fields are ignored, so principals from newer servers still parse.
+#[derive(Debug, Deserialize)]
+pub(super) struct PrincipalFields {
+	kind: Option<PrincipalKind>,
+	uid: Option<u32>,
+	gid: Option<u32>,
+	sid: Option<String>,
+	#[serde(default)]
+	domain: String,
+	#[serde(default)]
+	name: String,
+}
+
+impl PrincipalFields {
+	/// Works out which kind of principal these fields describe, given the name of the numeric id field.
+	fn kind(&self, id_field: &str, id: Option<u32>) -> Result<PrincipalKind, String> {
+		let kind = match (self.kind, id, &self.sid) {
+			(Some(kind), _, _) => kind,
+			(None, Some(_), Some(_)) => {
+				return Err(format!(
+					"both `{}` and `sid` are present, add a `kind` field to disambiguate",
+					id_field
+				))
+			}
+			(None, Some(_), None) => PrincipalKind::Unix,
+			(None, None, Some(_)) => PrincipalKind::Windows,
+			(None, None, None) => return Err(format!("missing field `{}` or `sid`", id_field)),
+		};
+		match kind {
+			PrincipalKind::Unix if id.is_none() => {
+				Err(format!("missing field `{}` for kind `unix`", id_field))
+			}
+			PrincipalKind::Unix if self.sid.is_some() => {
+				Err("field `sid` is not valid for kind `unix`".to_string())
+			}
+			PrincipalKind::Unix if !self.domain.is_empty() => {
+				Err("field `domain` is not valid for kind `unix`".to_string())
+			}
+			PrincipalKind::Windows if self.sid.is_none() => {
+				Err("missing field `sid` for kind `windows`".to_string())
+			}
+			PrincipalKind::Windows if id.is_some() => Err(format!(
+				"field `{}` is not valid for kind `windows`",
+				id_field
+			)),
+			kind => Ok(kind),
+		}
+	}
+}
+
+impl TryFrom<PrincipalFields> for FileOwner {
+	type Error = String;
+
+	fn try_from(fields: PrincipalFields) -> Result<Self, Self::Error> {
+		if fields.gid.is_some() {
+			return Err("unknown field `gid`, owners have a `uid`".to_string());
+		}
+		Ok(match fields.kind("uid", fields.uid)? {
+			PrincipalKind::Unix => FileOwner::Unix {
+				uid: fields.uid.unwrap_or_default(),
+				name: fields.name,
+			},
+			PrincipalKind::Windows => FileOwner::Windows {
+				sid: fields.sid.unwrap_or_default(),
+				domain: fields.domain,
+				name: fields.name,
+			},
+		})
+	}
+}
+
+impl TryFrom<PrincipalFields> for FileGroup {
+	type Error = String;
+
+	fn try_from(fields: PrincipalFields) -> Result<Self, Self::Error> {
+		if fields.uid.is_some() {
+			return Err("unknown field `uid`, groups have a `gid`".to_string());
+		}
+		Ok(match fields.kind("gid", fields.gid)? {
+			PrincipalKind::Unix => FileGroup::Unix {
+				gid: fields.gid.unwrap_or_default(),
+				name: fields.name,
+			},
+			PrincipalKind::Windows => FileGroup::Windows {
+				sid: fields.sid.unwrap_or_default(),
+				domain: fields.domain,
+				name: fields.name,
+			},
+		})
+	}
+}
+
+impl FileOwner {
+	/// The kind of platform this user comes from.
+	///
+	/// Represented as a string in JSON, with the key `kind`. See [`PrincipalKind`].
+	#[inline]
+	pub fn kind(&self) -> PrincipalKind {
+		match self {
+			FileOwner::Unix { .. } => PrincipalKind::Unix,
+			FileOwner::Windows { .. } => PrincipalKind::Windows,
+		}
+	}
+
+	/// The user's unique identifier as a string, regardless of platform:
+	/// the uid on Unix-like platforms, or the SID on Windows.
+	pub fn id_string(&self) -> String {
+		match self {
+			FileOwner::Unix { uid, .. } => uid.to_string(),
+			FileOwner::Windows { sid, .. } => sid.clone(),
+		}
+	}
+
+	/// A human-readable name for the user, regardless of platform.
+	/// This is `DOMAIN\name` on Windows if the domain is known,
+	/// and falls back to the [`id_string`](Self::id_string) if the name isn't known.
+	pub fn display_name(&self) -> String {
+		match self {
+			FileOwner::Unix { name, .. } | FileOwner::Windows { name, .. } if name.is_empty() => {
+				self.id_string()
+			}
+			FileOwner::Windows { domain, name, .. } if !domain.is_empty() => {
+				format!("{}\\{}", domain, name)
+			}
+			FileOwner::Unix { name, .. } | FileOwner::Windows { name, .. } => name.clone(),
+		}
+	}
+}
+
+impl FileGroup {
+	/// The kind of platform this group comes from.
+	///
+	/// Represented as a string in JSON, with the key `kind`. See [`PrincipalKind`].
+	#[inline]
+	pub fn kind(&self) -> PrincipalKind {
+		match self {
+			FileGroup::Unix { .. } => PrincipalKind::Unix,
+			FileGroup::Windows { .. } => PrincipalKind::Windows,
+		}
+	}
+
+	/// The group's unique identifier as a string, regardless of platform:
+	/// the gid on Unix-like platforms, or the SID on Windows.
+	pub fn id_string(&self) -> String {
+		match self {
+			FileGroup::Unix { gid, .. } => gid.to_string(),
+			FileGroup::Windows { sid, .. } => sid.clone(),
+		}
+	}
+
+	/// A human-readable name for the group, regardless of platform.
+	/// This is `DOMAIN\name` on Windows if the domain is known,
+	/// and falls back to the [`id_string`](Self::id_string) if the name isn't known.
+	pub fn display_name(&self) -> String {
+		match self {
+			FileGroup::Unix { name, .. } | FileGroup::Windows { name, .. } if name.is_empty() => {
+				self.id_string()
+			}
+			FileGroup::Windows { domain, name, .. } if !domain.is_empty() => {
+				format!("{}\\{}", domain, name)
+			}
+			FileGroup::Unix { name, .. } | FileGroup::Windows { name, .. } => name.clone(),
+		}
+	}
+}
+
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for FileOwner {
+	fn schema_name() -> std::borrow::Cow<'static, str> {
+		"FileOwner".into()
+	}
+
+	fn json_schema(generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+		principal_schema(generator, "uid", "user")
+	}
+}
+
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for FileGroup {
+	fn schema_name() -> std::borrow::Cow<'static, str> {
+		"FileGroup".into()
+	}
+
+	fn json_schema(generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+		principal_schema(generator, "gid", "group")
+	}
+}
+
+/// The schema of a [`FileOwner`] or [`FileGroup`], written by hand as the derived one would describe
+/// [`PrincipalFields`], which accepts more than it allows.
+/// The `kind` field is only optional when deserializing, and unknown fields are only accepted then,
+/// as long as they aren't fields of the other kind of principal.
+#[cfg(feature = "schema")]
+fn principal_schema(
+	generator: &mut schemars::SchemaGenerator,
+	id_field: &str,
+	noun: &str,
+) -> schemars::Schema {
+	let serialize = generator.contract().is_serialize();
+	let required = |id: &str| {
+		if serialize {
+			serde_json::json!(["kind", id])
+		} else {
+			serde_json::json!([id])
+		}
+	};
+	let other_id = if id_field == "uid" { "gid" } else { "uid" };
+	let closed = |invalid: &[&str]| -> (&'static str, serde_json::Value) {
+		if serialize {
+			("additionalProperties", serde_json::json!(false))
+		} else {
+			let invalid = invalid
+				.iter()
+				.map(|field| serde_json::json!({ "required": [field] }))
+				.collect::<Vec<_>>();
+			("not", serde_json::json!({ "anyOf": invalid }))
+		}
+	};
+	let (unix_key, unix_value) = closed(&["sid", "domain", other_id]);
+	let (windows_key, windows_value) = closed(&[id_field, other_id]);
+	schemars::json_schema!({
+		"description": format!(
+			"Describes the {} that owns a file, including their unique identifier and visible name.\n\n\
+			Serialized with a `kind` field of either `unix` or `windows`. \
+			Objects without a `kind` field are also accepted, in which case the kind \
+			is inferred from whether a `{}` or a `sid` is present.",
+			noun, id_field
+		),
+		"oneOf": [
+			{
+				"type": "object",
+				"properties": {
+					"kind": { "type": "string", "const": "unix" },
+					id_field: {
+						"description": format!("The {}'s unique identifier.", noun),
+						"type": "integer",
+						"format": "uint32",
+						"minimum": 0
+					},
+					"name": {
+						"description": format!("The {}'s visible name.", noun),
+						"type": "string"
+					}
+				},
+				"required": required(id_field),
+				unix_key: unix_value
+			},
+			{
+				"type": "object",
+				"properties": {
+					"kind": { "type": "string", "const": "windows" },
+					"sid": {
+						"description": format!("The security identifier (SID) of the {}.", noun),
+						"type": "string"
+					},
+					"domain": {
+						"description": format!("The {}'s domain.", noun),
+						"type": "string"
+					},
+					"name": {
+						"description": format!("The {}'s visible name.", noun),
+						"type": "string"
+					}
+				},
+				"required": required("sid"),
+				windows_key: windows_value
+			}
+		]
+	})
+}
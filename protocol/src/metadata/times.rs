@@ -0,0 +1,54 @@
+use std::fs;
+use time::OffsetDateTime;
+
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+#[cfg(target_os = "linux")]
+use std::{ffi::CString, mem::MaybeUninit, os::unix::ffi::OsStrExt, path::Path};
+
+/// Reads the birth time of the file at `path` with `statx`,
+/// returning `None` if the kernel or filesystem doesn't support it.
+#[cfg(target_os = "linux")]
+pub(super) fn birth_time(path: &Path) -> Option<OffsetDateTime> {
+	let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+	let mut statx = MaybeUninit::<libc::statx>::zeroed();
+	// SAFETY: `path` is a valid NUL-terminated string, and `statx` is a valid buffer for the kernel to write to.
+	let result = unsafe {
+		libc::statx(
+			libc::AT_FDCWD,
+			path.as_ptr(),
+			libc::AT_STATX_SYNC_AS_STAT,
+			libc::STATX_BTIME,
+			statx.as_mut_ptr(),
+		)
+	};
+	if result != 0 {
+		return None;
+	}
+	// SAFETY: statx succeeded, so it filled in the buffer.
+	let statx = unsafe { statx.assume_init() };
+	if statx.stx_mask & libc::STATX_BTIME == 0 {
+		return None;
+	}
+	from_parts(statx.stx_btime.tv_sec, i64::from(statx.stx_btime.tv_nsec))
+}
+
+/// Reads the time the file's metadata last changed, known as `ctime`.
+#[cfg(unix)]
+pub(super) fn change_time(metadata: &fs::Metadata) -> Option<OffsetDateTime> {
+	from_parts(metadata.ctime(), metadata.ctime_nsec())
+}
+
+/// Windows has no equivalent to `ctime`.
+#[cfg(not(unix))]
+pub(super) fn change_time(_metadata: &fs::Metadata) -> Option<OffsetDateTime> {
+	None
+}
+
+#[cfg(unix)]
+fn from_parts(seconds: i64, nanoseconds: i64) -> Option<OffsetDateTime> {
+	OffsetDateTime::from_unix_timestamp_nanos(
+		i128::from(seconds) * 1_000_000_000 + i128::from(nanoseconds),
+	)
+	.ok()
+}
@@ -0,0 +1,256 @@
+use crate::encoding::{deserialize_base64, serialize_base64};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt, io, path::Path};
+
+/// The query parameters accepted by the metadata and directory listing endpoints,
+/// for including extended attributes.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct XattrQuery {
+	#[serde(default)]
+	xattrs: Option<bool>,
+}
+
+impl XattrQuery {
+	/// Whether [`Metadata::xattrs`](super::Metadata::xattrs) is filled in. Defaults to `false`,
+	/// as reading extended attributes requires extra system calls for every file.
+	///
+	/// Represented as a bool in the query string, with the key `xattrs`.
+	#[inline]
+	pub fn xattrs(&self) -> bool {
+		self.xattrs.unwrap_or(false)
+	}
+
+	/// Duplicates this query object,
+	/// updating the xattrs flag with the specified value.
+	#[inline]
+	pub fn with_xattrs(self, xattrs: impl Into<Option<bool>>) -> Self {
+		Self {
+			xattrs: xattrs.into(),
+		}
+	}
+}
+
+/// The body of a request to set a single extended attribute, and the response to reading one.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Xattr {
+	name: String,
+	#[serde(
+		serialize_with = "serialize_base64",
+		deserialize_with = "deserialize_base64"
+	)]
+	#[cfg_attr(feature = "schema", schemars(with = "crate::schema::Base64"))]
+	value: Vec<u8>,
+}
+
+impl Xattr {
+	/// Creates a new extended attribute object.
+	#[inline]
+	pub fn new(name: impl ToString, value: impl Into<Vec<u8>>) -> Self {
+		Self {
+			name: name.to_string(),
+			value: value.into(),
+		}
+	}
+
+	/// The full name of the attribute, including its namespace, such as `user.provenance`.
+	///
+	/// Represented as a string in JSON, with the key `name`.
+	#[inline]
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	/// The value of the attribute.
+	///
+	/// Represented as a base64-encoded string in JSON, with the key `value`.
+	#[inline]
+	pub fn value(&self) -> &[u8] {
+		&self.value
+	}
+}
+
+/// The error returned when an extended attribute can't be accessed.
+#[derive(Debug)]
+pub enum XattrError {
+	/// The attribute's namespace is not allowed by the mount.
+	Forbidden(String),
+	/// Extended attributes aren't supported on this platform or filesystem.
+	Unsupported,
+	/// Accessing the attribute failed.
+	Io(io::Error),
+}
+
+impl fmt::Display for XattrError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			XattrError::Forbidden(name) => {
+				write!(
+					f,
+					"the extended attribute '{}' is not in an allowed namespace",
+					name
+				)
+			}
+			XattrError::Unsupported => f.write_str("extended attributes are not supported"),
+			XattrError::Io(err) => write!(f, "failed to access extended attribute: {}", err),
+		}
+	}
+}
+
+impl std::error::Error for XattrError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			XattrError::Io(err) => Some(err),
+			_ => None,
+		}
+	}
+}
+
+impl From<io::Error> for XattrError {
+	/// Filesystems without extended attributes fail with `ENOTSUP` or `EOPNOTSUPP`,
+	/// which the standard library doesn't map to [`io::ErrorKind::Unsupported`], so they're checked for explicitly.
+	fn from(err: io::Error) -> Self {
+		if err.kind() == io::ErrorKind::Unsupported || is_not_supported(&err) {
+			XattrError::Unsupported
+		} else {
+			XattrError::Io(err)
+		}
+	}
+}
+
+#[cfg(unix)]
+fn is_not_supported(err: &io::Error) -> bool {
+	use nix::errno::Errno;
+	// These are the same error on some platforms, but not all.
+	err.raw_os_error()
+		.is_some_and(|code| code == Errno::ENOTSUP as i32 || code == Errno::EOPNOTSUPP as i32)
+}
+
+#[cfg(not(unix))]
+fn is_not_supported(_err: &io::Error) -> bool {
+	false
+}
+
+/// Which extended attributes a mount exposes, by namespace.
+///
+/// Attributes outside the allowed namespaces are never listed,
+/// and attempts to read or change them are rejected.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct XattrPolicy {
+	namespaces: Vec<String>,
+}
+
+impl Default for XattrPolicy {
+	/// Only allows the `user` namespace, as the others (`trusted`, `security` and `system`)
+	/// are used by the operating system, and changing them may grant privileges.
+	#[inline]
+	fn default() -> Self {
+		Self {
+			namespaces: vec!["user".to_string()],
+		}
+	}
+}
+
+impl XattrPolicy {
+	/// Creates a policy allowing the given namespaces, such as `user` or `trusted`.
+	pub fn new<S: ToString>(namespaces: impl IntoIterator<Item = S>) -> Self {
+		Self {
+			namespaces: namespaces
+				.into_iter()
+				.map(|namespace| namespace.to_string())
+				.collect(),
+		}
+	}
+
+	/// The namespaces allowed by this policy.
+	#[inline]
+	pub fn namespaces(&self) -> &[String] {
+		&self.namespaces
+	}
+
+	/// Returns if the attribute with the given full name is allowed by this policy.
+	pub fn allows(&self, name: &str) -> bool {
+		name.split_once('.').is_some_and(|(namespace, rest)| {
+			!rest.is_empty() && self.namespaces.iter().any(|allowed| allowed == namespace)
+		})
+	}
+
+	fn check(&self, name: &str) -> Result<(), XattrError> {
+		if self.allows(name) {
+			Ok(())
+		} else {
+			Err(XattrError::Forbidden(name.to_string()))
+		}
+	}
+
+	/// Reads every allowed extended attribute of the file at `path`.
+	#[cfg(unix)]
+	pub fn list<P: AsRef<Path>>(&self, path: P) -> Result<BTreeMap<String, Vec<u8>>, XattrError> {
+		let path = path.as_ref();
+		let mut attributes = BTreeMap::new();
+		for name in xattr::list(path)? {
+			let Some(name) = name.to_str().filter(|name| self.allows(name)) else {
+				continue;
+			};
+			// The attribute may have been removed since it was listed.
+			if let Some(value) = xattr::get(path, name)? {
+				attributes.insert(name.to_string(), value);
+			}
+		}
+		Ok(attributes)
+	}
+
+	/// Reads a single extended attribute of the file at `path`, if it exists.
+	#[cfg(unix)]
+	pub fn get<P: AsRef<Path>>(&self, path: P, name: &str) -> Result<Option<Vec<u8>>, XattrError> {
+		self.check(name)?;
+		Ok(xattr::get(path, name)?)
+	}
+
+	/// Sets a single extended attribute of the file at `path`.
+	#[cfg(unix)]
+	pub fn set<P: AsRef<Path>>(&self, path: P, name: &str, value: &[u8]) -> Result<(), XattrError> {
+		self.check(name)?;
+		Ok(xattr::set(path, name, value)?)
+	}
+
+	/// Removes a single extended attribute of the file at `path`.
+	#[cfg(unix)]
+	pub fn remove<P: AsRef<Path>>(&self, path: P, name: &str) -> Result<(), XattrError> {
+		self.check(name)?;
+		Ok(xattr::remove(path, name)?)
+	}
+
+	/// Reads every allowed extended attribute of the file at `path`.
+	#[cfg(not(unix))]
+	pub fn list<P: AsRef<Path>>(&self, _path: P) -> Result<BTreeMap<String, Vec<u8>>, XattrError> {
+		Err(XattrError::Unsupported)
+	}
+
+	/// Reads a single extended attribute of the file at `path`, if it exists.
+	#[cfg(not(unix))]
+	pub fn get<P: AsRef<Path>>(&self, _path: P, name: &str) -> Result<Option<Vec<u8>>, XattrError> {
+		self.check(name)?;
+		Err(XattrError::Unsupported)
+	}
+
+	/// Sets a single extended attribute of the file at `path`.
+	#[cfg(not(unix))]
+	pub fn set<P: AsRef<Path>>(
+		&self,
+		_path: P,
+		name: &str,
+		_value: &[u8],
+	) -> Result<(), XattrError> {
+		self.check(name)?;
+		Err(XattrError::Unsupported)
+	}
+
+	/// Removes a single extended attribute of the file at `path`.
+	#[cfg(not(unix))]
+	pub fn remove<P: AsRef<Path>>(&self, _path: P, name: &str) -> Result<(), XattrError> {
+		self.check(name)?;
+		Err(XattrError::Unsupported)
+	}
+}
@@ -0,0 +1,666 @@
+use crate::{
+	archive::{ArchiveFormat, ArchiveQuery},
+	capabilities::{Capabilities, PROTOCOL_VERSION, WELL_KNOWN_PATH},
+	delta::{Delta, Signature, DEFAULT_BLOCK_SIZE, MAX_BLOCK_SIZE},
+	dir::{
+		DirectoryListEntry, DirectoryListQuery, DirectoryListing, DirectoryListingPage, TreeQuery,
+		NDJSON_CONTENT_TYPE,
+	},
+	error::ErrorResponse,
+	file::TYPE_HEADER,
+	format::{CBOR_CONTENT_TYPE, JSON_CONTENT_TYPE, MSGPACK_CONTENT_TYPE},
+	hash::{FileHash, HashQuery},
+	journal::{ChangeSet, ChangesQuery},
+	metadata::{Metadata, MetadataPatch, Xattr, XattrQuery},
+	schema::{SCHEMA_CONTENT_TYPE, SCHEMA_NAMES},
+	upload::{
+		UploadSession, UPLOAD_CONTENT_TYPE, UPLOAD_EXPIRES_HEADER, UPLOAD_LENGTH_HEADER,
+		UPLOAD_OFFSET_HEADER,
+	},
+	watch::{WatchEvent, WatchQuery, SSE_CONTENT_TYPE},
+	write::{SetLength, WriteQuery},
+};
+use schemars::{generate::SchemaSettings, JsonSchema, Schema};
+use serde_json::{json, Map, Value};
+use std::slice;
+
+/// The version of the OpenAPI specification the document follows.
+pub const OPENAPI_VERSION: &str = "3.0.3";
+
+/// Generates the OpenAPI document describing every endpoint of the protocol, as documented in [`api`](crate::api),
+/// with the schemas of every request and response body derived from this crate's types.
+///
+/// Streamed responses, such as tree listings and watch events, can't be fully described by OpenAPI 3.0,
+/// so their descriptions name the schema of each line or event instead.
+pub fn document() -> Value {
+	let mut generator = SchemaSettings::openapi3().for_serialize().into_generator();
+	let listing = generator.subschema_for::<DirectoryListing>();
+	let page = generator.subschema_for::<DirectoryListingPage>();
+	let entry = generator.subschema_for::<DirectoryListEntry>();
+	let metadata = generator.subschema_for::<Metadata>();
+	let metadata_patch = generator.subschema_for::<MetadataPatch>();
+	let error = generator.subschema_for::<ErrorResponse>();
+	let file_hash = generator.subschema_for::<FileHash>();
+	let set_length = generator.subschema_for::<SetLength>();
+	let change_set = generator.subschema_for::<ChangeSet>();
+	let signature = generator.subschema_for::<Signature>();
+	let delta = generator.subschema_for::<Delta>();
+	let xattr = generator.subschema_for::<Xattr>();
+	let upload_session = generator.subschema_for::<UploadSession>();
+	let capabilities = generator.subschema_for::<Capabilities>();
+	// Watch events are only ever sent as Server-Sent Events, so nothing references their schema directly.
+	generator.subschema_for::<WatchEvent>();
+	generator.subschema_for::<DirectoryListQuery>();
+	generator.subschema_for::<TreeQuery>();
+	generator.subschema_for::<ArchiveQuery>();
+	generator.subschema_for::<HashQuery>();
+	generator.subschema_for::<WatchQuery>();
+	generator.subschema_for::<ChangesQuery>();
+	generator.subschema_for::<WriteQuery>();
+	generator.subschema_for::<XattrQuery>();
+	let mut schemas = generator.take_definitions(true);
+	let list_query = query_parameters::<DirectoryListQuery>(&mut schemas);
+	let tree_query = query_parameters::<TreeQuery>(&mut schemas);
+	let archive_query = query_parameters::<ArchiveQuery>(&mut schemas);
+	let hash_query = query_parameters::<HashQuery>(&mut schemas);
+	let watch_query = query_parameters::<WatchQuery>(&mut schemas);
+	let changes_query = query_parameters::<ChangesQuery>(&mut schemas);
+	let write_query = query_parameters::<WriteQuery>(&mut schemas);
+	let xattr_query = query_parameters::<XattrQuery>(&mut schemas);
+
+	let location = vec![path_parameter("mount"), path_parameter("path")];
+	let mount = vec![path_parameter("mount")];
+	let upload = vec![path_parameter("mount"), path_parameter("id")];
+	let with =
+		|base: &[Value], extra: &[Value]| base.iter().chain(extra).cloned().collect::<Vec<_>>();
+	let if_match = header_parameter(
+		"If-Match",
+		"The `ETag` the file must have for the change to happen.",
+		false,
+	);
+	let repr_digest = header_parameter(
+		"Repr-Digest",
+		"The RFC 9530 digest the resulting file must have, which the server verifies before replacing the file.",
+		false,
+	);
+	let xattr_name = json!({
+		"name": "name",
+		"in": "query",
+		"required": true,
+		"description": "The full name of the attribute, including its namespace, such as `user.provenance`.",
+		"schema": { "type": "string" }
+	});
+
+	let errors = json!({
+		"description": "An error. See the documentation of each endpoint for which status codes it returns.",
+		"content": { JSON_CONTENT_TYPE: { "schema": error } }
+	});
+	let type_header = json!({
+		TYPE_HEADER: response_header("The type of the object at the requested path.")
+	});
+	let etag_header = json!({
+		"ETag": response_header("The new entity tag of the file.")
+	});
+	let upload_headers = json!({
+		UPLOAD_OFFSET_HEADER: response_header("The amount of bytes received so far."),
+		UPLOAD_LENGTH_HEADER: response_header("The total size of the file, if known."),
+		UPLOAD_EXPIRES_HEADER: response_header("The time the session expires if it receives no more data, as an HTTP date.")
+	});
+	let json_body = |schema: &Schema| json!({ JSON_CONTENT_TYPE: { "schema": schema } });
+	let binary_body = |content_type: &str| json!({ content_type: { "schema": binary() } });
+	let no_content = |description: &str| json!({ "description": description });
+	let head = |summary: &str, operation_id: &str, parameters: &[Value]| {
+		json!({
+			"summary": summary,
+			"operationId": operation_id,
+			"parameters": parameters,
+			"responses": {
+				"200": {
+					"description": "The headers a `GET` request would have returned, without a body.",
+					"headers": type_header
+				},
+				"default": errors
+			}
+		})
+	};
+
+	let archive_content = [ArchiveFormat::Tar, ArchiveFormat::TarGz, ArchiveFormat::Zip]
+		.iter()
+		.map(|format| {
+			(
+				format.content_type().to_string(),
+				json!({ "schema": binary() }),
+			)
+		})
+		.collect::<Map<_, _>>();
+	let content_range = header_parameter(
+		"Content-Range",
+		"The range of the file to write the body to, such as `bytes 100-199/*`. Required unless appending.",
+		false,
+	);
+	let block_size = json!({
+		"name": "block_size",
+		"in": "query",
+		"required": false,
+		"schema": {
+			"type": "integer",
+			"format": "uint32",
+			"minimum": 1,
+			"maximum": MAX_BLOCK_SIZE,
+			"default": DEFAULT_BLOCK_SIZE
+		}
+	});
+
+	let mut paths = Map::new();
+	let mut add = |path: String, operations: Value| {
+		paths.insert(path, operations);
+	};
+	add(
+		"/{mount}/archive/{path}".into(),
+		json!({
+			"get": {
+				"summary": "Download a directory as an archive.",
+				"operationId": "getArchive",
+				"parameters": with(&location, &archive_query),
+				"responses": {
+					"200": {
+						"description": "The archive, streamed as it is built.",
+						"headers": {
+							"Content-Disposition": response_header("A suggested file name for the archive.")
+						},
+						"content": archive_content
+					},
+					"default": errors
+				}
+			}
+		}),
+	);
+	add(
+		"/{mount}/changes".into(),
+		json!({
+			"get": {
+				"summary": "List the changes made to a mount since a sync token.",
+				"operationId": "getChanges",
+				"parameters": with(&mount, &changes_query),
+				"responses": {
+					"200": {
+						"description": "The changes since the token.",
+						"content": json_body(&change_set)
+					},
+					"default": errors
+				}
+			}
+		}),
+	);
+	add(
+		"/{mount}/delta/{path}".into(),
+		json!({
+			"post": {
+				"summary": "Rebuild a file from a delta against its current contents.",
+				"operationId": "postDelta",
+				"parameters": with(&location, &[if_match.clone(), repr_digest.clone()]),
+				"requestBody": {
+					"required": true,
+					"content": json_body(&delta)
+				},
+				"responses": {
+					"204": {
+						"description": "The file was rebuilt.",
+						"headers": etag_header
+					},
+					"default": errors
+				}
+			}
+		}),
+	);
+	add(
+		"/{mount}/dir/{path}".into(),
+		json!({
+			"get": {
+				"summary": "List the contents of a directory.",
+				"operationId": "getDir",
+				"parameters": with(&location, &list_query),
+				"responses": {
+					"200": {
+						"description": "The directory listing, paginated if `limit` or `cursor` was given. \
+							If NDJSON was requested, each line is a directory entry instead.",
+						"headers": type_header,
+						"content": {
+							JSON_CONTENT_TYPE: {
+								"schema": { "oneOf": [listing, page] }
+							},
+							NDJSON_CONTENT_TYPE: {
+								"schema": entry
+							},
+							CBOR_CONTENT_TYPE: {
+								"schema": { "oneOf": [listing, page] }
+							},
+							MSGPACK_CONTENT_TYPE: {
+								"schema": { "oneOf": [listing, page] }
+							}
+						}
+					},
+					"204": no_content("The directory is empty."),
+					"default": errors
+				}
+			},
+			"head": head("Get the headers of a directory listing.", "headDir", &location)
+		}),
+	);
+	add(
+		"/{mount}/file/{path}".into(),
+		json!({
+			"get": {
+				"summary": "Download a file.",
+				"operationId": "getFile",
+				"parameters": location,
+				"responses": {
+					"200": {
+						"description": "The contents of the file.",
+						"headers": type_header,
+						"content": binary_body("application/octet-stream")
+					},
+					"206": {
+						"description": "The requested range of the file.",
+						"content": binary_body("application/octet-stream")
+					},
+					"default": errors
+				}
+			},
+			"head": head("Get the headers of a file download.", "headFile", &location),
+			"patch": {
+				"summary": "Write to part of an existing file.",
+				"operationId": "patchFile",
+				"parameters": with(&with(&location, &write_query), &[content_range, if_match.clone()]),
+				"requestBody": {
+					"required": true,
+					"content": binary_body("application/octet-stream")
+				},
+				"responses": {
+					"204": {
+						"description": "The body was written.",
+						"headers": etag_header
+					},
+					"default": errors
+				}
+			}
+		}),
+	);
+	add(
+		"/{mount}/hash/{path}".into(),
+		json!({
+			"get": {
+				"summary": "Hash the contents of a file.",
+				"operationId": "getHash",
+				"parameters": with(&location, &hash_query),
+				"responses": {
+					"200": {
+						"description": "The hash of the file.",
+						"content": json_body(&file_hash)
+					},
+					"default": errors
+				}
+			}
+		}),
+	);
+	add(
+		"/{mount}/length/{path}".into(),
+		json!({
+			"post": {
+				"summary": "Truncate or extend a file.",
+				"operationId": "postLength",
+				"parameters": with(&location, &[if_match]),
+				"requestBody": {
+					"required": true,
+					"content": json_body(&set_length)
+				},
+				"responses": {
+					"204": {
+						"description": "The length of the file was set.",
+						"headers": etag_header
+					},
+					"default": errors
+				}
+			}
+		}),
+	);
+	add(
+		"/{mount}/metadata/{path}".into(),
+		json!({
+			"get": {
+				"summary": "Get the metadata of a file or directory.",
+				"operationId": "getMetadata",
+				"parameters": with(&location, &xattr_query),
+				"responses": {
+					"200": {
+						"description": "The metadata of the object at the requested path.",
+						"headers": type_header,
+						"content": json_body(&metadata)
+					},
+					"default": errors
+				}
+			},
+			"head": head("Get the headers of a metadata request.", "headMetadata", &location),
+			"patch": {
+				"summary": "Change the metadata of a file or directory.",
+				"operationId": "patchMetadata",
+				"parameters": location,
+				"requestBody": {
+					"required": true,
+					"content": json_body(&metadata_patch)
+				},
+				"responses": {
+					"200": {
+						"description": "The metadata was changed.",
+						"content": json_body(&metadata)
+					},
+					"default": errors
+				}
+			}
+		}),
+	);
+	add(
+		"/{mount}/signature/{path}".into(),
+		json!({
+			"get": {
+				"summary": "Get the block signature of a file, to compute a delta against.",
+				"operationId": "getSignature",
+				"parameters": with(&location, &[block_size]),
+				"responses": {
+					"200": {
+						"description": "The signature of the file.",
+						"headers": {
+							"ETag": response_header("The entity tag of the file the signature was computed from.")
+						},
+						"content": json_body(&signature)
+					},
+					"default": errors
+				}
+			}
+		}),
+	);
+	add(
+		"/{mount}/tree/{path}".into(),
+		json!({
+			"get": {
+				"summary": "List the contents of a directory and all of its subdirectories.",
+				"operationId": "getTree",
+				"parameters": with(&location, &tree_query),
+				"responses": {
+					"200": {
+						"description": "The tree listing, streamed with one directory entry per line. \
+							A listing cut short by `limit` ends with a `{\"truncated\":true}` line.",
+						"headers": type_header,
+						"content": {
+							NDJSON_CONTENT_TYPE: { "schema": entry }
+						}
+					},
+					"default": errors
+				}
+			},
+			"head": head("Get the headers of a tree listing.", "headTree", &location)
+		}),
+	);
+	add(
+		"/{mount}/upload/{path}".into(),
+		json!({
+			"post": {
+				"summary": "Create a resumable upload session.",
+				"operationId": "createUpload",
+				"parameters": with(&location, &[header_parameter(
+					UPLOAD_LENGTH_HEADER,
+					"The total size of the file, if known.",
+					false,
+				)]),
+				"responses": {
+					"201": {
+						"description": "The session was created.",
+						"headers": {
+							"Location": response_header("The URL of the session.")
+						},
+						"content": json_body(&upload_session)
+					},
+					"default": errors
+				}
+			}
+		}),
+	);
+	add(
+		"/{mount}/uploads/{id}".into(),
+		json!({
+			"get": {
+				"summary": "Get the state of an upload session.",
+				"operationId": "getUpload",
+				"parameters": upload,
+				"responses": {
+					"200": {
+						"description": "The session exists.",
+						"headers": upload_headers,
+						"content": json_body(&upload_session)
+					},
+					"default": errors
+				}
+			},
+			"head": {
+				"summary": "Get the state of an upload session, as headers.",
+				"operationId": "headUpload",
+				"parameters": upload,
+				"responses": {
+					"200": {
+						"description": "The session exists.",
+						"headers": upload_headers
+					},
+					"default": errors
+				}
+			},
+			"patch": {
+				"summary": "Append a chunk to an upload session.",
+				"operationId": "patchUpload",
+				"parameters": with(&upload, &[header_parameter(
+					UPLOAD_OFFSET_HEADER,
+					"The session's current offset.",
+					true,
+				)]),
+				"requestBody": {
+					"required": true,
+					"content": binary_body(UPLOAD_CONTENT_TYPE)
+				},
+				"responses": {
+					"204": {
+						"description": "The chunk was received.",
+						"headers": upload_headers
+					},
+					"default": errors
+				}
+			},
+			"delete": {
+				"summary": "Abort an upload session.",
+				"operationId": "deleteUpload",
+				"parameters": upload,
+				"responses": {
+					"204": no_content("The session was aborted."),
+					"default": errors
+				}
+			}
+		}),
+	);
+	add(
+		"/{mount}/uploads/{id}/finalize".into(),
+		json!({
+			"post": {
+				"summary": "Finish an upload session, moving the file into place.",
+				"operationId": "finalizeUpload",
+				"parameters": with(&upload, &[repr_digest]),
+				"responses": {
+					"204": no_content("The file was written, and the session no longer exists."),
+					"default": errors
+				}
+			}
+		}),
+	);
+	add(
+		"/{mount}/watch/{path}".into(),
+		json!({
+			"get": {
+				"summary": "Stream changes to a path as they happen.",
+				"operationId": "getWatch",
+				"parameters": with(&location, &watch_query),
+				"responses": {
+					"101": no_content("The watch was started over a WebSocket, with one `WatchEvent` JSON object per message."),
+					"200": {
+						"description": "Server-Sent Events, each with a `WatchEvent` JSON object as its data.",
+						"content": {
+							SSE_CONTENT_TYPE: { "schema": { "type": "string" } }
+						}
+					},
+					"default": errors
+				}
+			}
+		}),
+	);
+	add(
+		"/{mount}/xattr/{path}".into(),
+		json!({
+			"get": {
+				"summary": "Read a single extended attribute.",
+				"operationId": "getXattr",
+				"parameters": with(&location, slice::from_ref(&xattr_name)),
+				"responses": {
+					"200": {
+						"description": "The attribute.",
+						"content": json_body(&xattr)
+					},
+					"default": errors
+				}
+			},
+			"put": {
+				"summary": "Set a single extended attribute.",
+				"operationId": "putXattr",
+				"parameters": location,
+				"requestBody": {
+					"required": true,
+					"content": json_body(&xattr)
+				},
+				"responses": {
+					"204": no_content("The attribute was set."),
+					"default": errors
+				}
+			},
+			"delete": {
+				"summary": "Remove a single extended attribute.",
+				"operationId": "deleteXattr",
+				"parameters": with(&location, &[xattr_name]),
+				"responses": {
+					"204": no_content("The attribute was removed."),
+					"default": errors
+				}
+			}
+		}),
+	);
+	add(
+		WELL_KNOWN_PATH.into(),
+		json!({
+			"get": {
+				"summary": "Describe what the server supports.",
+				"operationId": "getCapabilities",
+				"responses": {
+					"200": {
+						"description": "The server's capabilities.",
+						"content": json_body(&capabilities)
+					},
+					"default": errors
+				}
+			}
+		}),
+	);
+	add(
+		format!("{}/schema/{{name}}", WELL_KNOWN_PATH),
+		json!({
+			"get": {
+				"summary": "Get the JSON Schema of one of the protocol's types.",
+				"operationId": "getSchema",
+				"parameters": [{
+					"name": "name",
+					"in": "path",
+					"required": true,
+					"schema": { "type": "string", "enum": SCHEMA_NAMES }
+				}],
+				"responses": {
+					"200": {
+						"description": "The JSON Schema (draft 2020-12) of the type.",
+						"content": {
+							SCHEMA_CONTENT_TYPE: { "schema": { "type": "object" } }
+						}
+					},
+					"default": errors
+				}
+			}
+		}),
+	);
+
+	json!({
+		"openapi": OPENAPI_VERSION,
+		"info": {
+			"title": "Xenon File Protocol",
+			"version": PROTOCOL_VERSION
+		},
+		"paths": paths,
+		"components": {
+			"schemas": schemas
+		}
+	})
+}
+
+fn path_parameter(name: &str) -> Value {
+	json!({
+		"name": name,
+		"in": "path",
+		"required": true,
+		"schema": { "type": "string" }
+	})
+}
+
+fn header_parameter(name: &str, description: &str, required: bool) -> Value {
+	json!({
+		"name": name,
+		"in": "header",
+		"required": required,
+		"description": description,
+		"schema": { "type": "string" }
+	})
+}
+
+fn response_header(description: &str) -> Value {
+	json!({
+		"description": description,
+		"schema": { "type": "string" }
+	})
+}
+
+fn binary() -> Value {
+	json!({ "type": "string", "format": "binary" })
+}
+
+/// Turns each field of a query string type into an optional query parameter,
+/// removing the type itself from `schemas`, as it's never a request or response body.
+fn query_parameters<T: JsonSchema>(schemas: &mut Map<String, Value>) -> Vec<Value> {
+	let properties = schemas
+		.remove(T::schema_name().as_ref())
+		.and_then(|mut schema| schema.get_mut("properties").map(Value::take))
+		.and_then(|properties| match properties {
+			Value::Object(properties) => Some(properties),
+			_ => None,
+		})
+		.unwrap_or_default();
+	properties
+		.into_iter()
+		.map(|(name, schema)| {
+			json!({
+				"name": name,
+				"in": "query",
+				"required": false,
+				"schema": schema
+			})
+		})
+		.collect()
+}
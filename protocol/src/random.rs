@@ -0,0 +1,19 @@
+//! Identifiers which need to be unpredictable, or unique across restarts.
+
+use std::{fmt::Write, io};
+
+/// Fills an array with bytes from the operating system's random number generator.
+pub(crate) fn random_bytes<const N: usize>() -> io::Result<[u8; N]> {
+	let mut bytes = [0; N];
+	getrandom::fill(&mut bytes)?;
+	Ok(bytes)
+}
+
+/// Generates an identifier of 32 lowercase hex digits, from 128 random bits.
+pub(crate) fn random_id() -> io::Result<String> {
+	let bytes = random_bytes::<16>()?;
+	Ok(bytes.iter().fold(String::new(), |mut out, byte| {
+		let _ = write!(out, "{:02x}", byte);
+		out
+	}))
+}
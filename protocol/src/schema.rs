@@ -0,0 +1,119 @@
+use crate::{
+	dir::DirectoryListEntry,
+	file::FileType,
+	metadata::{FileGroup, FileOwner, FilePermissions, Metadata},
+};
+use schemars::{generate::SchemaSettings, json_schema, JsonSchema, Schema, SchemaGenerator};
+use std::borrow::Cow;
+
+/// The value of the `Content-Type` header for JSON Schemas.
+pub const SCHEMA_CONTENT_TYPE: &str = "application/schema+json";
+
+/// The names of every type with a published schema, as accepted by [`schema`].
+pub const SCHEMA_NAMES: &[&str] = &[
+	"DirectoryListEntry",
+	"FileGroup",
+	"FileOwner",
+	"FilePermissions",
+	"FileType",
+	"Metadata",
+];
+
+/// Returns the JSON Schema (draft 2020-12) of the type named `name`,
+/// or `None` if it isn't one of the [`SCHEMA_NAMES`].
+///
+/// The schemas describe everything this crate accepts when deserializing,
+/// so for example sizes may be either numbers or strings of digits.
+pub fn schema(name: &str) -> Option<Schema> {
+	Some(match name {
+		"DirectoryListEntry" => schema_for::<DirectoryListEntry>(),
+		"FileGroup" => schema_for::<FileGroup>(),
+		"FileOwner" => schema_for::<FileOwner>(),
+		"FilePermissions" => schema_for::<FilePermissions>(),
+		"FileType" => schema_for::<FileType>(),
+		"Metadata" => schema_for::<Metadata>(),
+		_ => return None,
+	})
+}
+
+/// Returns the JSON Schema (draft 2020-12) of `T`, as it is deserialized.
+pub fn schema_for<T: JsonSchema>() -> Schema {
+	SchemaSettings::draft2020_12()
+		.for_deserialize()
+		.into_generator()
+		.into_root_schema_for::<T>()
+}
+
+/// A timestamp, represented as an RFC 3339 string.
+pub(crate) struct DateTime;
+
+impl JsonSchema for DateTime {
+	fn inline_schema() -> bool {
+		true
+	}
+
+	fn schema_name() -> Cow<'static, str> {
+		"DateTime".into()
+	}
+
+	fn json_schema(_: &mut SchemaGenerator) -> Schema {
+		json_schema!({
+			"type": "string",
+			"format": "date-time"
+		})
+	}
+}
+
+/// A size in bytes, which is always serialized as a number,
+/// but may be deserialized from a string of digits too.
+pub(crate) struct Size;
+
+impl JsonSchema for Size {
+	fn inline_schema() -> bool {
+		true
+	}
+
+	fn schema_name() -> Cow<'static, str> {
+		"Size".into()
+	}
+
+	fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+		let number = json_schema!({
+			"type": "integer",
+			"format": "uint64",
+			"minimum": 0
+		});
+		if generator.contract().is_serialize() {
+			return number;
+		}
+		json_schema!({
+			"anyOf": [
+				number,
+				{
+					"type": "string",
+					"pattern": "^[0-9]+$"
+				}
+			]
+		})
+	}
+}
+
+/// Binary data, represented as a base64 string.
+pub(crate) struct Base64;
+
+impl JsonSchema for Base64 {
+	fn inline_schema() -> bool {
+		true
+	}
+
+	fn schema_name() -> Cow<'static, str> {
+		"Base64".into()
+	}
+
+	fn json_schema(_: &mut SchemaGenerator) -> Schema {
+		json_schema!({
+			"type": "string",
+			"contentEncoding": "base64"
+		})
+	}
+}
@@ -0,0 +1,124 @@
+mod store;
+
+pub use self::store::{UploadError, UploadStore};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, skip_serializing_none, DisplayFromStr, PickFirst};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+/// The HTTP header containing the offset of the data in a `PATCH` request,
+/// or the amount of data the server has received so far in a response.
+pub const UPLOAD_OFFSET_HEADER: &str = "Upload-Offset";
+/// The HTTP header containing the total length of an upload, if it is known.
+pub const UPLOAD_LENGTH_HEADER: &str = "Upload-Length";
+/// The HTTP header containing the time an upload session expires, in RFC 3339 format.
+pub const UPLOAD_EXPIRES_HEADER: &str = "Upload-Expires";
+/// The media type of the body of a `PATCH` request to an upload session.
+pub const UPLOAD_CONTENT_TYPE: &str = "application/offset+octet-stream";
+
+/// The state of a resumable upload session.
+#[serde_as]
+#[skip_serializing_none]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct UploadSession {
+	id: String,
+	path: String,
+	#[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+	#[cfg_attr(feature = "schema", schemars(with = "crate::schema::Size"))]
+	offset: u64,
+	#[serde_as(as = "Option<PickFirst<(_, DisplayFromStr)>>")]
+	#[serde(default)]
+	#[cfg_attr(feature = "schema", schemars(with = "Option<crate::schema::Size>"))]
+	length: Option<u64>,
+	#[serde_as(as = "Rfc3339")]
+	#[cfg_attr(feature = "schema", schemars(with = "crate::schema::DateTime"))]
+	expires: OffsetDateTime,
+}
+
+impl UploadSession {
+	/// Creates a new upload session, which hasn't received any data yet.
+	pub fn new(
+		id: impl ToString,
+		path: impl ToString,
+		length: impl Into<Option<u64>>,
+		expires: OffsetDateTime,
+	) -> Self {
+		Self {
+			id: id.to_string(),
+			path: path.to_string(),
+			offset: 0,
+			length: length.into(),
+			expires,
+		}
+	}
+
+	/// The opaque identifier of this upload session.
+	///
+	/// Represented as a string in JSON, with the key `id`.
+	#[inline]
+	pub fn id(&self) -> &str {
+		&self.id
+	}
+
+	/// The path in the mount the file will be written to once the upload is finalized.
+	///
+	/// Represented as a string in JSON, with the key `path`.
+	#[inline]
+	pub fn path(&self) -> &str {
+		&self.path
+	}
+
+	/// The amount of bytes the server has received so far.
+	/// The next chunk must be sent at this offset.
+	///
+	/// Represented as a number in JSON, with the key `offset`.
+	#[inline]
+	pub fn offset(&self) -> u64 {
+		self.offset
+	}
+
+	/// Duplicates this upload session object,
+	/// updating the offset with the specified value.
+	#[inline]
+	pub fn with_offset(self, offset: u64) -> Self {
+		Self { offset, ..self }
+	}
+
+	/// The total length of the file being uploaded, if it was known when the session was created.
+	///
+	/// Represented as a number in JSON, with the key `length`.
+	#[inline]
+	pub fn length(&self) -> Option<u64> {
+		self.length
+	}
+
+	/// The time this session expires if no more data is received.
+	/// Each successful `PATCH` pushes this back.
+	///
+	/// Represented as a string in RFC 3339 format in JSON, with the key `expires`.
+	#[inline]
+	pub fn expires(&self) -> OffsetDateTime {
+		self.expires
+	}
+
+	/// Duplicates this upload session object,
+	/// updating the expiry time with the specified time.
+	#[inline]
+	pub fn with_expires(self, expires: OffsetDateTime) -> Self {
+		Self { expires, ..self }
+	}
+
+	/// Returns if this session has expired at the given time,
+	/// meaning it, and any data received so far, can be garbage collected.
+	#[inline]
+	pub fn is_expired(&self, now: OffsetDateTime) -> bool {
+		now >= self.expires
+	}
+
+	/// Returns if every byte of the file has been received.
+	/// This is always `false` if the length is unknown.
+	#[inline]
+	pub fn is_complete(&self) -> bool {
+		self.length == Some(self.offset)
+	}
+}
@@ -0,0 +1,346 @@
+use super::UploadSession;
+use crate::{hash::FileHash, random::random_id};
+use std::{
+	collections::HashMap,
+	fmt, fs,
+	fs::{File, OpenOptions},
+	io::{self, Read, Seek, SeekFrom, Write},
+	path::{Path, PathBuf},
+	sync::{Arc, Mutex, MutexGuard},
+	time::{Duration, SystemTime},
+};
+use time::OffsetDateTime;
+
+/// The error returned when an operation on an [`UploadStore`] fails.
+#[derive(Debug)]
+pub enum UploadError {
+	/// The session doesn't exist, or has expired.
+	///
+	/// Servers should respond with `404 Not Found`.
+	NotFound,
+	/// The chunk wasn't sent at the session's current offset, which is included.
+	///
+	/// Servers should respond with `409 Conflict`.
+	OffsetMismatch(u64),
+	/// The chunk would make the upload longer than its length.
+	/// Nothing from the chunk was kept.
+	///
+	/// Servers should respond with `413 Request Entity Too Large`.
+	TooLong,
+	/// The session was finalized before all of its length was received.
+	///
+	/// Servers should respond with `409 Conflict`.
+	Incomplete,
+	/// The uploaded data doesn't match the digest it was finalized with, which is included.
+	/// The session and its data are kept, so the client may retry.
+	///
+	/// Servers should respond with `400 Bad Request`.
+	DigestMismatch(FileHash),
+	/// Reading or writing the session failed.
+	/// Any data received before the failure is kept.
+	///
+	/// Servers should respond with `500 Internal Server Error`.
+	Io(io::Error),
+}
+
+impl fmt::Display for UploadError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			UploadError::NotFound => f.write_str("upload session not found"),
+			UploadError::OffsetMismatch(offset) => {
+				write!(f, "upload offset mismatch, expected {}", offset)
+			}
+			UploadError::TooLong => f.write_str("chunk is longer than the rest of the upload"),
+			UploadError::Incomplete => f.write_str("upload is incomplete"),
+			UploadError::DigestMismatch(expected) => write!(
+				f,
+				"upload doesn't match its {} digest",
+				expected.algorithm().digest_name()
+			),
+			UploadError::Io(err) => write!(f, "upload failed: {}", err),
+		}
+	}
+}
+
+impl std::error::Error for UploadError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			UploadError::Io(err) => Some(err),
+			_ => None,
+		}
+	}
+}
+
+impl From<io::Error> for UploadError {
+	fn from(err: io::Error) -> Self {
+		UploadError::Io(err)
+	}
+}
+
+/// Stores upload sessions and the data received so far in a local directory,
+/// expiring sessions which haven't received any data for a while.
+///
+/// Each session is kept as `<id>.json`, with its data in `<id>.part`.
+/// The directory should be on the same filesystem as the mount,
+/// so finalized uploads can be moved into place atomically.
+/// Expired sessions are removed when they're next accessed, or by [`collect_garbage`](Self::collect_garbage),
+/// which servers should call periodically.
+///
+/// Operations on a session are serialized, but different sessions can receive data at the same time.
+#[derive(Debug)]
+pub struct UploadStore {
+	dir: PathBuf,
+	ttl: Duration,
+	/// A lock for each session currently being operated on.
+	locks: Mutex<HashMap<String, Arc<Mutex<()>>>>,
+}
+
+impl UploadStore {
+	/// Opens the store in `dir`, creating the directory if it doesn't exist.
+	/// Sessions expire once they haven't received any data for `ttl`.
+	pub fn new(dir: impl Into<PathBuf>, ttl: Duration) -> io::Result<Self> {
+		let dir = dir.into();
+		fs::create_dir_all(&dir)?;
+		Ok(Self {
+			dir,
+			ttl,
+			locks: Mutex::new(HashMap::new()),
+		})
+	}
+
+	/// Returns how long sessions are kept without receiving any data.
+	#[inline]
+	pub fn ttl(&self) -> Duration {
+		self.ttl
+	}
+
+	/// Creates a new session, which will write to `path` once finalized.
+	pub fn create(
+		&self,
+		path: impl ToString,
+		length: impl Into<Option<u64>>,
+	) -> io::Result<UploadSession> {
+		let session = UploadSession::new(random_id()?, path, length, self.expires_from_now());
+		// Garbage collection would otherwise see the data file without a session, and remove it.
+		self.with_lock(session.id(), || {
+			File::create(self.data_path(session.id()))?;
+			self.save(&session)
+		})?;
+		Ok(session)
+	}
+
+	/// Returns the session with the given id, if it exists and hasn't expired.
+	pub fn get(&self, id: &str) -> Result<UploadSession, UploadError> {
+		self.with_lock(id, || self.load(id))
+	}
+
+	/// Appends `data` to the session with the given id, if `offset` is its current offset,
+	/// pushing back its expiry time.
+	///
+	/// If reading `data` fails partway through, every byte read so far is kept,
+	/// so the client can resume from the new offset.
+	pub fn append<R: Read>(
+		&self,
+		id: &str,
+		offset: u64,
+		data: R,
+	) -> Result<UploadSession, UploadError> {
+		self.with_lock(id, || self.append_locked(id, offset, data))
+	}
+
+	fn append_locked<R: Read>(
+		&self,
+		id: &str,
+		offset: u64,
+		data: R,
+	) -> Result<UploadSession, UploadError> {
+		let session = self.load(id)?;
+		if offset != session.offset() {
+			return Err(UploadError::OffsetMismatch(session.offset()));
+		}
+		let mut file = OpenOptions::new().write(true).open(self.data_path(id))?;
+		file.set_len(offset)?;
+		file.seek(SeekFrom::Start(offset))?;
+		let remaining = session.length().map(|length| length - offset);
+		let limit = remaining.map_or(u64::MAX, |remaining| remaining.saturating_add(1));
+		let mut data = data.take(limit);
+		let mut written = 0u64;
+		let mut buffer = [0u8; 64 * 1024];
+		let result = loop {
+			match data.read(&mut buffer) {
+				Ok(0) => break Ok(()),
+				Ok(read) => {
+					if let Err(err) = file.write_all(&buffer[..read]) {
+						break Err(err);
+					}
+					written += read as u64;
+				}
+				Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+				Err(err) => break Err(err),
+			}
+		};
+		if remaining.is_some_and(|remaining| written > remaining) {
+			file.set_len(offset)?;
+			return Err(UploadError::TooLong);
+		}
+		// Only count what actually made it to the file, which may be less than was read if writing failed.
+		let received = file.metadata()?.len().min(offset + written);
+		let session = session
+			.with_offset(received)
+			.with_expires(self.expires_from_now());
+		self.save(&session)?;
+		result?;
+		Ok(session)
+	}
+
+	/// Finishes the session with the given id, moving its data to `destination`.
+	///
+	/// If `expected` is given, such as from a `Repr-Digest` header, the data is hashed with its algorithm first,
+	/// and the session is left untouched with [`UploadError::DigestMismatch`] if the hashes differ.
+	/// Fails with [`UploadError::Incomplete`] if the session has a length, and not all of it has been received.
+	pub fn finalize(
+		&self,
+		id: &str,
+		destination: &Path,
+		expected: Option<&FileHash>,
+	) -> Result<UploadSession, UploadError> {
+		self.with_lock(id, || {
+			let session = self.load(id)?;
+			if session.length().is_some() && !session.is_complete() {
+				return Err(UploadError::Incomplete);
+			}
+			if let Some(expected) = expected {
+				let actual = FileHash::from_path(self.data_path(id), expected.algorithm())?;
+				if !actual.hash().eq_ignore_ascii_case(expected.hash()) {
+					return Err(UploadError::DigestMismatch(expected.clone()));
+				}
+			}
+			fs::rename(self.data_path(id), destination)?;
+			fs::remove_file(self.session_path(id))?;
+			Ok(session)
+		})
+	}
+
+	/// Aborts the session with the given id, discarding any data received so far.
+	pub fn abort(&self, id: &str) -> Result<(), UploadError> {
+		self.with_lock(id, || {
+			self.load(id)?;
+			self.remove(id)?;
+			Ok(())
+		})
+	}
+
+	/// Removes every session which has expired by `now`, along with its data,
+	/// returning how many were removed.
+	///
+	/// Data files without a session, such as those left behind by a crash, are removed too.
+	pub fn collect_garbage(&self, now: OffsetDateTime) -> io::Result<usize> {
+		let mut removed = 0;
+		for entry in fs::read_dir(&self.dir)? {
+			let path = entry?.path();
+			let id = match path.file_stem().and_then(|stem| stem.to_str()) {
+				Some(id) if is_valid_id(id) => id.to_string(),
+				_ => continue,
+			};
+			let extension = path.extension().and_then(|extension| extension.to_str());
+			if !matches!(extension, Some("json" | "part")) {
+				continue;
+			}
+			// Both files of a session are seen, so by the second one it may already be gone.
+			removed += self.with_lock(&id, || {
+				let exists = self.session_path(&id).exists();
+				let expired = if exists {
+					self.read_session(&id)
+						.map_or(true, |session| session.is_expired(now))
+				} else {
+					self.data_path(&id).exists()
+				};
+				if expired {
+					self.remove(&id)?;
+				}
+				Ok::<_, io::Error>(usize::from(expired && exists))
+			})?;
+		}
+		Ok(removed)
+	}
+
+	fn load(&self, id: &str) -> Result<UploadSession, UploadError> {
+		if !is_valid_id(id) {
+			return Err(UploadError::NotFound);
+		}
+		let session = match self.read_session(id) {
+			Ok(session) => session,
+			Err(err) if err.kind() == io::ErrorKind::NotFound => return Err(UploadError::NotFound),
+			Err(err) => return Err(err.into()),
+		};
+		if session.is_expired(OffsetDateTime::now_utc()) {
+			self.remove(id)?;
+			return Err(UploadError::NotFound);
+		}
+		Ok(session)
+	}
+
+	fn read_session(&self, id: &str) -> io::Result<UploadSession> {
+		let json = fs::read(self.session_path(id))?;
+		serde_json::from_slice(&json).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+	}
+
+	/// Writes the session next to its final location first, so a crash never leaves it half-written.
+	fn save(&self, session: &UploadSession) -> io::Result<()> {
+		let path = self.session_path(session.id());
+		let temporary = path.with_extension("json.tmp");
+		fs::write(&temporary, serde_json::to_vec(session)?)?;
+		fs::rename(temporary, path)
+	}
+
+	fn remove(&self, id: &str) -> io::Result<()> {
+		for path in [self.session_path(id), self.data_path(id)] {
+			match fs::remove_file(path) {
+				Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+				_ => {}
+			}
+		}
+		Ok(())
+	}
+
+	fn session_path(&self, id: &str) -> PathBuf {
+		self.dir.join(format!("{}.json", id))
+	}
+
+	fn data_path(&self, id: &str) -> PathBuf {
+		self.dir.join(format!("{}.part", id))
+	}
+
+	fn expires_from_now(&self) -> OffsetDateTime {
+		OffsetDateTime::from(SystemTime::now() + self.ttl)
+	}
+
+	/// Runs `f` while holding the lock for the session with the given id.
+	fn with_lock<T>(&self, id: &str, f: impl FnOnce() -> T) -> T {
+		let session_lock = lock(&self.locks).entry(id.to_string()).or_default().clone();
+		let result = {
+			let _guard = lock(&session_lock);
+			f()
+		};
+		// Locks are only cloned while the map is locked, so if the map holds the only other reference,
+		// nobody is waiting on it, and it can be dropped without leaving one behind for every session.
+		let mut locks = lock(&self.locks);
+		if Arc::strong_count(&session_lock) == 2 {
+			locks.remove(id);
+		}
+		result
+	}
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+	mutex
+		.lock()
+		.unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn is_valid_id(id: &str) -> bool {
+	id.len() == 32
+		&& id
+			.bytes()
+			.all(|byte| matches!(byte, b'0'..=b'9' | b'a'..=b'f'))
+}
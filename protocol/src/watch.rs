@@ -0,0 +1,203 @@
+mod watcher;
+
+pub use self::watcher::{Watcher, DEFAULT_POLL_INTERVAL};
+use crate::{dir::DirectoryListEntry, file::FileType};
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+use std::collections::{HashMap, HashSet};
+
+/// The media type of a stream of Server-Sent Events.
+pub const SSE_CONTENT_TYPE: &str = "text/event-stream";
+
+/// The kind of change a [`WatchEvent`] describes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum WatchEventKind {
+	/// A file object was created.
+	///
+	/// Represented as the string `create` in JSON.
+	Create,
+	/// The contents or metadata of a file object changed.
+	///
+	/// Represented as the string `modify` in JSON.
+	Modify,
+	/// A file object was deleted.
+	///
+	/// Represented as the string `delete` in JSON.
+	Delete,
+	/// A file object was renamed or moved within the watched path.
+	///
+	/// Represented as the string `rename` in JSON.
+	Rename,
+	/// Events were dropped, as they came in faster than they could be sent.
+	/// Clients should list the watched path again to find out its current state.
+	///
+	/// Represented as the string `overflow` in JSON.
+	Overflow,
+}
+
+impl WatchEventKind {
+	/// The name of this kind of event, used as the `event` field of a Server-Sent Event.
+	#[inline]
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			WatchEventKind::Create => "create",
+			WatchEventKind::Modify => "modify",
+			WatchEventKind::Delete => "delete",
+			WatchEventKind::Rename => "rename",
+			WatchEventKind::Overflow => "overflow",
+		}
+	}
+}
+
+/// The query parameters accepted by the watch endpoint.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WatchQuery {
+	#[serde(default)]
+	recursive: Option<bool>,
+}
+
+impl WatchQuery {
+	/// Whether changes in subdirectories are reported too. Defaults to `false`.
+	///
+	/// Represented as a bool in the query string, with the key `recursive`.
+	#[inline]
+	pub fn recursive(&self) -> bool {
+		self.recursive.unwrap_or(false)
+	}
+
+	/// Duplicates this query object,
+	/// updating the recursive flag with the specified value.
+	#[inline]
+	pub fn with_recursive(self, recursive: impl Into<Option<bool>>) -> Self {
+		Self {
+			recursive: recursive.into(),
+		}
+	}
+}
+
+/// A single change to a watched path.
+#[skip_serializing_none]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WatchEvent {
+	kind: WatchEventKind,
+	path: String,
+	from: Option<String>,
+	#[serde(rename = "type")]
+	file_type: Option<FileType>,
+}
+
+impl WatchEvent {
+	/// Creates a new event of the given kind, for the given path.
+	pub fn new(kind: WatchEventKind, path: impl ToString) -> Self {
+		Self {
+			kind,
+			path: path.to_string(),
+			from: None,
+			file_type: None,
+		}
+	}
+
+	/// Compares two listings of the same directory, returning the events which turn `old` into `new`.
+	///
+	/// This is intended for backends without native change notifications, which have to poll instead.
+	/// As such, renames are reported as a deletion followed by a creation.
+	/// Only the type, size and updated time are compared, so reading a file doesn't count as a modification.
+	pub fn diff(old: &[DirectoryListEntry], new: &[DirectoryListEntry]) -> Vec<Self> {
+		let old = old
+			.iter()
+			.map(|entry| (entry.name(), entry))
+			.collect::<HashMap<_, _>>();
+		let new_names = new
+			.iter()
+			.map(DirectoryListEntry::name)
+			.collect::<HashSet<_>>();
+		let mut events = old
+			.keys()
+			.filter(|name| !new_names.contains(*name))
+			.map(|name| Self::new(WatchEventKind::Delete, name))
+			.collect::<Vec<_>>();
+		events.sort_unstable_by(|a, b| a.path.cmp(&b.path));
+		for entry in new {
+			let kind = match old.get(entry.name()) {
+				None => WatchEventKind::Create,
+				Some(old)
+					if old.file_type() != entry.file_type()
+						|| old.metadata().size() != entry.metadata().size()
+						|| old.metadata().updated() != entry.metadata().updated() =>
+				{
+					WatchEventKind::Modify
+				}
+				Some(_) => continue,
+			};
+			events.push(Self::new(kind, entry.name()).with_file_type(entry.file_type()));
+		}
+		events
+	}
+
+	/// The kind of change this event describes.
+	///
+	/// Represented as a string in JSON, with the key `kind`. See [`WatchEventKind`].
+	#[inline]
+	pub fn kind(&self) -> WatchEventKind {
+		self.kind
+	}
+
+	/// The path that changed, relative to the watched path, using `/` as the separator.
+	/// For renames, this is the new path. For overflows, this is empty.
+	///
+	/// Represented as a string in JSON, with the key `path`.
+	#[inline]
+	pub fn path(&self) -> &str {
+		&self.path
+	}
+
+	/// The old path of a renamed file object, relative to the watched path.
+	/// Only present for renames.
+	///
+	/// Represented as a string in JSON, with the key `from`.
+	#[inline]
+	pub fn from(&self) -> Option<&str> {
+		self.from.as_deref()
+	}
+
+	/// Duplicates this event object,
+	/// updating the old path with the specified value.
+	#[inline]
+	pub fn with_from(self, from: impl Into<Option<String>>) -> Self {
+		Self {
+			from: from.into(),
+			..self
+		}
+	}
+
+	/// The type of the file object that changed, if known.
+	///
+	/// Represented as a string in JSON, with the key `type`. See [`FileType`].
+	#[inline]
+	pub fn file_type(&self) -> Option<FileType> {
+		self.file_type
+	}
+
+	/// Duplicates this event object,
+	/// updating the type with the specified value.
+	#[inline]
+	pub fn with_file_type(self, file_type: impl Into<Option<FileType>>) -> Self {
+		Self {
+			file_type: file_type.into(),
+			..self
+		}
+	}
+
+	/// Formats this event as a single Server-Sent Event, including the trailing blank line.
+	pub fn to_sse(&self) -> serde_json::Result<String> {
+		Ok(format!(
+			"event: {}\ndata: {}\n\n",
+			self.kind.as_str(),
+			serde_json::to_string(self)?
+		))
+	}
+}
@@ -0,0 +1,456 @@
+use super::{WatchEvent, WatchEventKind};
+use crate::{dir::DirectoryListEntry, file::FileType, metadata::Metadata};
+use std::{
+	collections::VecDeque,
+	fs, io,
+	path::{Path, PathBuf},
+	thread,
+	time::{Duration, Instant},
+};
+use time::OffsetDateTime;
+
+/// How often a [`Watcher`] rescans its path when it has to poll, unless told otherwise.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Watches a path on the local filesystem, producing [`WatchEvent`]s as it changes.
+///
+/// On Linux, this uses inotify. Everywhere else, or when inotify can't be used
+/// (for example, because the per-user watch limit has been reached),
+/// it falls back to rescanning the path every so often and [diffing](WatchEvent::diff) the results.
+///
+/// Paths in events are relative to the watched path, using `/` as the separator.
+/// If the watched path is a file rather than a directory, changes to it have an empty path.
+///
+/// Iterating over a watcher blocks until the next event. Servers which need to notice
+/// a client disconnecting should use [`next_timeout`](Self::next_timeout) instead.
+#[derive(Debug)]
+pub struct Watcher {
+	backend: Backend,
+	pending: VecDeque<WatchEvent>,
+}
+
+#[derive(Debug)]
+enum Backend {
+	#[cfg(target_os = "linux")]
+	Inotify(native::InotifyWatcher),
+	Poll(PollWatcher),
+}
+
+impl Watcher {
+	/// Starts watching `path`, including its subdirectories if `recursive` is `true`,
+	/// using native change notifications if they're available.
+	pub fn new<P: AsRef<Path>>(path: P, recursive: bool) -> io::Result<Self> {
+		let path = path.as_ref();
+		// Make sure a missing path is reported as such, rather than falling back to polling it.
+		fs::metadata(path)?;
+		#[cfg(target_os = "linux")]
+		if let Ok(watcher) = native::InotifyWatcher::new(path, recursive) {
+			return Ok(Self {
+				backend: Backend::Inotify(watcher),
+				pending: VecDeque::new(),
+			});
+		}
+		Self::polling(path, recursive, DEFAULT_POLL_INTERVAL)
+	}
+
+	/// Starts watching `path`, including its subdirectories if `recursive` is `true`,
+	/// by rescanning it every `interval`.
+	///
+	/// Polling can't tell renames apart from a deletion followed by a creation,
+	/// and only notices changes to the type, size or updated time of a file object.
+	pub fn polling<P: AsRef<Path>>(
+		path: P,
+		recursive: bool,
+		interval: Duration,
+	) -> io::Result<Self> {
+		Ok(Self {
+			backend: Backend::Poll(PollWatcher::new(path.as_ref(), recursive, interval)?),
+			pending: VecDeque::new(),
+		})
+	}
+
+	/// Whether this watcher polls the path, rather than using native change notifications.
+	#[inline]
+	pub fn is_polling(&self) -> bool {
+		matches!(self.backend, Backend::Poll(_))
+	}
+
+	/// Waits up to `timeout` for the next event, returning `None` if nothing changed in that time.
+	pub fn next_timeout(&mut self, timeout: Duration) -> io::Result<Option<WatchEvent>> {
+		let deadline = Instant::now() + timeout;
+		loop {
+			if let Some(event) = self.pending.pop_front() {
+				return Ok(Some(event));
+			}
+			let remaining = deadline.saturating_duration_since(Instant::now());
+			let events = match &mut self.backend {
+				#[cfg(target_os = "linux")]
+				Backend::Inotify(watcher) => watcher.wait(remaining)?,
+				Backend::Poll(watcher) => watcher.wait(remaining)?,
+			};
+			self.pending.extend(events);
+			if self.pending.is_empty() && Instant::now() >= deadline {
+				return Ok(None);
+			}
+		}
+	}
+}
+
+impl Iterator for Watcher {
+	type Item = io::Result<WatchEvent>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			match self.next_timeout(Duration::from_secs(60)) {
+				Ok(Some(event)) => return Some(Ok(event)),
+				Ok(None) => continue,
+				Err(err) => return Some(Err(err)),
+			}
+		}
+	}
+}
+
+fn file_type_of(metadata: &fs::Metadata) -> FileType {
+	FileType::from(metadata.file_type())
+}
+
+fn join(dir: &str, name: &str) -> String {
+	if dir.is_empty() {
+		name.to_string()
+	} else {
+		format!("{}/{}", dir, name)
+	}
+}
+
+#[derive(Debug)]
+struct PollWatcher {
+	root: PathBuf,
+	recursive: bool,
+	interval: Duration,
+	next_scan: Instant,
+	listing: Vec<DirectoryListEntry>,
+}
+
+impl PollWatcher {
+	fn new(root: &Path, recursive: bool, interval: Duration) -> io::Result<Self> {
+		let mut watcher = Self {
+			root: root.to_path_buf(),
+			recursive,
+			interval,
+			next_scan: Instant::now() + interval,
+			listing: Vec::new(),
+		};
+		watcher.listing = watcher.scan()?;
+		Ok(watcher)
+	}
+
+	fn wait(&mut self, timeout: Duration) -> io::Result<Vec<WatchEvent>> {
+		let now = Instant::now();
+		if self.next_scan > now + timeout {
+			thread::sleep(timeout);
+			return Ok(Vec::new());
+		}
+		thread::sleep(self.next_scan.saturating_duration_since(now));
+		self.next_scan = Instant::now() + self.interval;
+		let listing = self.scan()?;
+		let events = WatchEvent::diff(&self.listing, &listing);
+		self.listing = listing;
+		Ok(events)
+	}
+
+	/// Lists the watched path, naming each entry by its path relative to the root,
+	/// so the listings can be compared with [`WatchEvent::diff`].
+	/// A watched file is listed as a single entry with an empty name.
+	fn scan(&self) -> io::Result<Vec<DirectoryListEntry>> {
+		let mut listing = Vec::new();
+		match fs::symlink_metadata(&self.root) {
+			Ok(metadata) if metadata.is_dir() => self.scan_dir(&self.root, "", &mut listing)?,
+			Ok(metadata) => listing.push(entry("", &metadata)),
+			// A deleted path is reported as everything in it being deleted.
+			Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+			Err(err) => return Err(err),
+		}
+		listing.sort_unstable_by(|a, b| a.name().cmp(b.name()));
+		Ok(listing)
+	}
+
+	fn scan_dir(
+		&self,
+		dir: &Path,
+		relative: &str,
+		listing: &mut Vec<DirectoryListEntry>,
+	) -> io::Result<()> {
+		let entries = match fs::read_dir(dir) {
+			Ok(entries) => entries,
+			// Directories can disappear while they're being scanned.
+			Err(err) if err.kind() == io::ErrorKind::NotFound && !relative.is_empty() => {
+				return Ok(())
+			}
+			Err(err) => return Err(err),
+		};
+		for dir_entry in entries {
+			let dir_entry = dir_entry?;
+			let metadata = match dir_entry.metadata() {
+				Ok(metadata) => metadata,
+				Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+				Err(err) => return Err(err),
+			};
+			let path = join(relative, &dir_entry.file_name().to_string_lossy());
+			if self.recursive && metadata.is_dir() {
+				self.scan_dir(&dir_entry.path(), &path, listing)?;
+			}
+			listing.push(entry(&path, &metadata));
+		}
+		Ok(())
+	}
+}
+
+fn entry(path: &str, metadata: &fs::Metadata) -> DirectoryListEntry {
+	DirectoryListEntry::default()
+		.with_name(path)
+		.with_file_type(file_type_of(metadata))
+		.with_metadata(
+			Metadata::default()
+				.with_size(metadata.len())
+				.with_updated(metadata.modified().ok().map(OffsetDateTime::from)),
+		)
+}
+
+#[cfg(target_os = "linux")]
+mod native {
+	use super::{file_type_of, join, WatchEvent, WatchEventKind};
+	use crate::file::FileType;
+	use inotify::{EventMask, Inotify, WatchDescriptor, WatchMask};
+	use std::{
+		collections::HashMap,
+		ffi::OsString,
+		fs, io,
+		os::unix::io::AsRawFd,
+		path::{Path, PathBuf},
+		time::Duration,
+	};
+
+	const MASK: WatchMask = WatchMask::CREATE
+		.union(WatchMask::MODIFY)
+		.union(WatchMask::ATTRIB)
+		.union(WatchMask::DELETE)
+		.union(WatchMask::DELETE_SELF)
+		.union(WatchMask::MOVED_FROM)
+		.union(WatchMask::MOVED_TO)
+		.union(WatchMask::EXCL_UNLINK);
+
+	#[derive(Debug)]
+	pub(super) struct InotifyWatcher {
+		inotify: Inotify,
+		root: PathBuf,
+		recursive: bool,
+		/// The path of every watched directory, relative to the root.
+		dirs: HashMap<WatchDescriptor, String>,
+		buffer: Vec<u8>,
+	}
+
+	impl InotifyWatcher {
+		pub(super) fn new(root: &Path, recursive: bool) -> io::Result<Self> {
+			let mut watcher = Self {
+				inotify: Inotify::init()?,
+				root: root.to_path_buf(),
+				recursive,
+				dirs: HashMap::new(),
+				buffer: vec![0; 64 * 1024],
+			};
+			let wd = watcher.inotify.watches().add(root, MASK)?;
+			watcher.dirs.insert(wd, String::new());
+			if recursive && root.is_dir() {
+				watcher.watch_children("", &mut Vec::new())?;
+			}
+			Ok(watcher)
+		}
+
+		/// Watches every subdirectory of `relative`, adding everything found to `created`,
+		/// as a new directory's contents may have been created before it was watched.
+		fn watch_children(
+			&mut self,
+			relative: &str,
+			created: &mut Vec<WatchEvent>,
+		) -> io::Result<()> {
+			let entries = match fs::read_dir(self.root.join(relative)) {
+				Ok(entries) => entries,
+				Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+				Err(err) => return Err(err),
+			};
+			for dir_entry in entries {
+				let dir_entry = dir_entry?;
+				let Ok(metadata) = fs::symlink_metadata(dir_entry.path()) else {
+					continue;
+				};
+				let path = join(relative, &dir_entry.file_name().to_string_lossy());
+				created.push(
+					WatchEvent::new(WatchEventKind::Create, &path)
+						.with_file_type(file_type_of(&metadata)),
+				);
+				if metadata.is_dir() {
+					self.watch_dir(&path, created)?;
+				}
+			}
+			Ok(())
+		}
+
+		fn watch_dir(&mut self, relative: &str, created: &mut Vec<WatchEvent>) -> io::Result<()> {
+			match self
+				.inotify
+				.watches()
+				.add(self.root.join(relative), MASK | WatchMask::ONLYDIR)
+			{
+				Ok(wd) => {
+					self.dirs.insert(wd, relative.to_string());
+					self.watch_children(relative, created)
+				}
+				// The directory was removed or replaced before it could be watched.
+				Err(err) if matches!(err.raw_os_error(), Some(libc::ENOENT | libc::ENOTDIR)) => {
+					Ok(())
+				}
+				Err(err) => Err(err),
+			}
+		}
+
+		pub(super) fn wait(&mut self, timeout: Duration) -> io::Result<Vec<WatchEvent>> {
+			let mut fds = [nix::poll::PollFd::new(
+				self.inotify.as_raw_fd(),
+				nix::poll::PollFlags::POLLIN,
+			)];
+			let timeout = i32::try_from(timeout.as_millis()).unwrap_or(i32::MAX);
+			match nix::poll::poll(&mut fds, timeout) {
+				Ok(0) | Err(nix::errno::Errno::EINTR) => return Ok(Vec::new()),
+				Ok(_) => {}
+				Err(err) => return Err(err.into()),
+			}
+			let raw = match self.inotify.read_events(&mut self.buffer) {
+				Ok(events) => events
+					.map(|event| {
+						(
+							event.wd.clone(),
+							event.mask,
+							event.cookie,
+							event.name.map(OsString::from),
+						)
+					})
+					.collect::<Vec<_>>(),
+				Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(Vec::new()),
+				Err(err) => return Err(err),
+			};
+			let mut events = Vec::new();
+			let mut moved_from = HashMap::<u32, (String, bool)>::new();
+			for (wd, mask, cookie, name) in raw {
+				if mask.contains(EventMask::Q_OVERFLOW) {
+					events.push(WatchEvent::new(WatchEventKind::Overflow, ""));
+					continue;
+				}
+				if mask.contains(EventMask::IGNORED) {
+					self.dirs.remove(&wd);
+					continue;
+				}
+				let Some(dir) = self.dirs.get(&wd).cloned() else {
+					continue;
+				};
+				let is_dir = mask.contains(EventMask::ISDIR);
+				let path = match &name {
+					Some(name) => join(&dir, &name.to_string_lossy()),
+					None => dir.clone(),
+				};
+				if mask.contains(EventMask::DELETE_SELF) {
+					// Subdirectories are reported as deleted by their parent.
+					if dir.is_empty() {
+						events.push(WatchEvent::new(WatchEventKind::Delete, ""));
+					}
+				} else if mask.contains(EventMask::CREATE) {
+					events.push(self.created(&path, is_dir));
+					if is_dir && self.recursive {
+						self.watch_dir(&path, &mut events)?;
+					}
+				} else if mask.intersects(EventMask::MODIFY | EventMask::ATTRIB) {
+					events.push(
+						WatchEvent::new(WatchEventKind::Modify, &path)
+							.with_file_type(self.file_type(&path, is_dir)),
+					);
+				} else if mask.contains(EventMask::DELETE) {
+					events.push(deleted(&path, is_dir));
+				} else if mask.contains(EventMask::MOVED_FROM) {
+					moved_from.insert(cookie, (path, is_dir));
+				} else if mask.contains(EventMask::MOVED_TO) {
+					match moved_from.remove(&cookie) {
+						Some((from, _)) => {
+							if is_dir {
+								self.rename_dirs(&from, &path);
+							}
+							events.push(
+								WatchEvent::new(WatchEventKind::Rename, &path)
+									.with_from(from)
+									.with_file_type(self.file_type(&path, is_dir)),
+							);
+						}
+						// Moved in from outside of the watched path.
+						None => {
+							events.push(self.created(&path, is_dir));
+							if is_dir && self.recursive {
+								self.watch_dir(&path, &mut events)?;
+							}
+						}
+					}
+				}
+			}
+			// Anything moved out of the watched path is gone as far as the client is concerned.
+			for (from, is_dir) in moved_from.into_values() {
+				if is_dir {
+					self.unwatch_dirs(&from);
+				}
+				events.push(deleted(&from, is_dir));
+			}
+			Ok(events)
+		}
+
+		fn created(&self, path: &str, is_dir: bool) -> WatchEvent {
+			WatchEvent::new(WatchEventKind::Create, path)
+				.with_file_type(self.file_type(path, is_dir))
+		}
+
+		fn file_type(&self, path: &str, is_dir: bool) -> Option<FileType> {
+			if is_dir {
+				return Some(FileType::Directory);
+			}
+			fs::symlink_metadata(self.root.join(path))
+				.ok()
+				.map(|metadata| file_type_of(&metadata))
+		}
+
+		fn rename_dirs(&mut self, from: &str, to: &str) {
+			for dir in self.dirs.values_mut() {
+				if let Some(rest) = dir.strip_prefix(from) {
+					if rest.is_empty() || rest.starts_with('/') {
+						*dir = format!("{}{}", to, rest);
+					}
+				}
+			}
+		}
+
+		fn unwatch_dirs(&mut self, from: &str) {
+			let moved = self
+				.dirs
+				.iter()
+				.filter(|(_, dir)| {
+					dir.strip_prefix(from)
+						.is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+				})
+				.map(|(wd, _)| wd.clone())
+				.collect::<Vec<_>>();
+			for wd in moved {
+				self.dirs.remove(&wd);
+				let _ = self.inotify.watches().remove(wd);
+			}
+		}
+	}
+
+	fn deleted(path: &str, is_dir: bool) -> WatchEvent {
+		WatchEvent::new(WatchEventKind::Delete, path)
+			.with_file_type(is_dir.then_some(FileType::Directory))
+	}
+}
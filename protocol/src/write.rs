@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr, PickFirst};
+use std::{fmt, str::FromStr};
+
+/// The query parameters accepted by a partial write.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WriteQuery {
+	#[serde(default)]
+	append: Option<bool>,
+}
+
+impl WriteQuery {
+	/// Whether the body is appended to the end of the file,
+	/// instead of being written at the offset in the `Content-Range` header. Defaults to `false`.
+	///
+	/// Represented as a bool in the query string, with the key `append`.
+	#[inline]
+	pub fn append(&self) -> bool {
+		self.append.unwrap_or(false)
+	}
+
+	/// Duplicates this query object,
+	/// updating the append flag with the specified value.
+	#[inline]
+	pub fn with_append(self, append: impl Into<Option<bool>>) -> Self {
+		Self {
+			append: append.into(),
+		}
+	}
+}
+
+/// The body of a request to set the length of a file.
+#[serde_as]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SetLength {
+	#[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+	#[cfg_attr(feature = "schema", schemars(with = "crate::schema::Size"))]
+	length: u64,
+}
+
+impl SetLength {
+	/// Creates a new request to set the length of a file to `length` bytes.
+	#[inline]
+	pub fn new(length: u64) -> Self {
+		Self { length }
+	}
+
+	/// The new length of the file, in bytes.
+	/// If this is shorter than the file, the file is truncated.
+	/// If it is longer, the file is extended with zeroes.
+	///
+	/// Represented as a number in JSON, with the key `length`.
+	#[inline]
+	pub fn length(&self) -> u64 {
+		self.length
+	}
+}
+
+/// A parsed `Content-Range` header, in the form of `bytes <first>-<last>/<total>`,
+/// where `<total>` may be `*` if it is unknown.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ContentRange {
+	first: u64,
+	last: u64,
+	total: Option<u64>,
+}
+
+// A range always covers at least one byte, so it can never be empty.
+#[allow(clippy::len_without_is_empty)]
+impl ContentRange {
+	/// Creates a new range covering the bytes from `first` to `last`, inclusive.
+	///
+	/// Returns `None` if `last` comes before `first`,
+	/// if the range doesn't fit within `total`,
+	/// or if `last` is `u64::MAX`, as the range would end past the largest possible file.
+	pub fn new(first: u64, last: u64, total: impl Into<Option<u64>>) -> Option<Self> {
+		let total = total.into();
+		if last < first || last == u64::MAX || total.is_some_and(|total| last >= total) {
+			return None;
+		}
+		Some(Self { first, last, total })
+	}
+
+	/// The offset of the first byte in the range.
+	#[inline]
+	pub fn first(&self) -> u64 {
+		self.first
+	}
+
+	/// The offset of the last byte in the range, inclusive.
+	#[inline]
+	pub fn last(&self) -> u64 {
+		self.last
+	}
+
+	/// The amount of bytes in the range, which is always at least 1.
+	#[inline]
+	pub fn len(&self) -> u64 {
+		self.last - self.first + 1
+	}
+
+	/// The total length of the file, if it was specified.
+	#[inline]
+	pub fn total(&self) -> Option<u64> {
+		self.total
+	}
+}
+
+impl fmt::Display for ContentRange {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "bytes {}-{}/", self.first, self.last)?;
+		match self.total {
+			Some(total) => write!(f, "{}", total),
+			None => f.write_str("*"),
+		}
+	}
+}
+
+/// The error returned when parsing an invalid `Content-Range` header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidContentRange;
+
+impl fmt::Display for InvalidContentRange {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("invalid Content-Range header")
+	}
+}
+
+impl std::error::Error for InvalidContentRange {}
+
+impl FromStr for ContentRange {
+	type Err = InvalidContentRange;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let range = s.trim().strip_prefix("bytes ").ok_or(InvalidContentRange)?;
+		let (range, total) = range.split_once('/').ok_or(InvalidContentRange)?;
+		let (first, last) = range.split_once('-').ok_or(InvalidContentRange)?;
+		let total = match total {
+			"*" => None,
+			total => Some(parse_offset(total)?),
+		};
+		Self::new(parse_offset(first)?, parse_offset(last)?, total).ok_or(InvalidContentRange)
+	}
+}
+
+// `u64::from_str` also accepts a leading `+`, which isn't valid in a header.
+fn parse_offset(s: &str) -> Result<u64, InvalidContentRange> {
+	if s.is_empty() || !s.bytes().all(|byte| byte.is_ascii_digit()) {
+		return Err(InvalidContentRange);
+	}
+	s.parse().map_err(|_| InvalidContentRange)
+}
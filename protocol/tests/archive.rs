@@ -0,0 +1,516 @@
+use std::{
+	fs, io,
+	time::{Duration, SystemTime},
+};
+use tempfile::TempDir;
+use time::{macros::datetime, OffsetDateTime};
+use xfp_protocol::{
+	archive::{ArchiveFormat, ArchiveWriter, SymlinkPolicy},
+	metadata::{FilePermissions, Metadata, NameCache},
+};
+
+const FORMATS: [ArchiveFormat; 3] = [ArchiveFormat::Tar, ArchiveFormat::TarGz, ArchiveFormat::Zip];
+
+/// An entry read back from an archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ReadEntry {
+	/// The path, with a trailing `/` for directories.
+	path: String,
+	mode: u32,
+	mtime: i64,
+	/// The symlink target, if this is a symlink.
+	link: Option<String>,
+	data: Vec<u8>,
+}
+
+struct BitReader<'a> {
+	data: &'a [u8],
+	bit: usize,
+}
+
+impl BitReader<'_> {
+	fn bit(&mut self) -> u32 {
+		let bit = (self.data[self.bit / 8] >> (self.bit % 8)) & 1;
+		self.bit += 1;
+		u32::from(bit)
+	}
+
+	fn bits(&mut self, count: u32) -> u32 {
+		(0..count).fold(0, |value, i| value | self.bit() << i)
+	}
+
+	/// Reads a Huffman code of `count` bits, which is packed most significant bit first.
+	fn code(&mut self, code: u32, count: u32) -> u32 {
+		(0..count).fold(code, |code, _| code << 1 | self.bit())
+	}
+}
+
+/// Decodes the stored and fixed Huffman DEFLATE blocks the writer produces,
+/// returning the data and how many bytes were read.
+fn inflate(data: &[u8]) -> (Vec<u8>, usize) {
+	const LENGTH_BASE: [usize; 29] = [
+		3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115,
+		131, 163, 195, 227, 258,
+	];
+	const LENGTH_EXTRA: [u32; 29] = [
+		0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+	];
+	const DISTANCE_BASE: [usize; 30] = [
+		1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+		2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+	];
+	const DISTANCE_EXTRA: [u32; 30] = [
+		0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12,
+		13, 13,
+	];
+	let mut reader = BitReader { data, bit: 0 };
+	let mut out = Vec::new();
+	loop {
+		let last = reader.bit() == 1;
+		match reader.bits(2) {
+			0 => {
+				reader.bit = reader.bit.div_ceil(8) * 8;
+				let start = reader.bit / 8;
+				let len = usize::from(u16::from_le_bytes([data[start], data[start + 1]]));
+				let nlen = u16::from_le_bytes([data[start + 2], data[start + 3]]);
+				assert_eq!(len as u16, !nlen);
+				out.extend_from_slice(&data[start + 4..start + 4 + len]);
+				reader.bit = (start + 4 + len) * 8;
+			}
+			1 => loop {
+				let code = reader.code(0, 7);
+				let symbol = if code <= 0b0010111 {
+					256 + code
+				} else {
+					let code = reader.code(code, 1);
+					match code {
+						0x30..=0xBF => code - 0x30,
+						0xC0..=0xC7 => 280 + code - 0xC0,
+						_ => 144 + reader.code(code, 1) - 0x190,
+					}
+				};
+				match symbol {
+					0..=255 => out.push(symbol as u8),
+					256 => break,
+					_ => {
+						let index = (symbol - 257) as usize;
+						let length = LENGTH_BASE[index] + reader.bits(LENGTH_EXTRA[index]) as usize;
+						let index = reader.code(0, 5) as usize;
+						let distance =
+							DISTANCE_BASE[index] + reader.bits(DISTANCE_EXTRA[index]) as usize;
+						for _ in 0..length {
+							out.push(out[out.len() - distance]);
+						}
+					}
+				}
+			},
+			kind => panic!("unexpected block type {}", kind),
+		}
+		if last {
+			return (out, reader.bit.div_ceil(8));
+		}
+	}
+}
+
+fn gunzip(data: &[u8]) -> Vec<u8> {
+	assert_eq!(data[..3], [0x1F, 0x8B, 8]);
+	let (out, read) = inflate(&data[10..]);
+	let trailer = &data[10 + read..];
+	assert_eq!(trailer.len(), 8);
+	assert_eq!(
+		u32::from_le_bytes(trailer[4..].try_into().unwrap()),
+		out.len() as u32
+	);
+	out
+}
+
+fn octal(field: &[u8]) -> u64 {
+	let digits = std::str::from_utf8(field).unwrap();
+	u64::from_str_radix(digits.trim_matches(['\0', ' ']), 8).unwrap_or(0)
+}
+
+fn text(field: &[u8]) -> String {
+	let end = field
+		.iter()
+		.position(|byte| *byte == 0)
+		.unwrap_or(field.len());
+	String::from_utf8(field[..end].to_vec()).unwrap()
+}
+
+fn untar(data: &[u8]) -> Vec<ReadEntry> {
+	let mut entries = Vec::new();
+	let mut offset = 0;
+	let mut pax_path = None;
+	while data[offset..offset + 512].iter().any(|byte| *byte != 0) {
+		let header = &data[offset..offset + 512];
+		let checksum = header
+			.iter()
+			.enumerate()
+			.map(|(i, byte)| {
+				if (148..156).contains(&i) {
+					32
+				} else {
+					u64::from(*byte)
+				}
+			})
+			.sum::<u64>();
+		assert_eq!(octal(&header[148..156]), checksum);
+		assert_eq!(&header[257..263], b"ustar\0");
+		let size = octal(&header[124..136]) as usize;
+		let body = &data[offset + 512..offset + 512 + size];
+		offset += 512 + size.div_ceil(512) * 512;
+		if header[156] == b'x' {
+			let records = std::str::from_utf8(body).unwrap();
+			pax_path = records
+				.lines()
+				.find_map(|record| record.split_once(" path="))
+				.map(|(_, path)| path.to_string());
+			continue;
+		}
+		let prefix = text(&header[345..500]);
+		let name = text(&header[..100]);
+		let path = pax_path.take().unwrap_or(if prefix.is_empty() {
+			name
+		} else {
+			format!("{}/{}", prefix, name)
+		});
+		entries.push(ReadEntry {
+			path,
+			mode: octal(&header[100..108]) as u32,
+			mtime: octal(&header[136..148]) as i64,
+			link: (header[156] == b'2').then(|| text(&header[157..257])),
+			data: body.to_vec(),
+		});
+	}
+	assert!(data[offset..].iter().all(|byte| *byte == 0));
+	assert_eq!(data.len() - offset, 1024);
+	entries
+}
+
+fn u16_at(data: &[u8], offset: usize) -> usize {
+	usize::from(u16::from_le_bytes(
+		data[offset..offset + 2].try_into().unwrap(),
+	))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+	u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn unzip(data: &[u8]) -> Vec<ReadEntry> {
+	let end = data.len() - 22;
+	assert_eq!(u32_at(data, end), 0x0605_4B50);
+	let count = u16_at(data, end + 10);
+	let mut offset = u32_at(data, end + 16) as usize;
+	let mut entries = Vec::new();
+	for _ in 0..count {
+		assert_eq!(u32_at(data, offset), 0x0201_4B50);
+		let method = u16_at(data, offset + 10);
+		let compressed = u32_at(data, offset + 20) as usize;
+		let size = u32_at(data, offset + 24) as usize;
+		let name_len = u16_at(data, offset + 28);
+		let extra_len = u16_at(data, offset + 30);
+		let comment_len = u16_at(data, offset + 32);
+		let external = u32_at(data, offset + 38);
+		let local = u32_at(data, offset + 42) as usize;
+		let path = String::from_utf8(data[offset + 46..offset + 46 + name_len].to_vec()).unwrap();
+		let extra = &data[offset + 46 + name_len..offset + 46 + name_len + extra_len];
+		// The extended timestamp extra field, with only the modification time.
+		assert_eq!(u16_at(extra, 0), 0x5455);
+		let mtime = i64::from(u32_at(extra, 5) as i32);
+		offset += 46 + name_len + extra_len + comment_len;
+
+		assert_eq!(u32_at(data, local), 0x0403_4B50);
+		let start = local + 30 + u16_at(data, local + 26) + u16_at(data, local + 28);
+		let contents = match method {
+			0 => data[start..start + size].to_vec(),
+			8 => {
+				let (contents, read) = inflate(&data[start..]);
+				assert_eq!(read, compressed);
+				contents
+			}
+			method => panic!("unexpected method {}", method),
+		};
+		assert_eq!(contents.len(), size);
+		let mode = external >> 16;
+		let is_symlink = mode & 0o170000 == 0o120000;
+		entries.push(ReadEntry {
+			path,
+			mode: mode & 0o7777,
+			mtime,
+			link: is_symlink.then(|| String::from_utf8(contents.clone()).unwrap()),
+			data: if is_symlink { Vec::new() } else { contents },
+		});
+	}
+	entries
+}
+
+fn read_back(format: ArchiveFormat, data: &[u8]) -> Vec<ReadEntry> {
+	match format {
+		ArchiveFormat::Tar => untar(data),
+		ArchiveFormat::TarGz => untar(&gunzip(data)),
+		ArchiveFormat::Zip => unzip(data),
+	}
+}
+
+fn metadata(mode: u32, size: u64) -> Metadata {
+	Metadata::default()
+		.with_size(size)
+		.with_updated(datetime!(2022-07-01 12:34:56 UTC))
+		.with_permissions(FilePermissions::default().with_mode(mode))
+}
+
+fn entry(path: &str, mode: u32, link: Option<&str>, data: &[u8]) -> ReadEntry {
+	ReadEntry {
+		path: path.to_string(),
+		mode,
+		mtime: datetime!(2022-07-01 12:34:56 UTC).unix_timestamp(),
+		link: link.map(str::to_string),
+		data: data.to_vec(),
+	}
+}
+
+/// Data that is long enough to span several blocks, with both repetitive and incompressible parts.
+fn big_data() -> Vec<u8> {
+	let mut state = 0x2545_F491_u32;
+	let mut data = b"hello, world! ".repeat(10_000);
+	data.extend((0..100_000).map(|_| {
+		state ^= state << 13;
+		state ^= state >> 17;
+		state ^= state << 5;
+		state as u8
+	}));
+	data.extend_from_slice(&b"abc".repeat(30_000));
+	data
+}
+
+#[test]
+fn entries_round_trip_in_every_format() {
+	let big = big_data();
+	let long_path = format!("{}/file", "long-directory-name".repeat(8));
+	for format in FORMATS {
+		let mut archive = ArchiveWriter::new(format, Vec::new()).unwrap();
+		archive.append_dir("dir", &metadata(0o750, 0)).unwrap();
+		archive
+			.append_file("dir/small.txt", &metadata(0o640, 5), &b"hello"[..])
+			.unwrap();
+		archive
+			.append_file(
+				"big.bin",
+				&metadata(0o755, big.len() as u64),
+				big.as_slice(),
+			)
+			.unwrap();
+		archive
+			.append_file("empty", &metadata(0o600, 0), io::empty())
+			.unwrap();
+		archive
+			.append_symlink("link", &metadata(0o777, 0), "dir/small.txt")
+			.unwrap();
+		archive
+			.append_file(&long_path, &metadata(0o644, 4), &b"long"[..])
+			.unwrap();
+		let data = archive.finish().unwrap();
+		assert_eq!(
+			read_back(format, &data),
+			[
+				entry("dir/", 0o750, None, b""),
+				entry("dir/small.txt", 0o640, None, b"hello"),
+				entry("big.bin", 0o755, None, &big),
+				entry("empty", 0o600, None, b""),
+				entry("link", 0o777, Some("dir/small.txt"), b""),
+				entry(&long_path, 0o644, None, b"long"),
+			],
+			"{:?}",
+			format
+		);
+	}
+}
+
+#[test]
+fn compressed_formats_are_smaller() {
+	let data = b"hello, world! ".repeat(10_000);
+	let metadata = metadata(0o644, data.len() as u64);
+	for format in [ArchiveFormat::TarGz, ArchiveFormat::Zip] {
+		let mut archive = ArchiveWriter::new(format, Vec::new()).unwrap();
+		archive
+			.append_file("file", &metadata, data.as_slice())
+			.unwrap();
+		let archive = archive.finish().unwrap();
+		assert!(archive.len() < data.len() / 10, "{:?}", format);
+	}
+}
+
+#[test]
+fn files_must_match_their_size() {
+	for format in FORMATS {
+		let mut archive = ArchiveWriter::new(format, Vec::new()).unwrap();
+		let err = archive
+			.append_file("file", &metadata(0o644, 10), &b"short"[..])
+			.unwrap_err();
+		assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+		// Data past the size is ignored.
+		let mut archive = ArchiveWriter::new(format, Vec::new()).unwrap();
+		archive
+			.append_file("file", &metadata(0o644, 2), &b"long"[..])
+			.unwrap();
+		let data = archive.finish().unwrap();
+		assert_eq!(read_back(format, &data)[0].data, b"lo");
+	}
+}
+
+#[test]
+fn invalid_paths_are_rejected() {
+	let mut archive = ArchiveWriter::new(ArchiveFormat::Tar, Vec::new()).unwrap();
+	for path in ["", "/absolute", "a/../b", "a//b", "./a", "a/"] {
+		let err = archive
+			.append_file(path, &metadata(0o644, 0), io::empty())
+			.unwrap_err();
+		assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{}", path);
+	}
+}
+
+/// Creates `dir/file.txt`, `dir/secret.txt`, `private/key` and `top.txt`, with known modification times.
+fn tree() -> TempDir {
+	let dir = tempfile::tempdir().unwrap();
+	fs::create_dir_all(dir.path().join("dir")).unwrap();
+	fs::create_dir_all(dir.path().join("private")).unwrap();
+	let time = SystemTime::from(datetime!(2022-07-01 12:34:56 UTC));
+	for (path, contents) in [
+		("dir/file.txt", "file"),
+		("dir/secret.txt", "secret"),
+		("private/key", "key"),
+		("top.txt", "top"),
+	] {
+		let path = dir.path().join(path);
+		fs::write(&path, contents).unwrap();
+		fs::File::options()
+			.write(true)
+			.open(&path)
+			.unwrap()
+			.set_modified(time)
+			.unwrap();
+	}
+	dir
+}
+
+fn archive_tree(
+	dir: &TempDir,
+	format: ArchiveFormat,
+	symlinks: SymlinkPolicy,
+	authorize: impl FnMut(&str, &Metadata) -> bool,
+) -> Vec<ReadEntry> {
+	let mut archive = ArchiveWriter::new(format, Vec::new()).unwrap();
+	archive
+		.append_tree(
+			dir.path(),
+			symlinks,
+			&NameCache::new(Duration::from_secs(60)),
+			authorize,
+		)
+		.unwrap();
+	read_back(format, &archive.finish().unwrap())
+}
+
+fn paths(entries: &[ReadEntry]) -> Vec<&str> {
+	entries.iter().map(|entry| entry.path.as_str()).collect()
+}
+
+#[test]
+fn trees_keep_their_modification_times() {
+	let dir = tree();
+	for format in FORMATS {
+		let entries = archive_tree(&dir, format, SymlinkPolicy::Preserve, |_, _| true);
+		assert_eq!(
+			paths(&entries),
+			[
+				"dir/",
+				"dir/file.txt",
+				"dir/secret.txt",
+				"private/",
+				"private/key",
+				"top.txt"
+			]
+		);
+		let file = &entries[1];
+		assert_eq!(file.data, b"file");
+		assert_eq!(
+			OffsetDateTime::from_unix_timestamp(file.mtime).unwrap(),
+			datetime!(2022-07-01 12:34:56 UTC)
+		);
+	}
+}
+
+#[cfg(unix)]
+#[test]
+fn trees_keep_their_modes() {
+	use std::os::unix::fs::PermissionsExt;
+	let dir = tree();
+	let path = dir.path().join("top.txt");
+	fs::set_permissions(&path, fs::Permissions::from_mode(0o751)).unwrap();
+	for format in FORMATS {
+		let entries = archive_tree(&dir, format, SymlinkPolicy::Preserve, |_, _| true);
+		let top = entries
+			.iter()
+			.find(|entry| entry.path == "top.txt")
+			.unwrap();
+		assert_eq!(top.mode, 0o751, "{:?}", format);
+	}
+}
+
+#[test]
+fn unauthorized_entries_are_left_out() {
+	let dir = tree();
+	let mut asked = Vec::new();
+	let entries = archive_tree(
+		&dir,
+		ArchiveFormat::Tar,
+		SymlinkPolicy::Preserve,
+		|path, _| {
+			asked.push(path.to_string());
+			path != "private" && !path.ends_with("secret.txt")
+		},
+	);
+	assert_eq!(paths(&entries), ["dir/", "dir/file.txt", "top.txt"]);
+	// The contents of a left out directory are never looked at.
+	assert!(!asked.iter().any(|path| path == "private/key"));
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinks_follow_the_policy() {
+	use std::os::unix::fs::symlink;
+	let dir = tree();
+	let outside = tempfile::tempdir().unwrap();
+	fs::write(outside.path().join("outside.txt"), "outside").unwrap();
+	symlink("dir/file.txt", dir.path().join("inside")).unwrap();
+	symlink(
+		outside.path().join("outside.txt"),
+		dir.path().join("outside"),
+	)
+	.unwrap();
+	symlink("..", dir.path().join("dir/loop")).unwrap();
+
+	let entries = archive_tree(&dir, ArchiveFormat::Tar, SymlinkPolicy::Preserve, |_, _| {
+		true
+	});
+	let inside = entries.iter().find(|entry| entry.path == "inside").unwrap();
+	assert_eq!(inside.link.as_deref(), Some("dir/file.txt"));
+	let loop_link = entries
+		.iter()
+		.find(|entry| entry.path == "dir/loop")
+		.unwrap();
+	assert_eq!(loop_link.link.as_deref(), Some(".."));
+	assert!(entries.iter().any(|entry| entry.path == "outside"));
+
+	let entries = archive_tree(&dir, ArchiveFormat::Tar, SymlinkPolicy::Follow, |_, _| true);
+	let inside = entries.iter().find(|entry| entry.path == "inside").unwrap();
+	assert_eq!(inside.link, None);
+	assert_eq!(inside.data, b"file");
+	assert!(!entries.iter().any(|entry| entry.path == "outside"));
+	// The loop back to the root is stored as an empty directory.
+	assert!(entries.iter().any(|entry| entry.path == "dir/loop/"));
+	assert!(!entries
+		.iter()
+		.any(|entry| entry.path.starts_with("dir/loop/") && entry.path != "dir/loop/"));
+}
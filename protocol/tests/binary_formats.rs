@@ -0,0 +1,236 @@
+use time::macros::datetime;
+use xfp_protocol::{
+	dir::DirectoryListEntry,
+	file::FileType,
+	format::{Format, ListingFormat, NativeTimestamps},
+	metadata::{FileOwner, FilePermissions, Metadata},
+};
+
+fn metadata() -> Metadata {
+	Metadata::default()
+		.with_size(u64::MAX)
+		.with_created(datetime!(1969-07-20 20:17:40 UTC))
+		.with_updated(datetime!(2022-07-01 12:34:56.123456789 UTC))
+		.with_accessed(datetime!(2500-01-01 00:00:00.5 UTC))
+		.with_changed(datetime!(2022-07-01 12:00:00 UTC))
+		.with_permissions(
+			FilePermissions::default()
+				.with_read(true)
+				.with_mode(0o644)
+				.with_owner(FileOwner::Unix {
+					uid: 1000,
+					name: "lucy".to_string(),
+				}),
+		)
+}
+
+#[test]
+fn listings_round_trip_in_every_format() {
+	let listing = vec![
+		DirectoryListEntry::default()
+			.with_name("a")
+			.with_metadata(metadata()),
+		DirectoryListEntry::default()
+			.with_name("b")
+			.with_file_type(FileType::Directory),
+	];
+	for format in [Format::Json, Format::Cbor, Format::MessagePack] {
+		let body = format.encode(&listing).unwrap();
+		let decoded: Vec<DirectoryListEntry> = format.decode(&body).unwrap();
+		assert_eq!(decoded, listing, "{:?}", format);
+	}
+}
+
+#[test]
+fn cbor_timestamps_are_tagged() {
+	let body = Format::Cbor
+		.encode(&Metadata::default().with_changed(datetime!(2022-07-01 12:00:00 UTC)))
+		.unwrap();
+	let value: ciborium::value::Value = ciborium::de::from_reader(&body[..]).unwrap();
+	let changed = value
+		.as_map()
+		.unwrap()
+		.iter()
+		.find(|(key, _)| key.as_text() == Some("changed"))
+		.map(|(_, value)| value.clone());
+	assert_eq!(
+		changed,
+		Some(ciborium::value::Value::Tag(
+			0,
+			Box::new(ciborium::value::Value::Text(
+				"2022-07-01T12:00:00Z".to_string()
+			))
+		))
+	);
+}
+
+#[test]
+fn msgpack_timestamps_use_the_extension_type() {
+	let body = Format::MessagePack
+		.encode(&Metadata::default().with_changed(datetime!(2022-07-01 12:00:00 UTC)))
+		.unwrap();
+	// fixext 4, type -1, followed by the seconds since the epoch.
+	let mut expected = vec![0xd6, 0xff];
+	expected.extend_from_slice(&1656676800u32.to_be_bytes());
+	assert!(body
+		.windows(expected.len())
+		.any(|window| window == expected));
+}
+
+#[test]
+fn timestamps_are_plain_strings_unless_asked_for_natively() {
+	let metadata = Metadata::default().with_changed(datetime!(2022-07-01 12:00:00 UTC));
+	let mut plain = Vec::new();
+	ciborium::ser::into_writer(&metadata, &mut plain).unwrap();
+	let value: ciborium::value::Value = ciborium::de::from_reader(&plain[..]).unwrap();
+	assert!(value.as_map().unwrap().contains(&(
+		ciborium::value::Value::Text("changed".to_string()),
+		ciborium::value::Value::Text("2022-07-01T12:00:00Z".to_string())
+	)));
+	let plain = rmp_serde::to_vec_named(&metadata).unwrap();
+	assert!(plain
+		.windows(b"2022-07-01T12:00:00Z".len())
+		.any(|window| window == b"2022-07-01T12:00:00Z"));
+
+	for format in [Format::Cbor, Format::MessagePack] {
+		let native = match format {
+			Format::Cbor => {
+				let mut body = Vec::new();
+				ciborium::ser::into_writer(&NativeTimestamps::new(format, &metadata), &mut body)
+					.unwrap();
+				body
+			}
+			_ => rmp_serde::to_vec_named(&NativeTimestamps::new(format, &metadata)).unwrap(),
+		};
+		assert_eq!(native, format.encode(&metadata).unwrap(), "{:?}", format);
+	}
+}
+
+#[test]
+fn either_timestamp_form_is_decoded_however_it_is_decoded() {
+	let metadata = metadata();
+	let mut plain_cbor = Vec::new();
+	ciborium::ser::into_writer(&metadata, &mut plain_cbor).unwrap();
+	let native_cbor = Format::Cbor.encode(&metadata).unwrap();
+	for body in [&plain_cbor, &native_cbor] {
+		assert_eq!(Format::Cbor.decode::<Metadata>(body).unwrap(), metadata);
+		let decoded: Metadata = ciborium::de::from_reader(&body[..]).unwrap();
+		assert_eq!(decoded, metadata);
+	}
+	let plain_msgpack = rmp_serde::to_vec_named(&metadata).unwrap();
+	let native_msgpack = Format::MessagePack.encode(&metadata).unwrap();
+	for body in [&plain_msgpack, &native_msgpack] {
+		assert_eq!(
+			Format::MessagePack.decode::<Metadata>(body).unwrap(),
+			metadata
+		);
+		assert_eq!(rmp_serde::from_slice::<Metadata>(body).unwrap(), metadata);
+	}
+}
+
+#[test]
+fn negotiates_formats_from_accept() {
+	assert_eq!(Format::negotiate("application/cbor"), Some(Format::Cbor));
+	assert_eq!(
+		Format::negotiate("application/json;q=0.5, application/msgpack"),
+		Some(Format::MessagePack)
+	);
+	assert_eq!(Format::negotiate("*/*"), Some(Format::Json));
+	assert_eq!(Format::negotiate("application/cbor;q=0, text/html"), None);
+	assert_eq!(
+		Format::from_content_type("application/x-msgpack; charset=binary"),
+		Some(Format::MessagePack)
+	);
+}
+
+#[test]
+fn negotiation_prefers_the_most_specific_range() {
+	assert_eq!(
+		Format::negotiate("application/json;q=0, */*"),
+		Some(Format::Cbor)
+	);
+	assert_eq!(
+		Format::negotiate("application/*;q=0.2, application/msgpack;q=0.5, */*;q=0.9"),
+		Some(Format::MessagePack)
+	);
+	assert_eq!(
+		Format::negotiate("application/cbor, application/json"),
+		Some(Format::Cbor)
+	);
+	assert_eq!(Format::negotiate("application/*;q=0, */*"), None);
+}
+
+#[test]
+fn negotiation_ignores_invalid_quality_values() {
+	assert_eq!(
+		Format::negotiate("application/cbor;q=oops, application/json;q=0.5"),
+		Some(Format::Json)
+	);
+	for quality in ["NaN", "inf", "2", "0.5555", "-1", ""] {
+		assert_eq!(
+			Format::negotiate(&format!("application/cbor;q={}", quality)),
+			None,
+			"{}",
+			quality
+		);
+	}
+}
+
+#[test]
+fn negotiation_defaults_on_an_empty_accept() {
+	assert_eq!(Format::negotiate(""), Some(Format::Json));
+	assert_eq!(Format::negotiate("  "), Some(Format::Json));
+	assert_eq!(
+		ListingFormat::negotiate(""),
+		Some(ListingFormat::Body(Format::Json))
+	);
+}
+
+#[test]
+fn listings_negotiate_streaming() {
+	assert_eq!(
+		ListingFormat::negotiate("application/x-ndjson"),
+		Some(ListingFormat::Ndjson)
+	);
+	assert_eq!(
+		ListingFormat::negotiate("application/x-ndjson, application/json"),
+		Some(ListingFormat::Ndjson)
+	);
+	assert_eq!(
+		ListingFormat::negotiate("application/x-ndjson;q=0.5, application/cbor"),
+		Some(ListingFormat::Body(Format::Cbor))
+	);
+	assert_eq!(
+		ListingFormat::negotiate("*/*"),
+		Some(ListingFormat::Body(Format::Json))
+	);
+	assert_eq!(Format::negotiate("application/x-ndjson"), None);
+	assert_eq!(ListingFormat::Ndjson.content_type(), "application/x-ndjson");
+}
+
+/// Encodes metadata with its changed time set to an epoch-based CBOR date/time of `seconds`.
+fn cbor_with_epoch_float(seconds: f64) -> Vec<u8> {
+	use ciborium::value::Value;
+	let body = Format::Cbor.encode(&Metadata::default()).unwrap();
+	let mut value: Value = ciborium::de::from_reader(&body[..]).unwrap();
+	value.as_map_mut().unwrap().push((
+		Value::Text("changed".to_string()),
+		Value::Tag(1, Box::new(Value::Float(seconds))),
+	));
+	let mut body = Vec::new();
+	ciborium::ser::into_writer(&value, &mut body).unwrap();
+	body
+}
+
+#[test]
+fn cbor_rejects_non_finite_timestamps() {
+	let metadata: Metadata = Format::Cbor.decode(&cbor_with_epoch_float(1.5)).unwrap();
+	assert_eq!(
+		metadata.changed(),
+		Some(datetime!(1970-01-01 00:00:01.5 UTC))
+	);
+	for seconds in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY, 1e300] {
+		let result = Format::Cbor.decode::<Metadata>(&cbor_with_epoch_float(seconds));
+		assert!(result.is_err(), "{}", seconds);
+	}
+}
@@ -0,0 +1,82 @@
+use serde_json::json;
+use xfp_protocol::{
+	archive::ArchiveFormat,
+	capabilities::{AuthScheme, Capabilities, Endpoint, Feature, Unsupported},
+	hash::HashAlgorithm,
+};
+
+fn newer_server() -> Capabilities {
+	serde_json::from_value(json!({
+		"version": "0.9",
+		"endpoints": ["file", "hash", "teleport"],
+		"features": {
+			"ranges": true,
+			"archives": ["tar", "tar.zst", "zip", 7],
+			"hashes": ["sha256", "sha3-512", "blake3"],
+			"quantum": true
+		},
+		"auth": ["bearer", "passkey"]
+	}))
+	.unwrap()
+}
+
+#[test]
+fn unknown_entries_from_newer_servers_are_tolerated() {
+	let capabilities = newer_server();
+	assert!(capabilities.is_compatible());
+	assert_eq!(
+		capabilities.endpoints(),
+		[Endpoint::File, Endpoint::Hash, Endpoint::Unknown]
+	);
+	assert_eq!(
+		capabilities.features().archives(),
+		[ArchiveFormat::Tar, ArchiveFormat::Zip]
+	);
+	assert_eq!(
+		capabilities.features().hashes(),
+		[HashAlgorithm::Sha256, HashAlgorithm::Blake3]
+	);
+	assert_eq!(
+		capabilities.auth(),
+		[AuthScheme::Bearer, AuthScheme::Unknown]
+	);
+}
+
+#[test]
+fn features_are_gated_on_the_descriptor() {
+	let capabilities = newer_server();
+	assert!(capabilities.supports(Feature::Endpoint(Endpoint::Hash)));
+	assert!(capabilities.supports(Feature::Ranges));
+	assert!(capabilities.supports(Feature::Archive(ArchiveFormat::Zip)));
+	assert!(!capabilities.supports(Feature::Archive(ArchiveFormat::TarGz)));
+	assert!(capabilities.supports(Feature::Hash(HashAlgorithm::Blake3)));
+	assert!(!capabilities.supports(Feature::Uploads));
+	// Unknown entries never count as support for anything.
+	assert!(!capabilities.supports(Feature::Endpoint(Endpoint::Unknown)));
+	assert!(!capabilities.supports(Feature::Auth(AuthScheme::Unknown)));
+	let err = capabilities
+		.require(Feature::Hash(HashAlgorithm::Md5))
+		.unwrap_err();
+	assert_eq!(err, Unsupported(Feature::Hash(HashAlgorithm::Md5)));
+	assert_eq!(err.to_string(), "the server does not support md5 hashes");
+}
+
+#[test]
+fn missing_fields_default_to_unsupported() {
+	let capabilities: Capabilities = serde_json::from_value(json!({"version": "0.2"})).unwrap();
+	assert_eq!(capabilities, Capabilities::default());
+	assert!(!capabilities.supports(Feature::Endpoint(Endpoint::File)));
+	let capabilities: Capabilities = serde_json::from_value(json!({"version": "1.0"})).unwrap();
+	assert!(!capabilities.is_compatible());
+}
+
+#[test]
+fn capabilities_round_trip() {
+	let capabilities = newer_server();
+	let json = serde_json::to_value(&capabilities).unwrap();
+	assert_eq!(json["features"]["archives"], json!(["tar", "zip"]));
+	assert_eq!(
+		serde_json::from_value::<Capabilities>(json).unwrap(),
+		capabilities
+	);
+}
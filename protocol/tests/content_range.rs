@@ -0,0 +1,69 @@
+use xfp_protocol::write::{ContentRange, InvalidContentRange};
+
+fn parse(header: &str) -> Result<ContentRange, InvalidContentRange> {
+	header.parse()
+}
+
+#[test]
+fn parses_known_and_unknown_totals() {
+	let range = parse("bytes 0-99/1000").unwrap();
+	assert_eq!(
+		(range.first(), range.last(), range.total()),
+		(0, 99, Some(1000))
+	);
+	assert_eq!(range.len(), 100);
+	let range = parse("bytes 5-5/*").unwrap();
+	assert_eq!((range.first(), range.last(), range.total()), (5, 5, None));
+	assert_eq!(range.len(), 1);
+}
+
+#[test]
+fn round_trips_through_display() {
+	for header in ["bytes 0-99/1000", "bytes 10-20/*"] {
+		assert_eq!(parse(header).unwrap().to_string(), header);
+	}
+}
+
+#[test]
+fn rejects_malformed_headers() {
+	for header in [
+		"",
+		"bytes",
+		"bytes 0-99",
+		"bytes 0/100",
+		"items 0-99/100",
+		"bytes -99/100",
+		"bytes 0-/100",
+		"bytes 0-99/",
+		"bytes +0-99/100",
+		"bytes 0-+99/100",
+		"bytes 0-99/+100",
+		"bytes 0x0-99/100",
+		"bytes 0-99/*/*",
+	] {
+		assert_eq!(parse(header), Err(InvalidContentRange), "{header:?}");
+	}
+}
+
+#[test]
+fn rejects_impossible_ranges() {
+	assert_eq!(parse("bytes 10-9/*"), Err(InvalidContentRange));
+	assert_eq!(parse("bytes 0-100/100"), Err(InvalidContentRange));
+	assert_eq!(parse("bytes 0-0/0"), Err(InvalidContentRange));
+	assert_eq!(ContentRange::new(10, 9, None), None);
+}
+
+#[test]
+fn rejects_ranges_ending_at_the_largest_offset() {
+	assert_eq!(
+		parse("bytes 0-18446744073709551615/*"),
+		Err(InvalidContentRange)
+	);
+	assert_eq!(ContentRange::new(u64::MAX, u64::MAX, None), None);
+	assert_eq!(
+		parse("bytes 0-18446744073709551616/*"),
+		Err(InvalidContentRange)
+	);
+	let range = ContentRange::new(0, u64::MAX - 1, None).unwrap();
+	assert_eq!(range.len(), u64::MAX);
+}
@@ -0,0 +1,160 @@
+use std::io::{self, Cursor};
+use xfp_protocol::delta::{Delta, DeltaOp, RollingChecksum, Signature, MAX_BLOCK_SIZE};
+
+/// Deterministic, incompressible-looking test data.
+fn data(len: usize, seed: u32) -> Vec<u8> {
+	let mut state = seed;
+	(0..len)
+		.map(|_| {
+			state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+			(state >> 16) as u8
+		})
+		.collect()
+}
+
+fn round_trip(old: &[u8], new: &[u8], block_size: u32) -> Delta {
+	let signature = Signature::from_reader(old, block_size).unwrap();
+	let delta = Delta::compute(&signature, new).unwrap();
+	assert_eq!(delta.size(), new.len() as u64);
+	let mut rebuilt = Vec::new();
+	delta.apply(Cursor::new(old), &mut rebuilt).unwrap();
+	assert_eq!(rebuilt, new);
+	delta
+}
+
+#[test]
+fn rolling_matches_a_fresh_checksum() {
+	let data = data(1000, 1);
+	for len in [1, 2, 7, 64] {
+		let mut rolling = RollingChecksum::new(&data[..len]);
+		for start in 1..data.len() - len {
+			rolling.roll(data[start - 1], data[start + len - 1]);
+			assert_eq!(
+				rolling,
+				RollingChecksum::new(&data[start..start + len]),
+				"len {len} start {start}"
+			);
+		}
+	}
+}
+
+#[test]
+fn signatures_use_the_rolling_checksum() {
+	let data = data(10, 2);
+	let signature = Signature::from_reader(&data[..], 4).unwrap();
+	assert_eq!(signature.size(), 10);
+	let weak = signature
+		.blocks()
+		.iter()
+		.map(|block| block.weak())
+		.collect::<Vec<_>>();
+	assert_eq!(
+		weak,
+		[
+			RollingChecksum::new(&data[..4]).digest(),
+			RollingChecksum::new(&data[4..8]).digest(),
+			RollingChecksum::new(&data[8..]).digest(),
+		]
+	);
+}
+
+#[test]
+fn identical_files_are_all_copies() {
+	let old = data(10_000, 3);
+	let delta = round_trip(&old, &old[..8192], 1024);
+	assert_eq!(delta.ops(), [DeltaOp::Copy { block: 0, count: 8 }]);
+	assert_eq!(delta.literal_len(), 0);
+}
+
+#[test]
+fn shifted_files_only_send_the_insertion() {
+	let old = data(8192, 4);
+	let mut new = b"inserted".to_vec();
+	new.extend_from_slice(&old);
+	let delta = round_trip(&old, &new, 1024);
+	assert_eq!(
+		delta.ops(),
+		[
+			DeltaOp::Data {
+				data: b"inserted".to_vec()
+			},
+			DeltaOp::Copy { block: 0, count: 8 },
+		]
+	);
+}
+
+#[test]
+fn edits_round_trip() {
+	let old = data(20_000, 5);
+	let mut new = old.clone();
+	new[5000..5100].copy_from_slice(&data(100, 6));
+	new.drain(12_000..12_500);
+	new.extend_from_slice(&data(777, 7));
+	let delta = round_trip(&old, &new, 512);
+	assert!(delta.literal_len() < 2000, "{}", delta.literal_len());
+	// Reordered blocks are still found.
+	let mut swapped = old[4096..8192].to_vec();
+	swapped.extend_from_slice(&old[..4096]);
+	assert_eq!(round_trip(&old, &swapped, 4096).literal_len(), 0);
+}
+
+#[test]
+fn small_and_empty_files_round_trip() {
+	round_trip(b"", b"", 16);
+	round_trip(b"", b"new", 16);
+	round_trip(b"old", b"", 16);
+	round_trip(b"short", b"short", 16);
+	round_trip(&data(100, 8), &data(50, 9), 1);
+}
+
+#[test]
+fn zero_block_sizes_are_rejected() {
+	let signature: Signature =
+		serde_json::from_str(r#"{"block_size":0,"size":0,"blocks":[]}"#).unwrap();
+	let err = Delta::compute(&signature, &b"data"[..]).unwrap_err();
+	assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn oversized_block_sizes_are_rejected() {
+	assert!(Signature::from_reader(&b"data"[..], MAX_BLOCK_SIZE).is_ok());
+	let err = Signature::from_reader(&b"data"[..], MAX_BLOCK_SIZE + 1).unwrap_err();
+	assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+	let signature: Signature =
+		serde_json::from_str(r#"{"block_size":4294967295,"size":0,"blocks":[]}"#).unwrap();
+	let err = Delta::compute(&signature, &b"data"[..]).unwrap_err();
+	assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+fn apply(json: &str, base: &[u8]) -> io::Result<Vec<u8>> {
+	let delta: Delta = serde_json::from_str(json).unwrap();
+	let mut output = Vec::new();
+	delta.apply(Cursor::new(base), &mut output)?;
+	Ok(output)
+}
+
+#[test]
+fn invalid_deltas_are_rejected() {
+	let base = data(100, 10);
+	assert_eq!(
+		apply(
+			r#"{"block_size":10,"size":20,"ops":[{"op":"copy","block":2,"count":2}]}"#,
+			&base
+		)
+		.unwrap(),
+		base[20..40]
+	);
+	for json in [
+		// The start of the copy overflows.
+		r#"{"block_size":4096,"size":0,"ops":[{"op":"copy","block":"18446744073709551615","count":1}]}"#,
+		// The length of the copy overflows.
+		r#"{"block_size":4096,"size":0,"ops":[{"op":"copy","block":0,"count":"18446744073709551615"}]}"#,
+		// The copy is past the end of the file.
+		r#"{"block_size":10,"size":20,"ops":[{"op":"copy","block":9,"count":2}]}"#,
+		// The size is wrong.
+		r#"{"block_size":10,"size":11,"ops":[{"op":"copy","block":0,"count":1}]}"#,
+	] {
+		let err = apply(json, &base).unwrap_err();
+		assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{json}");
+	}
+}
@@ -0,0 +1,104 @@
+use std::{
+	fs::{self, File},
+	path::Path,
+	time::SystemTime,
+};
+use xfp_protocol::hash::{FileHash, HashAlgorithm, HashCache, InvalidHash};
+
+const EMPTY_SHA256: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+fn hash(algorithm: &str, hash: &str) -> FileHash {
+	serde_json::from_value(serde_json::json!({ "algorithm": algorithm, "hash": hash })).unwrap()
+}
+
+/// Rewrites a file with same-length contents, without changing its modification time,
+/// so that only a cached hash can tell the difference.
+fn rewrite_in_place(path: &Path, contents: &str) {
+	let modified = fs::metadata(path).unwrap().modified().unwrap();
+	fs::write(path, contents).unwrap();
+	File::options()
+		.write(true)
+		.open(path)
+		.unwrap()
+		.set_modified(modified)
+		.unwrap();
+}
+
+#[test]
+fn repr_digest_encodes_the_raw_digest() {
+	let digest = FileHash::from_reader(&b""[..], HashAlgorithm::Sha256).unwrap();
+	assert_eq!(digest.hash(), EMPTY_SHA256);
+	assert_eq!(
+		digest.repr_digest().unwrap(),
+		"sha-256=:47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=:"
+	);
+}
+
+#[test]
+fn repr_digest_rejects_malformed_hashes() {
+	assert_eq!(hash("sha256", "").repr_digest(), Err(InvalidHash));
+	assert_eq!(
+		hash("sha256", &EMPTY_SHA256[1..]).repr_digest(),
+		Err(InvalidHash)
+	);
+	assert_eq!(hash("md5", EMPTY_SHA256).repr_digest(), Err(InvalidHash));
+	let garbage = format!("zz{}", &EMPTY_SHA256[2..]);
+	assert_eq!(hash("sha256", &garbage).repr_digest(), Err(InvalidHash));
+	let signed = format!("+e{}", &EMPTY_SHA256[2..]);
+	assert_eq!(hash("sha256", &signed).repr_digest(), Err(InvalidHash));
+}
+
+#[test]
+fn cache_rehashes_changed_files() {
+	let dir = tempfile::tempdir().unwrap();
+	let path = dir.path().join("file");
+	fs::write(&path, "one").unwrap();
+	let cache = HashCache::new(4);
+	let first = cache.hash(&path, HashAlgorithm::Blake3).unwrap();
+	fs::write(&path, "three").unwrap();
+	let second = cache.hash(&path, HashAlgorithm::Blake3).unwrap();
+	assert_ne!(first, second);
+	assert_eq!(
+		second,
+		FileHash::from_path(&path, HashAlgorithm::Blake3).unwrap()
+	);
+}
+
+#[test]
+fn cache_evicts_the_least_recently_used_hash() {
+	let dir = tempfile::tempdir().unwrap();
+	let paths = ["a", "b", "c"].map(|name| dir.path().join(name));
+	for path in &paths {
+		fs::write(path, "old").unwrap();
+		File::options()
+			.write(true)
+			.open(path)
+			.unwrap()
+			.set_modified(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000))
+			.unwrap();
+	}
+	let cache = HashCache::new(2);
+	let old = cache.hash(&paths[0], HashAlgorithm::Sha256).unwrap();
+	cache.hash(&paths[1], HashAlgorithm::Sha256).unwrap();
+	// Using `a` again makes `b` the least recently used, so it's the one evicted for `c`.
+	cache.hash(&paths[0], HashAlgorithm::Sha256).unwrap();
+	cache.hash(&paths[2], HashAlgorithm::Sha256).unwrap();
+	for path in &paths {
+		rewrite_in_place(path, "new");
+	}
+	assert_eq!(cache.hash(&paths[0], HashAlgorithm::Sha256).unwrap(), old);
+	assert_ne!(cache.hash(&paths[1], HashAlgorithm::Sha256).unwrap(), old);
+}
+
+#[test]
+fn cache_clear_forgets_everything() {
+	let dir = tempfile::tempdir().unwrap();
+	let path = dir.path().join("file");
+	fs::write(&path, "old").unwrap();
+	let cache = HashCache::new(4);
+	let old = cache.hash(&path, HashAlgorithm::Md5).unwrap();
+	rewrite_in_place(&path, "new");
+	assert_eq!(cache.hash(&path, HashAlgorithm::Md5).unwrap(), old);
+	cache.clear();
+	assert_ne!(cache.hash(&path, HashAlgorithm::Md5).unwrap(), old);
+}
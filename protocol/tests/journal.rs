@@ -0,0 +1,249 @@
+use std::{fs, io::Write};
+use time::{Duration, OffsetDateTime};
+use xfp_protocol::{
+	journal::{InvalidSyncToken, Journal, SyncToken},
+	watch::{WatchEvent, WatchEventKind},
+};
+
+fn created(path: &str) -> WatchEvent {
+	WatchEvent::new(WatchEventKind::Create, path)
+}
+
+fn paths(journal: &Journal, since: SyncToken, limit: Option<u64>) -> (Vec<String>, bool) {
+	let changes = journal.changes(Some(since), limit).unwrap();
+	assert!(!changes.resync_required());
+	let paths = changes
+		.changes()
+		.iter()
+		.map(|entry| entry.event().path().to_string())
+		.collect();
+	(paths, changes.more())
+}
+
+#[test]
+fn tokens_round_trip() {
+	let token = SyncToken::new(0xdead_beef, 42);
+	assert_eq!(token.to_string(), "00000000deadbeef000000000000002a");
+	assert_eq!(token.to_string().parse(), Ok(token));
+}
+
+#[test]
+fn tokens_must_be_exactly_32_lowercase_hex_digits() {
+	for token in [
+		"",
+		"00000000deadbeef000000000000002",
+		"00000000deadbeef000000000000002a0",
+		"+0000000deadbeef000000000000002a",
+		"00000000deadbeef+00000000000002a",
+		"00000000DEADBEEF000000000000002A",
+		"00000000deadbeef00000000000000 a",
+		"00000000deadbeef00000000000000é",
+	] {
+		assert_eq!(
+			token.parse::<SyncToken>(),
+			Err(InvalidSyncToken),
+			"{token:?}"
+		);
+	}
+}
+
+#[test]
+fn changes_continue_from_their_token() {
+	let dir = tempfile::tempdir().unwrap();
+	let journal = Journal::open(dir.path().join("journal")).unwrap();
+	let start = journal.token();
+	assert_eq!(start.seq(), 0);
+	for path in ["a", "b", "c"] {
+		journal.append(created(path)).unwrap();
+	}
+	let first = journal.changes(Some(start), Some(2)).unwrap();
+	assert!(first.more());
+	assert_eq!(first.changes().len(), 2);
+	let token = first.token().parse().unwrap();
+	assert_eq!(
+		paths(&journal, token, Some(2)),
+		(vec!["c".to_string()], false)
+	);
+	let end = journal.token();
+	assert_eq!(end.seq(), 3);
+	assert_eq!(paths(&journal, end, None), (Vec::new(), false));
+}
+
+#[test]
+fn no_token_only_returns_the_current_end() {
+	let dir = tempfile::tempdir().unwrap();
+	let journal = Journal::open(dir.path().join("journal")).unwrap();
+	journal.append(created("a")).unwrap();
+	let changes = journal.changes(None, None).unwrap();
+	assert!(changes.changes().is_empty());
+	assert_eq!(changes.token(), journal.token().to_string());
+}
+
+#[test]
+fn journals_persist_across_reopening() {
+	let dir = tempfile::tempdir().unwrap();
+	let path = dir.path().join("journal");
+	let journal = Journal::open(&path).unwrap();
+	let start = journal.token();
+	journal.append(created("a")).unwrap();
+	drop(journal);
+	let journal = Journal::open(&path).unwrap();
+	assert_eq!(journal.id(), start.journal());
+	journal.append(created("b")).unwrap();
+	assert_eq!(
+		paths(&journal, start, None),
+		(vec!["a".to_string(), "b".to_string()], false)
+	);
+}
+
+#[test]
+fn incomplete_lines_are_discarded() {
+	let dir = tempfile::tempdir().unwrap();
+	let path = dir.path().join("journal");
+	let journal = Journal::open(&path).unwrap();
+	let start = journal.token();
+	journal.append(created("a")).unwrap();
+	drop(journal);
+	fs::OpenOptions::new()
+		.append(true)
+		.open(&path)
+		.unwrap()
+		.write_all(b"{\"seq\":2,\"ti")
+		.unwrap();
+	let journal = Journal::open(&path).unwrap();
+	assert_eq!(journal.append(created("b")).unwrap().seq(), 2);
+	assert_eq!(
+		paths(&journal, start, None),
+		(vec!["a".to_string(), "b".to_string()], false)
+	);
+}
+
+#[test]
+fn foreign_and_future_tokens_require_a_resync() {
+	let dir = tempfile::tempdir().unwrap();
+	let journal = Journal::open(dir.path().join("journal")).unwrap();
+	let other = Journal::open(dir.path().join("other")).unwrap();
+	journal.append(created("a")).unwrap();
+	for token in [other.token(), SyncToken::new(journal.id(), 5)] {
+		let changes = journal.changes(Some(token), None).unwrap();
+		assert!(changes.resync_required());
+		assert!(changes.changes().is_empty());
+		assert_eq!(changes.token(), journal.token().to_string());
+	}
+}
+
+#[test]
+fn truncation_forces_older_tokens_to_resync() {
+	let dir = tempfile::tempdir().unwrap();
+	let path = dir.path().join("journal");
+	let journal = Journal::open(&path).unwrap();
+	let start = journal.token();
+	journal.append(created("a")).unwrap();
+	let after_a = journal.token();
+	journal.append(created("b")).unwrap();
+	assert_eq!(
+		journal
+			.truncate(OffsetDateTime::now_utc() - Duration::hours(1))
+			.unwrap(),
+		0
+	);
+	assert_eq!(
+		journal
+			.truncate(OffsetDateTime::now_utc() + Duration::hours(1))
+			.unwrap(),
+		2
+	);
+	assert!(journal
+		.changes(Some(start), None)
+		.unwrap()
+		.resync_required());
+	assert!(journal
+		.changes(Some(after_a), None)
+		.unwrap()
+		.resync_required());
+	let end = journal.token();
+	assert_eq!(end.seq(), 2);
+	journal.append(created("c")).unwrap();
+	assert_eq!(paths(&journal, end, None), (vec!["c".to_string()], false));
+	drop(journal);
+	let journal = Journal::open(&path).unwrap();
+	assert!(journal
+		.changes(Some(after_a), None)
+		.unwrap()
+		.resync_required());
+	assert_eq!(paths(&journal, end, None), (vec!["c".to_string()], false));
+}
+
+/// Set in the child process of [`failed_appends_are_cut_back`], to the journal it should append to.
+#[cfg(target_os = "linux")]
+const FAILING_JOURNAL: &str = "XFP_FAILING_JOURNAL";
+
+#[cfg(target_os = "linux")]
+#[test]
+fn failed_appends_are_cut_back() {
+	use std::{env, process::Command};
+
+	if let Some(path) = env::var_os(FAILING_JOURNAL) {
+		// File size limits apply to the whole process, so they're only set in a child of the test.
+		let journal = Journal::open(&path).unwrap();
+		let len = fs::metadata(&path).unwrap().len();
+		unsafe {
+			// Writing past the limit fails with EFBIG, rather than killing the process.
+			libc::signal(libc::SIGXFSZ, libc::SIG_IGN);
+			let limit = libc::rlimit {
+				rlim_cur: len + 16,
+				rlim_max: libc::RLIM_INFINITY,
+			};
+			assert_eq!(libc::setrlimit(libc::RLIMIT_FSIZE, &limit), 0);
+		}
+		assert!(journal.append(created(&"x".repeat(1024))).is_err());
+		return;
+	}
+
+	let dir = tempfile::tempdir().unwrap();
+	let path = dir.path().join("journal");
+	let journal = Journal::open(&path).unwrap();
+	let start = journal.token();
+	journal.append(created("a")).unwrap();
+	drop(journal);
+	let before = fs::read(&path).unwrap();
+	let status = Command::new(env::current_exe().unwrap())
+		.args(["failed_appends_are_cut_back", "--exact", "--test-threads=1"])
+		.env(FAILING_JOURNAL, &path)
+		.status()
+		.unwrap();
+	assert!(status.success());
+	assert_eq!(fs::read(&path).unwrap(), before);
+	let journal = Journal::open(&path).unwrap();
+	assert_eq!(journal.append(created("b")).unwrap().seq(), 2);
+	assert_eq!(
+		paths(&journal, start, None),
+		(vec!["a".to_string(), "b".to_string()], false)
+	);
+}
+
+#[test]
+fn changes_can_start_anywhere_in_the_journal() {
+	let dir = tempfile::tempdir().unwrap();
+	let path = dir.path().join("journal");
+	let journal = Journal::open(&path).unwrap();
+	let mut tokens = vec![journal.token()];
+	for name in ["a", "b", "c", "d"] {
+		journal.append(created(name)).unwrap();
+		tokens.push(journal.token());
+	}
+	let journal = Journal::open(&path).unwrap();
+	journal.append(created("e")).unwrap();
+	assert_eq!(
+		paths(&journal, tokens[2], Some(2)),
+		(vec!["c".to_string(), "d".to_string()], true)
+	);
+	assert_eq!(
+		paths(&journal, tokens[3], None),
+		(vec!["d".to_string(), "e".to_string()], false)
+	);
+	assert_eq!(
+		paths(&journal, tokens[4], Some(1)),
+		(vec!["e".to_string()], false)
+	);
+}
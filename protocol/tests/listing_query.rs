@@ -0,0 +1,180 @@
+use time::macros::datetime;
+use xfp_protocol::{
+	dir::{
+		DirectoryListEntry, DirectoryListQuery, DirectoryListingPage, InvalidQuery, SortKey,
+		SortOrder,
+	},
+	file::FileType,
+	metadata::Metadata,
+};
+
+fn entry(name: &str) -> DirectoryListEntry {
+	DirectoryListEntry::default().with_name(name)
+}
+
+fn names(entries: &[DirectoryListEntry]) -> Vec<&str> {
+	entries.iter().map(DirectoryListEntry::name).collect()
+}
+
+fn mixed_names() -> Vec<DirectoryListEntry> {
+	["file10", "File2", "file2", "file1", "a", "file02"]
+		.into_iter()
+		.map(entry)
+		.collect()
+}
+
+fn sorted(query: &DirectoryListQuery, entries: Vec<DirectoryListEntry>) -> Vec<String> {
+	names(&query.apply(entries).unwrap())
+		.into_iter()
+		.map(str::to_string)
+		.collect()
+}
+
+#[test]
+fn names_sort_byte_by_byte_by_default() {
+	let query = DirectoryListQuery::default().with_sort(SortKey::Name);
+	assert_eq!(
+		sorted(&query, mixed_names()),
+		["File2", "a", "file02", "file1", "file10", "file2"]
+	);
+}
+
+#[test]
+fn natural_sort_compares_numbers_and_ignores_case() {
+	let query = DirectoryListQuery::default()
+		.with_sort(SortKey::Name)
+		.with_natural(true);
+	// `File2`, `file02` and `file2` are equal naturally, so they fall back to comparing bytes.
+	assert_eq!(
+		sorted(&query, mixed_names()),
+		["a", "file1", "File2", "file02", "file2", "file10"]
+	);
+}
+
+#[test]
+fn natural_sort_handles_huge_numbers() {
+	let query = DirectoryListQuery::default()
+		.with_natural(true)
+		.with_limit(10);
+	let entries = [
+		"x99999999999999999999999",
+		"x100000000000000000000000",
+		"x9",
+	]
+	.into_iter()
+	.map(entry)
+	.collect();
+	assert_eq!(
+		sorted(&query, entries),
+		[
+			"x9",
+			"x99999999999999999999999",
+			"x100000000000000000000000"
+		]
+	);
+}
+
+#[test]
+fn descending_order_reverses_everything() {
+	let query = DirectoryListQuery::default()
+		.with_sort(SortKey::Name)
+		.with_natural(true)
+		.with_order(SortOrder::Desc);
+	assert_eq!(
+		sorted(&query, mixed_names()),
+		["file10", "file2", "file02", "File2", "file1", "a"]
+	);
+}
+
+#[test]
+fn ties_are_broken_by_name() {
+	let entries = vec![
+		entry("b").with_metadata(Metadata::default().with_size(1)),
+		entry("c").with_metadata(Metadata::default().with_size(0)),
+		entry("a").with_metadata(Metadata::default().with_size(1)),
+	];
+	let query = DirectoryListQuery::default().with_sort(SortKey::Size);
+	assert_eq!(sorted(&query, entries), ["c", "a", "b"]);
+}
+
+#[test]
+fn types_sort_directories_first() {
+	let entries = vec![
+		entry("link").with_file_type(FileType::Symlink),
+		entry("file").with_file_type(FileType::File),
+		entry("dir").with_file_type(FileType::Directory),
+	];
+	let query = DirectoryListQuery::default().with_sort(SortKey::Type);
+	assert_eq!(sorted(&query, entries), ["dir", "file", "link"]);
+}
+
+#[test]
+fn entries_without_an_updated_time_come_first() {
+	let entries = vec![
+		entry("new")
+			.with_metadata(Metadata::default().with_updated(datetime!(2022-01-02 0:00 UTC))),
+		entry("old")
+			.with_metadata(Metadata::default().with_updated(datetime!(2022-01-01 0:00 UTC))),
+		entry("unknown"),
+	];
+	let query = DirectoryListQuery::default().with_sort(SortKey::Updated);
+	assert_eq!(sorted(&query, entries), ["unknown", "old", "new"]);
+}
+
+#[test]
+fn unsorted_listings_keep_their_order() {
+	let query = DirectoryListQuery::default();
+	assert_eq!(
+		sorted(&query, mixed_names()),
+		["file10", "File2", "file2", "file1", "a", "file02"]
+	);
+}
+
+#[test]
+fn pagination_follows_natural_order() {
+	let mut query = DirectoryListQuery::default()
+		.with_natural(true)
+		.with_limit(1);
+	let mut seen = Vec::new();
+	loop {
+		let page = DirectoryListingPage::paginate(mixed_names(), &query).unwrap();
+		seen.extend(names(page.entries()).into_iter().map(str::to_string));
+		match page.next_cursor() {
+			Some(cursor) => query = query.with_cursor(cursor.to_string()),
+			None => break,
+		}
+	}
+	assert_eq!(seen, ["a", "file1", "File2", "file02", "file2", "file10"]);
+}
+
+#[test]
+fn filters_by_type_hidden_and_glob() {
+	let entries = || {
+		vec![
+			entry(".hidden.txt"),
+			entry("notes.txt"),
+			entry("image.png"),
+			entry("docs").with_file_type(FileType::Directory),
+		]
+	};
+	let query = DirectoryListQuery::default().with_hidden(false);
+	assert_eq!(
+		sorted(&query, entries()),
+		["notes.txt", "image.png", "docs"]
+	);
+	let query = DirectoryListQuery::default().with_glob("*.txt".to_string());
+	assert_eq!(sorted(&query, entries()), [".hidden.txt", "notes.txt"]);
+	let query = DirectoryListQuery::default().with_glob("[dn]*".to_string());
+	assert_eq!(sorted(&query, entries()), ["notes.txt", "docs"]);
+	let query = DirectoryListQuery::default().with_file_type(FileType::Directory);
+	assert_eq!(sorted(&query, entries()), ["docs"]);
+}
+
+#[test]
+fn invalid_globs_are_rejected() {
+	let query = DirectoryListQuery::default().with_glob("[".to_string());
+	assert!(matches!(
+		query.apply(mixed_names()),
+		Err(InvalidQuery::Glob(_))
+	));
+}
@@ -0,0 +1,96 @@
+#![cfg(unix)]
+
+use std::{
+	fs,
+	os::unix::fs::PermissionsExt,
+	path::Path,
+	time::{Duration, SystemTime},
+};
+use time::macros::datetime;
+use xfp_protocol::metadata::{MetadataPatch, MetadataPatchError, PermissionsPatch};
+
+fn mode(path: &Path) -> u32 {
+	fs::metadata(path).unwrap().permissions().mode() & 0o7777
+}
+
+fn file_with_mode(dir: &tempfile::TempDir, mode: u32) -> std::path::PathBuf {
+	let path = dir.path().join("file");
+	fs::write(&path, "contents").unwrap();
+	fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+	path
+}
+
+#[test]
+fn applies_mode_and_times() {
+	let dir = tempfile::tempdir().unwrap();
+	let path = file_with_mode(&dir, 0o644);
+	let patch = MetadataPatch::default()
+		.with_updated(datetime!(2020-01-02 3:04:05.5 UTC))
+		.with_permissions(PermissionsPatch::default().with_mode(0o4750));
+	patch.apply_to_path(&path).unwrap();
+	let metadata = fs::metadata(&path).unwrap();
+	assert_eq!(metadata.permissions().mode() & 0o7777, 0o4750);
+	assert_eq!(
+		metadata.modified().unwrap(),
+		SystemTime::UNIX_EPOCH + Duration::from_millis(1_577_934_245_500)
+	);
+}
+
+#[test]
+fn keeps_times_that_are_not_patched() {
+	let dir = tempfile::tempdir().unwrap();
+	let path = file_with_mode(&dir, 0o644);
+	let modified = fs::metadata(&path).unwrap().modified().unwrap();
+	let patch = MetadataPatch::default().with_accessed(datetime!(2001-01-01 0:00 UTC));
+	patch.apply_to_path(&path).unwrap();
+	let metadata = fs::metadata(&path).unwrap();
+	assert_eq!(metadata.modified().unwrap(), modified);
+	assert_eq!(
+		metadata.accessed().unwrap(),
+		SystemTime::UNIX_EPOCH + Duration::from_secs(978_307_200)
+	);
+}
+
+#[test]
+fn sets_times_on_files_that_cannot_be_opened() {
+	let dir = tempfile::tempdir().unwrap();
+	for mode in [0o000, 0o200, 0o444] {
+		let path = file_with_mode(&dir, mode);
+		let patch = MetadataPatch::default().with_updated(datetime!(2010-01-01 0:00 UTC));
+		patch.apply_to_path(&path).unwrap();
+		assert_eq!(
+			fs::metadata(&path).unwrap().modified().unwrap(),
+			SystemTime::UNIX_EPOCH + Duration::from_secs(1_262_304_000)
+		);
+	}
+}
+
+#[test]
+fn rejects_invalid_mode_bits() {
+	let dir = tempfile::tempdir().unwrap();
+	let path = file_with_mode(&dir, 0o644);
+	let patch =
+		MetadataPatch::default().with_permissions(PermissionsPatch::default().with_mode(0o100_644));
+	let err = patch.apply_to_path(&path).unwrap_err();
+	assert!(matches!(err, MetadataPatchError::InvalidMode(0o100_644)));
+	assert_eq!(
+		err.to_string(),
+		"invalid mode 100644: only the bits in 7777 can be set, found 100000"
+	);
+	assert_eq!(mode(&path), 0o644);
+}
+
+#[test]
+fn rolls_back_the_mode_when_a_later_change_fails() {
+	let dir = tempfile::tempdir().unwrap();
+	let path = file_with_mode(&dir, 0o644);
+	// Too far in the future for a `timespec`, so setting the times fails after the mode was changed.
+	let patch = MetadataPatch::default()
+		.with_updated(datetime!(9999-01-01 0:00 UTC))
+		.with_permissions(PermissionsPatch::default().with_mode(0o600));
+	assert!(matches!(
+		patch.apply_to_path(&path),
+		Err(MetadataPatchError::Io(_))
+	));
+	assert_eq!(mode(&path), 0o644);
+}
@@ -0,0 +1,72 @@
+use std::fs;
+use xfp_protocol::metadata::{sniff, MimeDetector};
+
+#[test]
+fn extensions_are_mapped_to_media_types() {
+	let detector = MimeDetector::new();
+	assert_eq!(detector.detect("page.html").as_deref(), Some("text/html"));
+	assert_eq!(
+		detector.detect("dir/photo.PNG").as_deref(),
+		Some("image/png")
+	);
+	assert_eq!(detector.detect("unknown.xfpunknown"), None);
+	assert_eq!(detector.detect("no-extension"), None);
+}
+
+#[test]
+fn overrides_take_precedence_regardless_of_case() {
+	let detector = MimeDetector::new()
+		.with_override("HTML", "text/plain")
+		.with_override("Custom", "application/x-custom");
+	assert_eq!(detector.detect("page.html").as_deref(), Some("text/plain"));
+	assert_eq!(detector.detect("page.HtMl").as_deref(), Some("text/plain"));
+	assert_eq!(
+		detector.detect("data.CUSTOM").as_deref(),
+		Some("application/x-custom")
+	);
+}
+
+#[test]
+fn override_extensions_may_start_with_dots() {
+	let detector = MimeDetector::new()
+		.with_override(".log", "text/plain")
+		.with_override("..weird", "application/x-weird");
+	assert_eq!(detector.detect("server.log").as_deref(), Some("text/plain"));
+	assert_eq!(
+		detector.detect("file.weird").as_deref(),
+		Some("application/x-weird")
+	);
+}
+
+#[test]
+fn headers_are_sniffed() {
+	assert_eq!(sniff(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), Some("image/png"));
+	assert_eq!(sniff(b"%PDF-1.7\n"), Some("application/pdf"));
+	assert_eq!(sniff(b"GIF89a"), Some("image/gif"));
+	assert_eq!(sniff(b"\x7fELF\x02\x01\x01"), Some("application/x-elf"));
+	assert_eq!(sniff(b"plain text"), None);
+	assert_eq!(sniff(b""), None);
+}
+
+#[test]
+fn riff_is_only_webp_with_the_webp_form_type() {
+	assert_eq!(sniff(b"RIFF\x24\0\0\0WEBPVP8 "), Some("image/webp"));
+	assert_eq!(sniff(b"RIFF\x24\0\0\0WAVEfmt "), None);
+	// Too short to hold the form type.
+	assert_eq!(sniff(b"RIFF\x24\0\0\0WEB"), None);
+}
+
+#[test]
+fn contents_are_only_sniffed_when_enabled() {
+	let dir = tempfile::tempdir().unwrap();
+	let image = dir.path().join("image");
+	fs::write(&image, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+	let text = dir.path().join("notes.txt");
+	fs::write(&text, b"\x89PNG\r\n\x1a\n").unwrap();
+	assert_eq!(MimeDetector::new().detect(&image), None);
+	let detector = MimeDetector::new().with_sniffing(true);
+	assert_eq!(detector.detect(&image).as_deref(), Some("image/png"));
+	// Extensions still win over contents.
+	assert_eq!(detector.detect(&text).as_deref(), Some("text/plain"));
+	assert_eq!(detector.detect(dir.path().join("missing")), None);
+}
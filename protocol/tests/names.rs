@@ -0,0 +1,113 @@
+#![cfg(unix)]
+
+use std::{
+	fs,
+	os::unix::fs::MetadataExt,
+	sync::atomic::{AtomicUsize, Ordering},
+	thread,
+	time::Duration,
+};
+use xfp_protocol::{
+	dir::{DirectoryEntryReader, TreeQuery},
+	metadata::{FileGroup, FileOwner, Metadata, NameCache},
+};
+
+// Every test has its own lookups, so they can count their calls while tests run in parallel.
+
+static HITS: AtomicUsize = AtomicUsize::new(0);
+
+fn hits_lookup(uid: u32) -> Option<String> {
+	let calls = HITS.fetch_add(1, Ordering::SeqCst) + 1;
+	(uid != 404).then(|| format!("user{}-{}", uid, calls))
+}
+
+#[test]
+fn names_are_cached_until_they_expire() {
+	let cache = NameCache::new(Duration::from_secs(60)).with_lookups(hits_lookup, hits_lookup);
+	assert_eq!(cache.user_name(1000).as_deref(), Some("user1000-1"));
+	assert_eq!(cache.user_name(1000).as_deref(), Some("user1000-1"));
+	assert_eq!(cache.user_name(1001).as_deref(), Some("user1001-2"));
+	// Users and groups are cached separately.
+	assert_eq!(cache.group_name(1000).as_deref(), Some("user1000-3"));
+	// Failed lookups are cached too.
+	assert_eq!(cache.user_name(404), None);
+	assert_eq!(cache.user_name(404), None);
+	assert_eq!(HITS.load(Ordering::SeqCst), 4);
+}
+
+static EXPIRY: AtomicUsize = AtomicUsize::new(0);
+
+fn expiry_lookup(uid: u32) -> Option<String> {
+	let calls = EXPIRY.fetch_add(1, Ordering::SeqCst) + 1;
+	Some(format!("user{}-{}", uid, calls))
+}
+
+#[test]
+fn expired_names_are_looked_up_again() {
+	let cache =
+		NameCache::new(Duration::from_millis(50)).with_lookups(expiry_lookup, expiry_lookup);
+	assert_eq!(cache.user_name(1000).as_deref(), Some("user1000-1"));
+	thread::sleep(Duration::from_millis(100));
+	assert_eq!(cache.user_name(1000).as_deref(), Some("user1000-2"));
+	assert_eq!(cache.user_name(1000).as_deref(), Some("user1000-2"));
+	assert_eq!(EXPIRY.load(Ordering::SeqCst), 2);
+}
+
+static CLEAR: AtomicUsize = AtomicUsize::new(0);
+
+fn clear_lookup(uid: u32) -> Option<String> {
+	let calls = CLEAR.fetch_add(1, Ordering::SeqCst) + 1;
+	Some(format!("user{}-{}", uid, calls))
+}
+
+#[test]
+fn cleared_names_are_looked_up_again() {
+	let cache = NameCache::new(Duration::from_secs(60)).with_lookups(clear_lookup, clear_lookup);
+	assert_eq!(cache.user_name(1000).as_deref(), Some("user1000-1"));
+	cache.clear();
+	assert_eq!(cache.user_name(1000).as_deref(), Some("user1000-2"));
+}
+
+static LISTING: AtomicUsize = AtomicUsize::new(0);
+
+fn listing_lookup(id: u32) -> Option<String> {
+	LISTING.fetch_add(1, Ordering::SeqCst);
+	Some(format!("principal{}", id))
+}
+
+#[test]
+fn listings_look_names_up_through_the_cache() {
+	let dir = tempfile::tempdir().unwrap();
+	for name in ["a", "b", "c", "d"] {
+		fs::write(dir.path().join(name), name).unwrap();
+	}
+	let stat = fs::metadata(dir.path().join("a")).unwrap();
+	let cache =
+		NameCache::new(Duration::from_secs(60)).with_lookups(listing_lookup, listing_lookup);
+
+	let metadata = Metadata::from_path_cached(dir.path().join("a"), &cache).unwrap();
+	assert_eq!(
+		metadata.permissions().owner(),
+		Some(&FileOwner::Unix {
+			uid: stat.uid(),
+			name: format!("principal{}", stat.uid())
+		})
+	);
+
+	let mut body = Vec::new();
+	TreeQuery::default()
+		.write_tree(dir.path(), &cache, &mut body)
+		.unwrap();
+	for entry in DirectoryEntryReader::new(body.as_slice()) {
+		let permissions = entry.unwrap().metadata().permissions().clone();
+		assert_eq!(
+			permissions.group(),
+			Some(&FileGroup::Unix {
+				gid: stat.gid(),
+				name: format!("principal{}", stat.gid())
+			})
+		);
+	}
+	// One lookup each for the owner and the group, however many files there are.
+	assert_eq!(LISTING.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,70 @@
+use xfp_protocol::dir::{write_entry, write_truncated, DirectoryEntryReader, DirectoryListEntry};
+
+fn entries() -> Vec<DirectoryListEntry> {
+	["a", "a/b", "c"]
+		.into_iter()
+		.map(|path| {
+			DirectoryListEntry::default()
+				.with_name(path.rsplit('/').next().unwrap())
+				.with_path(path.to_string())
+		})
+		.collect()
+}
+
+#[test]
+fn complete_listings_are_not_truncated() {
+	let mut body = Vec::new();
+	for entry in entries() {
+		write_entry(&mut body, &entry).unwrap();
+	}
+	let mut reader = DirectoryEntryReader::new(body.as_slice());
+	let read = reader.by_ref().collect::<Result<Vec<_>, _>>().unwrap();
+	assert_eq!(read, entries());
+	assert!(!reader.is_truncated());
+}
+
+#[test]
+fn truncated_listings_end_with_a_marker() {
+	let mut body = Vec::new();
+	for entry in &entries()[..2] {
+		write_entry(&mut body, entry).unwrap();
+	}
+	write_truncated(&mut body).unwrap();
+	assert!(body.ends_with(b"{\"truncated\":true}\n"));
+	let mut reader = DirectoryEntryReader::new(body.as_slice());
+	let read = reader.by_ref().collect::<Result<Vec<_>, _>>().unwrap();
+	assert_eq!(read, entries()[..2]);
+	assert!(reader.is_truncated());
+}
+
+#[test]
+fn markers_with_extra_fields_are_rejected() {
+	let body = b"{\"truncated\":true,\"name\":\"x\"}\n";
+	let mut reader = DirectoryEntryReader::new(&body[..]);
+	assert!(reader.next().unwrap().is_err());
+	assert!(!reader.is_truncated());
+}
+
+#[test]
+fn markers_which_are_not_true_are_rejected() {
+	let mut body = Vec::new();
+	write_entry(&mut body, &entries()[0]).unwrap();
+	body.extend_from_slice(b"{\"truncated\":false}\n");
+	write_entry(&mut body, &entries()[1]).unwrap();
+	let mut reader = DirectoryEntryReader::new(body.as_slice());
+	assert_eq!(reader.next().unwrap().unwrap(), entries()[0]);
+	let err = reader.next().unwrap().unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+	assert!(!reader.is_truncated());
+}
+
+#[test]
+fn blank_lines_are_skipped() {
+	let mut body = b"\n  \n".to_vec();
+	write_entry(&mut body, &entries()[0]).unwrap();
+	body.extend_from_slice(b"\n");
+	let read = DirectoryEntryReader::new(body.as_slice())
+		.collect::<Result<Vec<_>, _>>()
+		.unwrap();
+	assert_eq!(read, entries()[..1]);
+}
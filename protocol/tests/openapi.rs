@@ -0,0 +1,112 @@
+//! Checks that the checked-in `openapi.json` matches the one generated from the protocol types.
+//!
+//! Run with `XFP_BLESS=1 cargo test --features openapi --test openapi` to regenerate it after changing the types.
+
+use std::{env, fs, path::Path};
+
+#[test]
+fn openapi_document_is_up_to_date() {
+	let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("openapi.json");
+	let generated =
+		serde_json::to_string_pretty(&xfp_protocol::openapi::document()).unwrap() + "\n";
+	if env::var_os("XFP_BLESS").is_some() {
+		fs::write(&path, generated).unwrap();
+		return;
+	}
+	let checked_in = fs::read_to_string(&path).unwrap_or_default();
+	assert!(
+		checked_in == generated,
+		"openapi.json is out of date, regenerate it with `XFP_BLESS=1 cargo test --features openapi --test openapi`"
+	);
+}
+
+#[test]
+fn openapi_document_references_resolve() {
+	let document = xfp_protocol::openapi::document();
+	let mut refs = Vec::new();
+	collect_refs(&document, &mut refs);
+	assert!(!refs.is_empty());
+	for reference in refs {
+		let pointer = reference.trim_start_matches('#');
+		assert!(
+			document.pointer(pointer).is_some(),
+			"unresolved reference {}",
+			reference
+		);
+	}
+}
+
+/// Every endpoint summarized in `src/api.rs`, such as ``/// GET, PUT and DELETE `/:mount/xattr/:path` ``,
+/// has to be described by the document, so an endpoint can't be added to the protocol without it.
+#[test]
+fn openapi_document_describes_every_endpoint() {
+	const METHODS: &[&str] = &["GET", "HEAD", "POST", "PUT", "PATCH", "DELETE"];
+	let document = xfp_protocol::openapi::document();
+	let api = fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("src/api.rs")).unwrap();
+	let mut summary = None;
+	let mut checked = 0;
+	for line in api.lines() {
+		if let Some(line) = line.strip_prefix("/// ") {
+			summary = Some(line);
+			continue;
+		}
+		let Some(module) = line
+			.strip_prefix("pub mod ")
+			.and_then(|module| module.strip_suffix(';'))
+		else {
+			continue;
+		};
+		let summary = summary
+			.take()
+			.unwrap_or_else(|| panic!("api::{} has no summary", module));
+		let methods = summary
+			.split('`')
+			.next()
+			.unwrap()
+			.split(|c: char| !c.is_ascii_alphabetic())
+			.filter(|word| METHODS.contains(word))
+			.map(str::to_lowercase)
+			.collect::<Vec<_>>();
+		// Code spans are every other part, and each is a path.
+		for path in summary.split('`').skip(1).step_by(2) {
+			let path = path
+				.split('/')
+				.map(|segment| match segment.strip_prefix(':') {
+					Some(name) => format!("{{{}}}", name),
+					None => segment.to_string(),
+				})
+				.collect::<Vec<_>>()
+				.join("/");
+			let operations = document["paths"].get(&path).unwrap_or_else(|| {
+				panic!(
+					"api::{} documents {}, which isn't in the OpenAPI document",
+					module, path
+				)
+			});
+			for method in &methods {
+				assert!(
+					operations.get(method).is_some(),
+					"api::{} documents {} {}, which isn't in the OpenAPI document",
+					module,
+					method.to_uppercase(),
+					path
+				);
+			}
+			checked += 1;
+		}
+	}
+	assert!(checked > 0);
+}
+
+fn collect_refs(value: &serde_json::Value, refs: &mut Vec<String>) {
+	match value {
+		serde_json::Value::Object(object) => {
+			if let Some(serde_json::Value::String(reference)) = object.get("$ref") {
+				refs.push(reference.clone());
+			}
+			object.values().for_each(|value| collect_refs(value, refs));
+		}
+		serde_json::Value::Array(array) => array.iter().for_each(|value| collect_refs(value, refs)),
+		_ => {}
+	}
+}
@@ -0,0 +1,108 @@
+use xfp_protocol::{
+	dir::{DirectoryListEntry, DirectoryListQuery, DirectoryListingPage, InvalidQuery, SortKey},
+	metadata::Metadata,
+};
+
+fn entry(name: &str, size: u64) -> DirectoryListEntry {
+	DirectoryListEntry::default()
+		.with_name(name)
+		.with_metadata(Metadata::default().with_size(size))
+}
+
+fn names(entries: &[DirectoryListEntry]) -> Vec<&str> {
+	entries.iter().map(DirectoryListEntry::name).collect()
+}
+
+fn listing() -> Vec<DirectoryListEntry> {
+	["e", "b", "d", "a", "c"]
+		.iter()
+		.enumerate()
+		.map(|(idx, name)| entry(name, idx as u64))
+		.collect()
+}
+
+#[test]
+fn pages_cover_the_whole_listing_in_order() {
+	let mut query = DirectoryListQuery::default().with_limit(2);
+	let mut seen = Vec::new();
+	loop {
+		let page = DirectoryListingPage::paginate(listing(), &query).unwrap();
+		assert!(page.entries().len() <= 2);
+		seen.extend(names(page.entries()).into_iter().map(str::to_string));
+		match page.next_cursor() {
+			Some(cursor) => query = query.with_cursor(cursor.to_string()),
+			None => break,
+		}
+	}
+	assert_eq!(seen, ["a", "b", "c", "d", "e"]);
+}
+
+#[test]
+fn cursors_survive_changes_between_requests() {
+	let query = DirectoryListQuery::default().with_limit(2);
+	let first = DirectoryListingPage::paginate(listing(), &query).unwrap();
+	assert_eq!(names(first.entries()), ["a", "b"]);
+
+	// "b" is deleted and "aa" is added before the next request.
+	let mut changed = listing();
+	changed.retain(|entry| entry.name() != "b");
+	changed.push(entry("aa", 10));
+	let query = query.with_cursor(first.next_cursor().unwrap().to_string());
+	let second = DirectoryListingPage::paginate(changed, &query).unwrap();
+	assert_eq!(names(second.entries()), ["c", "d"]);
+}
+
+#[test]
+fn cursors_follow_the_sort_key() {
+	let query = DirectoryListQuery::default()
+		.with_sort(SortKey::Size)
+		.with_limit(3);
+	let first = DirectoryListingPage::paginate(listing(), &query).unwrap();
+	assert_eq!(names(first.entries()), ["e", "b", "d"]);
+	let query = query.with_cursor(first.next_cursor().unwrap().to_string());
+	let second = DirectoryListingPage::paginate(listing(), &query).unwrap();
+	assert_eq!(names(second.entries()), ["a", "c"]);
+	assert_eq!(second.next_cursor(), None);
+}
+
+#[test]
+fn cursors_from_a_different_ordering_are_rejected() {
+	let query = DirectoryListQuery::default().with_limit(1);
+	let page = DirectoryListingPage::paginate(listing(), &query).unwrap();
+	let cursor = page.next_cursor().unwrap().to_string();
+	let resorted = query.with_sort(SortKey::Size).with_cursor(cursor);
+	assert_eq!(
+		DirectoryListingPage::paginate(listing(), &resorted),
+		Err(InvalidQuery::Cursor)
+	);
+}
+
+#[test]
+fn malformed_cursors_are_rejected() {
+	for cursor in ["zz", "abc", "", "00"] {
+		let query = DirectoryListQuery::default().with_cursor(cursor.to_string());
+		assert_eq!(
+			DirectoryListingPage::paginate(listing(), &query),
+			Err(InvalidQuery::Cursor),
+			"{:?}",
+			cursor
+		);
+	}
+}
+
+#[test]
+fn a_limit_of_zero_is_rejected() {
+	let query = DirectoryListQuery::default().with_limit(0);
+	assert_eq!(
+		DirectoryListingPage::paginate(listing(), &query),
+		Err(InvalidQuery::Limit)
+	);
+}
+
+#[test]
+fn the_last_page_has_no_cursor() {
+	let query = DirectoryListQuery::default().with_limit(5);
+	let page = DirectoryListingPage::paginate(listing(), &query).unwrap();
+	assert_eq!(page.entries().len(), 5);
+	assert_eq!(page.next_cursor(), None);
+}
@@ -0,0 +1,166 @@
+use xfp_protocol::metadata::{Access, AclEntry, AclTag, FileGroup, FileOwner, FilePermissions};
+
+const OWNER: u32 = 1000;
+const GROUP: u32 = 100;
+
+fn permissions(mode: u32) -> FilePermissions {
+	FilePermissions::default()
+		.with_mode(mode)
+		.with_owner(FileOwner::Unix {
+			uid: OWNER,
+			name: String::new(),
+		})
+		.with_group(FileGroup::Unix {
+			gid: GROUP,
+			name: String::new(),
+		})
+}
+
+fn entry(tag: AclTag, id: impl Into<Option<u32>>, perm: &str) -> AclEntry {
+	AclEntry::new(
+		tag,
+		id,
+		perm.contains('r'),
+		perm.contains('w'),
+		perm.contains('x'),
+	)
+}
+
+#[test]
+fn mode_bits_pick_the_most_specific_class() {
+	let file = permissions(0o640);
+	assert_eq!(file.allows(OWNER, &[], Access::Write), Some(true));
+	assert_eq!(file.allows(OWNER, &[], Access::Execute), Some(false));
+	assert_eq!(file.allows(1, &[GROUP], Access::Read), Some(true));
+	assert_eq!(file.allows(1, &[GROUP], Access::Write), Some(false));
+	assert_eq!(file.allows(1, &[1], Access::Read), Some(false));
+	// The owner class applies to the owner, even if the group class would grant more.
+	assert_eq!(
+		permissions(0o070).allows(OWNER, &[GROUP], Access::Read),
+		Some(false)
+	);
+	assert_eq!(
+		permissions(0o007).allows(1, &[GROUP], Access::Read),
+		Some(false)
+	);
+	assert_eq!(
+		FilePermissions::default().allows(OWNER, &[], Access::Read),
+		None
+	);
+}
+
+#[test]
+fn acl_owner_entries_ignore_the_mask() {
+	let file = permissions(0o700).with_acl(vec![
+		entry(AclTag::UserObj, None, "rw"),
+		entry(AclTag::Mask, None, ""),
+		entry(AclTag::Other, None, "r"),
+	]);
+	assert_eq!(file.allows(OWNER, &[], Access::Write), Some(true));
+	assert_eq!(file.allows(OWNER, &[], Access::Execute), Some(false));
+}
+
+#[test]
+fn acl_named_users_are_limited_by_the_mask() {
+	let acl = vec![
+		entry(AclTag::UserObj, None, "rwx"),
+		entry(AclTag::User, 2, "rw"),
+		entry(AclTag::GroupObj, None, "rwx"),
+		entry(AclTag::Mask, None, "r"),
+		entry(AclTag::Other, None, "rwx"),
+	];
+	let file = permissions(0o777).with_acl(acl);
+	assert_eq!(file.allows(2, &[], Access::Read), Some(true));
+	assert_eq!(file.allows(2, &[], Access::Write), Some(false));
+	// A named user entry applies even when the user is in the file's group, or other grants more.
+	assert_eq!(file.allows(2, &[GROUP], Access::Execute), Some(false));
+	assert_eq!(file.allows(1, &[GROUP], Access::Write), Some(false));
+	assert_eq!(file.allows(1, &[], Access::Write), Some(true));
+}
+
+#[test]
+fn acl_groups_grant_if_any_matching_entry_does() {
+	let file = permissions(0o770).with_acl(vec![
+		entry(AclTag::UserObj, None, "rwx"),
+		entry(AclTag::GroupObj, None, "r"),
+		entry(AclTag::Group, 5, "w"),
+		entry(AclTag::Other, None, "x"),
+	]);
+	assert_eq!(file.allows(1, &[GROUP, 5], Access::Read), Some(true));
+	assert_eq!(file.allows(1, &[GROUP, 5], Access::Write), Some(true));
+	// Matching a group entry stops other from applying.
+	assert_eq!(file.allows(1, &[5], Access::Execute), Some(false));
+	assert_eq!(file.allows(1, &[6], Access::Execute), Some(true));
+	assert_eq!(file.allows(1, &[6], Access::Read), Some(false));
+}
+
+fn xattr(entries: &[(u16, u16, u32)]) -> Vec<u8> {
+	let mut value = 2u32.to_le_bytes().to_vec();
+	for (tag, perm, id) in entries {
+		value.extend_from_slice(&tag.to_le_bytes());
+		value.extend_from_slice(&perm.to_le_bytes());
+		value.extend_from_slice(&id.to_le_bytes());
+	}
+	value
+}
+
+#[test]
+fn acl_xattrs_are_parsed() {
+	let value = xattr(&[
+		(0x01, 6, u32::MAX),
+		(0x02, 5, 1001),
+		(0x04, 4, u32::MAX),
+		(0x08, 2, 50),
+		(0x10, 7, u32::MAX),
+		(0x20, 0, u32::MAX),
+	]);
+	assert_eq!(
+		AclEntry::parse_xattr(&value).unwrap(),
+		[
+			entry(AclTag::UserObj, None, "rw"),
+			entry(AclTag::User, 1001, "rx"),
+			entry(AclTag::GroupObj, None, "r"),
+			entry(AclTag::Group, 50, "w"),
+			entry(AclTag::Mask, None, "rwx"),
+			entry(AclTag::Other, None, ""),
+		]
+	);
+	assert_eq!(AclEntry::parse_xattr(&xattr(&[])).unwrap(), []);
+}
+
+#[test]
+fn invalid_acl_xattrs_are_rejected() {
+	let valid = xattr(&[(0x01, 6, u32::MAX), (0x20, 4, u32::MAX)]);
+	// Cutting the value between two entries leaves a valid, shorter list.
+	for len in 0..valid.len() {
+		if len < 4 || (len - 4) % 8 != 0 {
+			assert_eq!(
+				AclEntry::parse_xattr(&valid[..len]),
+				None,
+				"truncated to {len}"
+			);
+		}
+	}
+	let mut version = valid.clone();
+	version[0] = 1;
+	assert_eq!(AclEntry::parse_xattr(&version), None);
+	assert_eq!(AclEntry::parse_xattr(&xattr(&[(0x40, 7, 0)])), None);
+	assert_eq!(AclEntry::parse_xattr(b"not an acl at all"), None);
+}
+
+#[cfg(unix)]
+#[test]
+fn owners_without_names_keep_their_ids() {
+	use std::os::unix::fs::MetadataExt;
+	let dir = tempfile::tempdir().unwrap();
+	let metadata = dir.path().metadata().unwrap();
+	let permissions = FilePermissions::from_path(dir.path()).unwrap();
+	match permissions.owner() {
+		Some(FileOwner::Unix { uid, .. }) => assert_eq!(*uid, metadata.uid()),
+		owner => panic!("unexpected owner {:?}", owner),
+	}
+	match permissions.group() {
+		Some(FileGroup::Unix { gid, .. }) => assert_eq!(*gid, metadata.gid()),
+		group => panic!("unexpected group {:?}", group),
+	}
+}
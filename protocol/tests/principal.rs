@@ -0,0 +1,125 @@
+use serde_json::json;
+use xfp_protocol::metadata::{FileGroup, FileOwner, PrincipalKind};
+
+fn owner(value: serde_json::Value) -> Result<FileOwner, String> {
+	serde_json::from_value(value).map_err(|err| err.to_string())
+}
+
+fn group(value: serde_json::Value) -> Result<FileGroup, String> {
+	serde_json::from_value(value).map_err(|err| err.to_string())
+}
+
+#[test]
+fn tagged_principals_round_trip() {
+	let unix = FileOwner::Unix {
+		uid: 1000,
+		name: "alice".to_string(),
+	};
+	let json = serde_json::to_value(&unix).unwrap();
+	assert_eq!(json, json!({"kind": "unix", "uid": 1000, "name": "alice"}));
+	assert_eq!(owner(json).unwrap(), unix);
+	let windows = FileGroup::Windows {
+		sid: "S-1-5-32-544".to_string(),
+		domain: "BUILTIN".to_string(),
+		name: "Administrators".to_string(),
+	};
+	let json = serde_json::to_value(&windows).unwrap();
+	assert_eq!(
+		json,
+		json!({"kind": "windows", "sid": "S-1-5-32-544", "domain": "BUILTIN", "name": "Administrators"})
+	);
+	assert_eq!(group(json).unwrap(), windows);
+}
+
+#[test]
+fn legacy_principals_infer_their_kind() {
+	assert_eq!(
+		owner(json!({"uid": 1000, "name": "alice"})).unwrap(),
+		FileOwner::Unix {
+			uid: 1000,
+			name: "alice".to_string()
+		}
+	);
+	assert_eq!(
+		owner(json!({"sid": "S-1-5-18"})).unwrap(),
+		FileOwner::Windows {
+			sid: "S-1-5-18".to_string(),
+			domain: String::new(),
+			name: String::new()
+		}
+	);
+	let group = group(json!({"gid": 100})).unwrap();
+	assert_eq!(group.kind(), PrincipalKind::Unix);
+	assert_eq!(group.display_name(), "100");
+}
+
+#[test]
+fn ambiguous_principals_are_rejected() {
+	let err = owner(json!({"uid": 1000, "sid": "S-1-5-18"})).unwrap_err();
+	assert!(err.contains("both `uid` and `sid` are present"), "{err}");
+	let err = group(json!({"gid": 100, "sid": "S-1-5-18"})).unwrap_err();
+	assert!(err.contains("both `gid` and `sid` are present"), "{err}");
+	let err = owner(json!({"name": "alice"})).unwrap_err();
+	assert!(err.contains("missing field `uid` or `sid`"), "{err}");
+	// A kind settles which fields are used, but the others still aren't allowed.
+	let err = owner(json!({"kind": "unix", "uid": 1000, "sid": "S-1-5-18"})).unwrap_err();
+	assert!(
+		err.contains("field `sid` is not valid for kind `unix`"),
+		"{err}"
+	);
+}
+
+#[test]
+fn kinds_conflicting_with_the_fields_are_rejected() {
+	let err = owner(json!({"kind": "unix", "sid": "S-1-5-18"})).unwrap_err();
+	assert!(err.contains("missing field `uid` for kind `unix`"), "{err}");
+	let err = owner(json!({"kind": "windows", "uid": 1000})).unwrap_err();
+	assert!(
+		err.contains("missing field `sid` for kind `windows`"),
+		"{err}"
+	);
+	let err = group(json!({"kind": "windows", "sid": "S-1-5-18", "gid": 100})).unwrap_err();
+	assert!(
+		err.contains("field `gid` is not valid for kind `windows`"),
+		"{err}"
+	);
+	let err = group(json!({"kind": "unix", "gid": 100, "domain": "BUILTIN"})).unwrap_err();
+	assert!(
+		err.contains("field `domain` is not valid for kind `unix`"),
+		"{err}"
+	);
+	assert!(owner(json!({"kind": "mac", "uid": 1000})).is_err());
+}
+
+#[test]
+fn unknown_fields_are_ignored() {
+	assert_eq!(
+		owner(json!({"uid": 1000, "name": "alice", "home": "/home/alice"})).unwrap(),
+		FileOwner::Unix {
+			uid: 1000,
+			name: "alice".to_string()
+		}
+	);
+	assert_eq!(
+		group(json!({"kind": "windows", "sid": "S-1-5-32-544", "rid": 544})).unwrap(),
+		FileGroup::Windows {
+			sid: "S-1-5-32-544".to_string(),
+			domain: String::new(),
+			name: String::new()
+		}
+	);
+}
+
+#[test]
+fn ids_of_the_other_principal_are_rejected() {
+	let err = owner(json!({"kind": "unix", "gid": 100})).unwrap_err();
+	assert!(
+		err.contains("unknown field `gid`, owners have a `uid`"),
+		"{err}"
+	);
+	let err = group(json!({"uid": 1000})).unwrap_err();
+	assert!(
+		err.contains("unknown field `uid`, groups have a `gid`"),
+		"{err}"
+	);
+}
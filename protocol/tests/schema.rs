@@ -0,0 +1,85 @@
+use serde_json::{json, Value};
+use xfp_protocol::schema::{schema, SCHEMA_NAMES};
+
+fn schema_json(name: &str) -> Value {
+	schema(name).unwrap().to_value()
+}
+
+#[test]
+fn every_published_schema_exists() {
+	for name in SCHEMA_NAMES {
+		let schema = schema_json(name);
+		assert_eq!(
+			schema["$schema"], "https://json-schema.org/draft/2020-12/schema",
+			"{name}"
+		);
+		assert_eq!(schema["title"], *name);
+	}
+	assert!(schema("Nonexistent").is_none());
+	assert!(schema("metadata").is_none());
+}
+
+#[test]
+fn sizes_accept_strings_of_digits() {
+	let metadata = schema_json("Metadata");
+	let any_of = metadata["properties"]["size"]["anyOf"].as_array().unwrap();
+	assert!(any_of.contains(&json!({
+		"type": "integer",
+		"format": "uint64",
+		"minimum": 0
+	})));
+	assert!(any_of.contains(&json!({
+		"type": "string",
+		"pattern": "^[0-9]+$"
+	})));
+}
+
+#[test]
+fn timestamps_are_date_times() {
+	let metadata = schema_json("Metadata");
+	for field in ["created", "updated", "accessed", "changed"] {
+		let property = &metadata["properties"][field];
+		assert_eq!(property["format"], "date-time", "{field}");
+		assert_eq!(property["type"], json!(["string", "null"]), "{field}");
+	}
+}
+
+#[test]
+fn principals_may_omit_their_kind() {
+	for (name, id) in [("FileOwner", "uid"), ("FileGroup", "gid")] {
+		let principal = schema_json(name);
+		let variants = principal["oneOf"].as_array().unwrap();
+		assert_eq!(variants[0]["required"], json!([id]), "{name}");
+		assert_eq!(variants[1]["required"], json!(["sid"]), "{name}");
+		assert_eq!(variants[0]["properties"]["kind"]["const"], "unix");
+		// Unknown fields are ignored, but not the fields of the other kind of principal.
+		assert!(variants[0].get("additionalProperties").is_none(), "{name}");
+		assert!(variants[0]["not"]["anyOf"]
+			.as_array()
+			.unwrap()
+			.contains(&json!({"required": ["sid"]})));
+		assert!(variants[1]["not"]["anyOf"]
+			.as_array()
+			.unwrap()
+			.contains(&json!({"required": [id]})));
+		assert!(principal["description"]
+			.as_str()
+			.unwrap()
+			.contains("Objects without a `kind` field are also accepted"));
+	}
+}
+
+#[test]
+fn nested_schemas_are_defined() {
+	let entry = schema_json("DirectoryListEntry");
+	assert_eq!(entry["properties"]["metadata"]["$ref"], "#/$defs/Metadata");
+	for definition in [
+		"Metadata",
+		"FilePermissions",
+		"FileOwner",
+		"FileGroup",
+		"FileType",
+	] {
+		assert!(entry["$defs"][definition].is_object(), "{definition}");
+	}
+}
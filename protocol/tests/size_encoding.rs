@@ -0,0 +1,34 @@
+use xfp_protocol::metadata::{FilePermissions, Metadata};
+
+#[test]
+fn size_serializes_as_number() {
+	let metadata = Metadata::default().with_size(1024);
+	let json = serde_json::to_value(&metadata).unwrap();
+	assert_eq!(json["size"], serde_json::json!(1024));
+}
+
+#[test]
+fn size_deserializes_from_number_or_string() {
+	let permissions = r#"{"read":true,"write":false,"execute":false}"#;
+	for size in ["1024", r#""1024""#] {
+		let json = format!(r#"{{"permissions":{},"size":{}}}"#, permissions, size);
+		let metadata: Metadata = serde_json::from_str(&json).unwrap();
+		assert_eq!(metadata.size(), 1024);
+	}
+}
+
+#[test]
+fn size_rejects_garbage() {
+	let json = r#"{"permissions":{"read":true,"write":false,"execute":false},"size":"big"}"#;
+	assert!(serde_json::from_str::<Metadata>(json).is_err());
+}
+
+#[test]
+fn metadata_round_trips() {
+	let metadata = Metadata::default()
+		.with_size(u64::MAX)
+		.with_updated(time::macros::datetime!(2022-07-01 12:34:56.123456789 UTC))
+		.with_permissions(FilePermissions::default().with_read(true).with_mode(0o644));
+	let json = serde_json::to_string(&metadata).unwrap();
+	assert_eq!(serde_json::from_str::<Metadata>(&json).unwrap(), metadata);
+}
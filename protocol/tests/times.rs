@@ -0,0 +1,53 @@
+use std::fs;
+use time::macros::datetime;
+use xfp_protocol::metadata::Metadata;
+
+#[cfg(unix)]
+#[test]
+fn changed_times_are_read_on_unix() {
+	use std::os::unix::fs::MetadataExt;
+
+	let dir = tempfile::tempdir().unwrap();
+	let path = dir.path().join("file");
+	fs::write(&path, b"data").unwrap();
+	let changed = Metadata::from_path(&path).unwrap().changed().unwrap();
+	let stat = fs::metadata(&path).unwrap();
+	assert_eq!(changed.unix_timestamp(), stat.ctime());
+	assert_eq!(i64::from(changed.nanosecond()), stat.ctime_nsec());
+}
+
+#[test]
+fn created_times_match_what_the_filesystem_reports() {
+	let dir = tempfile::tempdir().unwrap();
+	let path = dir.path().join("file");
+	fs::write(&path, b"data").unwrap();
+	let created = Metadata::from_path(&path).unwrap().created();
+	assert_eq!(
+		created.is_some(),
+		fs::metadata(&path).unwrap().created().is_ok()
+	);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn missing_birth_times_are_left_out() {
+	// procfs doesn't record when its files were created.
+	let metadata = Metadata::from_path("/proc/self/status").unwrap();
+	assert_eq!(metadata.created(), None);
+	let json = serde_json::to_value(&metadata).unwrap();
+	assert!(json.get("created").is_none());
+	let metadata: Metadata = serde_json::from_value(json).unwrap();
+	assert_eq!(metadata.created(), None);
+}
+
+#[test]
+fn nanoseconds_survive_json() {
+	let metadata = Metadata::default()
+		.with_created(datetime!(2021-06-01 12:00:00.000000001 UTC))
+		.with_updated(datetime!(2022-01-02 03:04:05.123456789 UTC))
+		.with_accessed(datetime!(2022-01-02 03:04:05.5 UTC))
+		.with_changed(datetime!(2022-01-02 03:04:05.999999999 UTC));
+	let json = serde_json::to_string(&metadata).unwrap();
+	assert!(json.contains("2022-01-02T03:04:05.123456789Z"));
+	assert_eq!(serde_json::from_str::<Metadata>(&json).unwrap(), metadata);
+}
@@ -0,0 +1,96 @@
+use std::{fs, time::Duration};
+use tempfile::TempDir;
+use xfp_protocol::{
+	dir::{DirectoryEntryReader, DirectoryListEntry, TreeQuery},
+	metadata::NameCache,
+};
+
+/// Creates `a/b/c.txt`, `a/d.txt` and `e.txt`.
+fn tree() -> TempDir {
+	let dir = tempfile::tempdir().unwrap();
+	fs::create_dir_all(dir.path().join("a/b")).unwrap();
+	fs::write(dir.path().join("a/b/c.txt"), "c").unwrap();
+	fs::write(dir.path().join("a/d.txt"), "d").unwrap();
+	fs::write(dir.path().join("e.txt"), "e").unwrap();
+	dir
+}
+
+/// Lists the tree at `dir` with `query`, returning the paths listed and whether the listing was truncated.
+fn list(dir: &TempDir, query: TreeQuery) -> (Vec<String>, bool) {
+	let mut body = Vec::new();
+	let names = NameCache::new(Duration::from_secs(60));
+	let written = query.write_tree(dir.path(), &names, &mut body).unwrap();
+	let mut reader = DirectoryEntryReader::new(body.as_slice());
+	let entries = reader
+		.by_ref()
+		.collect::<Result<Vec<DirectoryListEntry>, _>>()
+		.unwrap();
+	assert_eq!(written, entries.len() as u64);
+	let paths = entries
+		.iter()
+		.map(|entry| entry.path().unwrap().to_string())
+		.collect();
+	(paths, reader.is_truncated())
+}
+
+#[test]
+fn trees_are_listed_depth_first() {
+	let dir = tree();
+	let (paths, truncated) = list(&dir, TreeQuery::default());
+	assert_eq!(paths, ["a", "a/b", "a/b/c.txt", "a/d.txt", "e.txt"]);
+	assert!(!truncated);
+}
+
+#[test]
+fn depth_limits_how_far_the_walk_descends() {
+	let dir = tree();
+	let (paths, truncated) = list(&dir, TreeQuery::default().with_depth(1));
+	assert_eq!(paths, ["a", "e.txt"]);
+	assert!(!truncated);
+	let (paths, _) = list(&dir, TreeQuery::default().with_depth(2));
+	assert_eq!(paths, ["a", "a/b", "a/d.txt", "e.txt"]);
+}
+
+#[test]
+fn limit_truncates_the_listing() {
+	let dir = tree();
+	let (paths, truncated) = list(&dir, TreeQuery::default().with_limit(3));
+	assert_eq!(paths, ["a", "a/b", "a/b/c.txt"]);
+	assert!(truncated);
+	// A tree with exactly `limit` entries isn't truncated.
+	let (paths, truncated) = list(&dir, TreeQuery::default().with_limit(5));
+	assert_eq!(paths.len(), 5);
+	assert!(!truncated);
+}
+
+#[cfg(unix)]
+#[test]
+fn symlink_loops_are_listed_once() {
+	let dir = tree();
+	std::os::unix::fs::symlink("..", dir.path().join("a/b/up")).unwrap();
+	let (paths, truncated) = list(&dir, TreeQuery::default().with_follow_symlinks(true));
+	assert_eq!(
+		paths,
+		["a", "a/b", "a/b/c.txt", "a/b/up", "a/d.txt", "e.txt"]
+	);
+	assert!(!truncated);
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinks_are_only_followed_when_asked() {
+	let dir = tree();
+	std::os::unix::fs::symlink("a/b", dir.path().join("link")).unwrap();
+	let (paths, _) = list(&dir, TreeQuery::default());
+	assert_eq!(paths.last().unwrap(), "link");
+	let (paths, _) = list(&dir, TreeQuery::default().with_follow_symlinks(true));
+	assert_eq!(paths[paths.len() - 2..], ["link", "link/c.txt"]);
+}
+
+#[test]
+fn missing_roots_are_errors() {
+	let dir = tree();
+	let names = NameCache::new(Duration::from_secs(60));
+	let result = TreeQuery::default().write_tree(dir.path().join("missing"), &names, Vec::new());
+	assert!(result.is_err());
+}
@@ -0,0 +1,161 @@
+use std::{fs, io, sync::mpsc, thread, time::Duration};
+use time::OffsetDateTime;
+use xfp_protocol::{
+	hash::{FileHash, HashAlgorithm},
+	upload::{UploadError, UploadStore},
+};
+
+#[test]
+fn chunks_are_appended_and_finalized() {
+	let dir = tempfile::tempdir().unwrap();
+	let store = UploadStore::new(dir.path().join("uploads"), Duration::from_secs(60)).unwrap();
+	let session = store.create("file.txt", 11).unwrap();
+	let session = store.append(session.id(), 0, &b"hello "[..]).unwrap();
+	assert_eq!(session.offset(), 6);
+	assert!(matches!(
+		store.append(session.id(), 0, &b"world"[..]),
+		Err(UploadError::OffsetMismatch(6))
+	));
+	assert!(matches!(
+		store.finalize(session.id(), &dir.path().join("file.txt"), None),
+		Err(UploadError::Incomplete)
+	));
+	let session = store.append(session.id(), 6, &b"world"[..]).unwrap();
+	assert!(session.is_complete());
+	store
+		.finalize(session.id(), &dir.path().join("file.txt"), None)
+		.unwrap();
+	assert_eq!(
+		fs::read(dir.path().join("file.txt")).unwrap(),
+		b"hello world"
+	);
+	assert!(matches!(
+		store.get(session.id()),
+		Err(UploadError::NotFound)
+	));
+}
+
+#[test]
+fn finalized_data_is_checked_against_its_digest() {
+	let dir = tempfile::tempdir().unwrap();
+	let store = UploadStore::new(dir.path().join("uploads"), Duration::from_secs(60)).unwrap();
+	let destination = dir.path().join("file.txt");
+	let session = store.create("file.txt", 11).unwrap();
+	store.append(session.id(), 0, &b"hello world"[..]).unwrap();
+
+	let wrong = FileHash::from_reader(&b"hello there"[..], HashAlgorithm::Sha256).unwrap();
+	match store.finalize(session.id(), &destination, Some(&wrong)) {
+		Err(UploadError::DigestMismatch(expected)) => assert_eq!(expected, wrong),
+		other => panic!("expected a digest mismatch, got {:?}", other),
+	}
+	assert!(!destination.exists());
+	assert_eq!(store.get(session.id()).unwrap().offset(), 11);
+
+	let right = FileHash::from_reader(&b"hello world"[..], HashAlgorithm::Sha256).unwrap();
+	store
+		.finalize(session.id(), &destination, Some(&right))
+		.unwrap();
+	assert_eq!(fs::read(&destination).unwrap(), b"hello world");
+	assert!(matches!(
+		store.get(session.id()),
+		Err(UploadError::NotFound)
+	));
+}
+
+#[test]
+fn chunks_longer_than_the_upload_are_rejected() {
+	let dir = tempfile::tempdir().unwrap();
+	let store = UploadStore::new(dir.path(), Duration::from_secs(60)).unwrap();
+	let session = store.create("file.txt", 4).unwrap();
+	assert!(matches!(
+		store.append(session.id(), 0, &b"too long"[..]),
+		Err(UploadError::TooLong)
+	));
+	assert_eq!(store.get(session.id()).unwrap().offset(), 0);
+}
+
+struct FailsAfter(&'static [u8]);
+
+impl io::Read for FailsAfter {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		if self.0.is_empty() {
+			return Err(io::ErrorKind::ConnectionReset.into());
+		}
+		let read = self.0.len().min(buf.len());
+		buf[..read].copy_from_slice(&self.0[..read]);
+		self.0 = &self.0[read..];
+		Ok(read)
+	}
+}
+
+#[test]
+fn interrupted_chunks_keep_what_was_received() {
+	let dir = tempfile::tempdir().unwrap();
+	let store = UploadStore::new(dir.path(), Duration::from_secs(60)).unwrap();
+	let session = store.create("file.txt", None).unwrap();
+	assert!(matches!(
+		store.append(session.id(), 0, FailsAfter(b"abc")),
+		Err(UploadError::Io(_))
+	));
+	assert_eq!(store.get(session.id()).unwrap().offset(), 3);
+}
+
+#[test]
+fn expired_sessions_are_garbage_collected() {
+	let dir = tempfile::tempdir().unwrap();
+	let store = UploadStore::new(dir.path(), Duration::from_secs(60)).unwrap();
+	let expired = store.create("a", None).unwrap();
+	let kept = store.create("b", None).unwrap();
+	fs::write(dir.path().join(format!("{:032x}.part", 1)), b"orphan").unwrap();
+	store.append(kept.id(), 0, &b"data"[..]).unwrap();
+	// Appending pushed back the expiry of `kept`, so only `expired` has expired at its own expiry time.
+	let removed = store.collect_garbage(expired.expires()).unwrap();
+	assert_eq!(removed, 1);
+	assert!(matches!(
+		store.get(expired.id()),
+		Err(UploadError::NotFound)
+	));
+	assert!(store.get(kept.id()).is_ok());
+	assert!(!dir.path().join(format!("{:032x}.part", 1)).exists());
+	let later = OffsetDateTime::now_utc() + time::Duration::minutes(5);
+	assert_eq!(store.collect_garbage(later).unwrap(), 1);
+}
+
+#[test]
+fn invalid_ids_are_not_found() {
+	let dir = tempfile::tempdir().unwrap();
+	let store = UploadStore::new(dir.path(), Duration::from_secs(60)).unwrap();
+	assert!(matches!(
+		store.get("../../etc/passwd"),
+		Err(UploadError::NotFound)
+	));
+}
+
+/// Sends nothing until it's told to, then ends.
+struct WaitsFor(mpsc::Receiver<()>);
+
+impl io::Read for WaitsFor {
+	fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+		self.0
+			.recv_timeout(Duration::from_secs(10))
+			.map_err(|_| io::Error::from(io::ErrorKind::TimedOut))?;
+		Ok(0)
+	}
+}
+
+#[test]
+fn sessions_receive_data_at_the_same_time() {
+	let dir = tempfile::tempdir().unwrap();
+	let store = UploadStore::new(dir.path(), Duration::from_secs(60)).unwrap();
+	let slow = store.create("slow", None).unwrap();
+	let fast = store.create("fast", None).unwrap();
+	let (send, receive) = mpsc::channel();
+	thread::scope(|scope| {
+		let slow = scope.spawn(|| store.append(slow.id(), 0, WaitsFor(receive)));
+		// This would wait for the slow chunk if the whole store were locked while it's received.
+		store.append(fast.id(), 0, &b"data"[..]).unwrap();
+		send.send(()).unwrap();
+		slow.join().unwrap().unwrap();
+	});
+	assert_eq!(store.get(fast.id()).unwrap().offset(), 4);
+}
@@ -0,0 +1,184 @@
+use std::{
+	fs,
+	path::Path,
+	time::{Duration, Instant},
+};
+use time::macros::datetime;
+use xfp_protocol::{
+	dir::DirectoryListEntry,
+	file::FileType,
+	metadata::Metadata,
+	watch::{WatchEvent, WatchEventKind, Watcher},
+};
+
+fn entry(name: &str, size: u64) -> DirectoryListEntry {
+	DirectoryListEntry::default()
+		.with_name(name)
+		.with_metadata(Metadata::default().with_size(size))
+}
+
+fn summary(events: &[WatchEvent]) -> Vec<(WatchEventKind, &str)> {
+	events
+		.iter()
+		.map(|event| (event.kind(), event.path()))
+		.collect()
+}
+
+/// Collects events until `done` is satisfied, or fails after a few seconds.
+fn collect_until(watcher: &mut Watcher, done: impl Fn(&[WatchEvent]) -> bool) -> Vec<WatchEvent> {
+	let deadline = Instant::now() + Duration::from_secs(10);
+	let mut events = Vec::new();
+	while !done(&events) {
+		assert!(Instant::now() < deadline, "timed out, got {:?}", events);
+		if let Some(event) = watcher.next_timeout(Duration::from_millis(100)).unwrap() {
+			events.push(event);
+		}
+	}
+	events
+}
+
+fn has(kind: WatchEventKind, path: &'static str) -> impl Fn(&[WatchEvent]) -> bool {
+	move |events| {
+		events
+			.iter()
+			.any(|event| event.kind() == kind && event.path() == path)
+	}
+}
+
+#[test]
+fn diff_reports_deletions_then_changes_in_listing_order() {
+	let old = [
+		entry("same", 1),
+		entry("gone-b", 1),
+		entry("gone-a", 1),
+		entry("grown", 1),
+	];
+	let new = [entry("new", 1), entry("grown", 2), entry("same", 1)];
+	let events = WatchEvent::diff(&old, &new);
+	assert_eq!(
+		summary(&events),
+		[
+			(WatchEventKind::Delete, "gone-a"),
+			(WatchEventKind::Delete, "gone-b"),
+			(WatchEventKind::Create, "new"),
+			(WatchEventKind::Modify, "grown"),
+		]
+	);
+	assert_eq!(events[2].file_type(), Some(FileType::File));
+	assert_eq!(events[0].file_type(), None);
+}
+
+#[test]
+fn diff_notices_type_and_time_changes() {
+	let old = [
+		entry("dir", 0),
+		entry("touched", 0)
+			.with_metadata(Metadata::default().with_updated(datetime!(2020-01-01 0:00 UTC))),
+	];
+	let new = [
+		entry("dir", 0).with_file_type(FileType::Directory),
+		entry("touched", 0)
+			.with_metadata(Metadata::default().with_updated(datetime!(2020-01-02 0:00 UTC))),
+	];
+	let events = WatchEvent::diff(&old, &new);
+	assert_eq!(
+		summary(&events),
+		[
+			(WatchEventKind::Modify, "dir"),
+			(WatchEventKind::Modify, "touched"),
+		]
+	);
+	assert_eq!(events[0].file_type(), Some(FileType::Directory));
+}
+
+#[test]
+fn diff_ignores_unchanged_listings() {
+	let listing = [entry("a", 1), entry("b", 2)];
+	assert!(WatchEvent::diff(&listing, &listing).is_empty());
+	assert!(WatchEvent::diff(&[], &[]).is_empty());
+}
+
+#[test]
+fn events_format_as_sse() {
+	let event = WatchEvent::new(WatchEventKind::Rename, "b").with_from("a".to_string());
+	assert_eq!(
+		event.to_sse().unwrap(),
+		"event: rename\ndata: {\"kind\":\"rename\",\"path\":\"b\",\"from\":\"a\"}\n\n"
+	);
+}
+
+fn exercise(mut watcher: Watcher, root: &Path) {
+	fs::write(root.join("file"), "one").unwrap();
+	collect_until(&mut watcher, has(WatchEventKind::Create, "file"));
+	fs::write(root.join("file"), "three").unwrap();
+	collect_until(&mut watcher, has(WatchEventKind::Modify, "file"));
+	fs::create_dir(root.join("dir")).unwrap();
+	fs::write(root.join("dir/nested"), "").unwrap();
+	collect_until(&mut watcher, has(WatchEventKind::Create, "dir/nested"));
+	fs::remove_file(root.join("file")).unwrap();
+	collect_until(&mut watcher, has(WatchEventKind::Delete, "file"));
+}
+
+#[test]
+fn polling_watcher_reports_changes() {
+	let dir = tempfile::tempdir().unwrap();
+	let watcher = Watcher::polling(dir.path(), true, Duration::from_millis(20)).unwrap();
+	assert!(watcher.is_polling());
+	exercise(watcher, dir.path());
+}
+
+#[test]
+fn polling_watcher_ignores_subdirectories_unless_recursive() {
+	let dir = tempfile::tempdir().unwrap();
+	fs::create_dir(dir.path().join("sub")).unwrap();
+	let mut watcher = Watcher::polling(dir.path(), false, Duration::from_millis(20)).unwrap();
+	fs::write(dir.path().join("sub/hidden"), "").unwrap();
+	fs::write(dir.path().join("visible"), "").unwrap();
+	let events = collect_until(&mut watcher, has(WatchEventKind::Create, "visible"));
+	assert!(events.iter().all(|event| event.path() != "sub/hidden"));
+}
+
+#[test]
+fn missing_paths_are_not_watched() {
+	let dir = tempfile::tempdir().unwrap();
+	let err = Watcher::new(dir.path().join("missing"), false).unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn inotify_watcher_reports_changes() {
+	let dir = tempfile::tempdir().unwrap();
+	let watcher = Watcher::new(dir.path(), true).unwrap();
+	assert!(!watcher.is_polling());
+	exercise(watcher, dir.path());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn inotify_watcher_reports_renames() {
+	let dir = tempfile::tempdir().unwrap();
+	fs::create_dir(dir.path().join("old")).unwrap();
+	let mut watcher = Watcher::new(dir.path(), true).unwrap();
+	fs::rename(dir.path().join("old"), dir.path().join("new")).unwrap();
+	let events = collect_until(&mut watcher, has(WatchEventKind::Rename, "new"));
+	let rename = events.last().unwrap();
+	assert_eq!(rename.from(), Some("old"));
+	assert_eq!(rename.file_type(), Some(FileType::Directory));
+	// The renamed directory is still watched, under its new path.
+	fs::write(dir.path().join("new/inside"), "").unwrap();
+	collect_until(&mut watcher, has(WatchEventKind::Create, "new/inside"));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn inotify_watcher_reports_moves_out_as_deletions() {
+	let dir = tempfile::tempdir().unwrap();
+	let outside = tempfile::tempdir_in(dir.path().parent().unwrap()).unwrap();
+	let watched = dir.path().join("watched");
+	fs::create_dir(&watched).unwrap();
+	fs::write(watched.join("file"), "").unwrap();
+	let mut watcher = Watcher::new(&watched, false).unwrap();
+	fs::rename(watched.join("file"), outside.path().join("file")).unwrap();
+	collect_until(&mut watcher, has(WatchEventKind::Delete, "file"));
+}
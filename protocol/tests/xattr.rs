@@ -0,0 +1,33 @@
+use std::io;
+use xfp_protocol::metadata::XattrError;
+
+#[test]
+fn unsupported_errors_are_recognized() {
+	assert!(matches!(
+		XattrError::from(io::Error::from(io::ErrorKind::Unsupported)),
+		XattrError::Unsupported
+	));
+	#[cfg(unix)]
+	for errno in [nix::errno::Errno::ENOTSUP, nix::errno::Errno::EOPNOTSUPP] {
+		assert!(matches!(
+			XattrError::from(io::Error::from_raw_os_error(errno as i32)),
+			XattrError::Unsupported
+		));
+	}
+}
+
+#[test]
+fn other_errors_are_kept() {
+	let err = XattrError::from(io::Error::from(io::ErrorKind::PermissionDenied));
+	match err {
+		XattrError::Io(err) => assert_eq!(err.kind(), io::ErrorKind::PermissionDenied),
+		err => panic!("unexpected error {:?}", err),
+	}
+	#[cfg(unix)]
+	assert!(matches!(
+		XattrError::from(io::Error::from_raw_os_error(
+			nix::errno::Errno::ENOENT as i32
+		)),
+		XattrError::Io(_)
+	));
+}
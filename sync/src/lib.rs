@@ -0,0 +1,307 @@
+//! Planning and running synchronisation between a local tree and an xfp mount.
+//!
+//! The mount is reached through a [`Remote`], which a client implements on top of its HTTP requests.
+//! A command line tool wrapping this is left to the client.
+
+mod run;
+mod throttle;
+
+pub use self::{
+	run::{local_tree, Remote, SyncError, SyncReport, SyncRunner},
+	throttle::{RateLimiter, Throttled},
+};
+use std::{
+	collections::{BTreeMap, BTreeSet},
+	fmt,
+	ops::Bound,
+};
+use xfp_protocol::{dir::DirectoryListEntry, file::FileType};
+
+/// The direction files are synchronised in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum SyncDirection {
+	/// Mirror the local tree to the mount.
+	Push,
+	/// Mirror the mount to the local tree.
+	Pull,
+	/// Copy changes both ways, with the most recently updated side winning.
+	/// Deletions are never propagated, as a missing file can't be told apart from a new one on the other side.
+	Both,
+}
+
+/// How files are compared to decide if they need to be transferred.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum CompareMode {
+	/// Files are the same if they have the same size and updated time, to the second.
+	/// Files without an updated time on either side are always treated as changed.
+	SizeAndTime,
+	/// Files are the same if they have the same size and hash.
+	Checksum,
+}
+
+/// Options for planning a synchronisation between a local tree and a mount.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SyncOptions {
+	direction: SyncDirection,
+	compare: CompareMode,
+	delete_extraneous: bool,
+}
+
+impl Default for SyncOptions {
+	#[inline]
+	fn default() -> Self {
+		Self {
+			direction: SyncDirection::Push,
+			compare: CompareMode::SizeAndTime,
+			delete_extraneous: false,
+		}
+	}
+}
+
+/// A single step of a synchronisation plan.
+///
+/// Paths are relative to the root of the synchronised trees, using `/` as the separator.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SyncAction {
+	/// Create a directory on the mount.
+	CreateRemoteDir(String),
+	/// Create a directory in the local tree.
+	CreateLocalDir(String),
+	/// Upload a local file to the mount.
+	Upload(String),
+	/// Download a file from the mount to the local tree.
+	Download(String),
+	/// Delete a file or directory from the mount.
+	DeleteRemote(String),
+	/// Delete a file or directory from the local tree.
+	DeleteLocal(String),
+}
+
+impl fmt::Display for SyncAction {
+	/// Describes this action, such as `upload dir/file`, for printing a dry run.
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let (verb, path) = match self {
+			SyncAction::CreateRemoteDir(path) => ("create remote directory", path),
+			SyncAction::CreateLocalDir(path) => ("create local directory", path),
+			SyncAction::Upload(path) => ("upload", path),
+			SyncAction::Download(path) => ("download", path),
+			SyncAction::DeleteRemote(path) => ("delete remote", path),
+			SyncAction::DeleteLocal(path) => ("delete local", path),
+		};
+		write!(f, "{} {}", verb, path)
+	}
+}
+
+impl SyncOptions {
+	/// The direction to synchronise in. Defaults to [`SyncDirection::Push`].
+	#[inline]
+	pub fn direction(&self) -> SyncDirection {
+		self.direction
+	}
+
+	/// Duplicates these options,
+	/// updating the direction with the specified value.
+	#[inline]
+	pub fn with_direction(self, direction: SyncDirection) -> Self {
+		Self { direction, ..self }
+	}
+
+	/// How files are compared. Defaults to [`CompareMode::SizeAndTime`].
+	#[inline]
+	pub fn compare(&self) -> CompareMode {
+		self.compare
+	}
+
+	/// Duplicates these options,
+	/// updating the comparison mode with the specified value.
+	#[inline]
+	pub fn with_compare(self, compare: CompareMode) -> Self {
+		Self { compare, ..self }
+	}
+
+	/// Whether files which only exist on the destination are deleted. Defaults to `false`.
+	/// This has no effect when synchronising both ways.
+	#[inline]
+	pub fn delete_extraneous(&self) -> bool {
+		self.delete_extraneous
+	}
+
+	/// Duplicates these options,
+	/// updating the delete extraneous flag with the specified value.
+	#[inline]
+	pub fn with_delete_extraneous(self, delete_extraneous: bool) -> Self {
+		Self {
+			delete_extraneous,
+			..self
+		}
+	}
+
+	/// Plans the actions needed to synchronise two trees, given as [tree listings](xfp_protocol::api::get_tree)
+	/// of the local tree and the mount.
+	///
+	/// In [`CompareMode::Checksum`], `same_contents` is called with the local and remote entries of
+	/// every pair of files with the same size, and should return if their hashes match.
+	/// It is never called in [`CompareMode::SizeAndTime`].
+	///
+	/// Directories are always created before their contents, and deleted after them,
+	/// so the actions can be run in order. When an entry is replaced by one of another type,
+	/// the old entry and everything inside it is deleted first, even without [`delete_extraneous`](Self::delete_extraneous).
+	/// Consecutive transfers may be run in parallel, see [`SyncRunner`].
+	pub fn plan(
+		&self,
+		local: &[DirectoryListEntry],
+		remote: &[DirectoryListEntry],
+		mut same_contents: impl FnMut(&DirectoryListEntry, &DirectoryListEntry) -> bool,
+	) -> Vec<SyncAction> {
+		let (local_paths, remote_paths) = (by_path(local), by_path(remote));
+		let mut actions = Vec::new();
+		let mut deletions = Vec::new();
+		let mut replaced = Vec::<&str>::new();
+		let paths = local_paths
+			.keys()
+			.chain(remote_paths.keys())
+			.copied()
+			.collect::<BTreeSet<_>>();
+		for path in paths {
+			// The contents of a replaced directory were already deleted along with it.
+			if replaced.iter().any(|dir| is_inside(path, dir)) {
+				continue;
+			}
+			let path_string = path.to_string();
+			match (local_paths.get(path), remote_paths.get(path)) {
+				(Some(local), None) => match self.direction {
+					SyncDirection::Push | SyncDirection::Both => {
+						actions.push(create_remote(local.file_type(), path_string))
+					}
+					SyncDirection::Pull if self.delete_extraneous => {
+						deletions.push(SyncAction::DeleteLocal(path_string))
+					}
+					SyncDirection::Pull => {}
+				},
+				(None, Some(remote)) => match self.direction {
+					SyncDirection::Pull | SyncDirection::Both => {
+						actions.push(create_local(remote.file_type(), path_string))
+					}
+					SyncDirection::Push if self.delete_extraneous => {
+						deletions.push(SyncAction::DeleteRemote(path_string))
+					}
+					SyncDirection::Push => {}
+				},
+				(Some(local), Some(remote)) if local.file_type() != remote.file_type() => {
+					let push = match self.direction {
+						SyncDirection::Push => true,
+						SyncDirection::Pull => false,
+						SyncDirection::Both => is_newer(local, remote),
+					};
+					// The replaced entry, and everything inside it, has to be deleted before it can be recreated with another type.
+					let (old, delete): (_, fn(String) -> SyncAction) = if push {
+						(&remote_paths, SyncAction::DeleteRemote)
+					} else {
+						(&local_paths, SyncAction::DeleteLocal)
+					};
+					actions.extend(
+						subtree(old, path)
+							.into_iter()
+							.rev()
+							.map(|path| delete(path.to_string())),
+					);
+					actions.push(delete(path_string.clone()));
+					actions.push(if push {
+						create_remote(local.file_type(), path_string)
+					} else {
+						create_local(remote.file_type(), path_string)
+					});
+					replaced.push(path);
+				}
+				(Some(local), Some(remote)) => {
+					if local.file_type() == FileType::Directory
+						|| self.is_same(local, remote, &mut same_contents)
+					{
+						continue;
+					}
+					actions.push(match self.direction {
+						SyncDirection::Push => SyncAction::Upload(path_string),
+						SyncDirection::Pull => SyncAction::Download(path_string),
+						SyncDirection::Both if is_newer(remote, local) => {
+							SyncAction::Download(path_string)
+						}
+						SyncDirection::Both => SyncAction::Upload(path_string),
+					});
+				}
+				(None, None) => unreachable!(),
+			}
+		}
+		// Deepest paths come last in path order, so deleting in reverse removes contents before their directories.
+		actions.extend(deletions.into_iter().rev());
+		actions
+	}
+
+	fn is_same(
+		&self,
+		local: &DirectoryListEntry,
+		remote: &DirectoryListEntry,
+		same_contents: &mut impl FnMut(&DirectoryListEntry, &DirectoryListEntry) -> bool,
+	) -> bool {
+		if local.metadata().size() != remote.metadata().size() {
+			return false;
+		}
+		match self.compare {
+			CompareMode::SizeAndTime => {
+				let seconds = |entry: &DirectoryListEntry| {
+					entry
+						.metadata()
+						.updated()
+						.map(|updated| updated.unix_timestamp())
+				};
+				match (seconds(local), seconds(remote)) {
+					(Some(local), Some(remote)) => local == remote,
+					_ => false,
+				}
+			}
+			CompareMode::Checksum => same_contents(local, remote),
+		}
+	}
+}
+
+fn by_path(entries: &[DirectoryListEntry]) -> BTreeMap<&str, &DirectoryListEntry> {
+	entries
+		.iter()
+		.map(|entry| (entry.path().unwrap_or_else(|| entry.name()), entry))
+		.collect()
+}
+
+fn is_inside(path: &str, dir: &str) -> bool {
+	path.strip_prefix(dir)
+		.is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Every path inside `dir`, in path order.
+fn subtree<'a>(entries: &BTreeMap<&'a str, &DirectoryListEntry>, dir: &str) -> Vec<&'a str> {
+	// `0` comes right after `/`, so this covers exactly the paths starting with `dir/`.
+	let (start, end) = (format!("{}/", dir), format!("{}0", dir));
+	entries
+		.range::<str, _>((
+			Bound::Excluded(start.as_str()),
+			Bound::Excluded(end.as_str()),
+		))
+		.map(|(path, _)| *path)
+		.collect()
+}
+
+fn is_newer(a: &DirectoryListEntry, b: &DirectoryListEntry) -> bool {
+	a.metadata().updated() > b.metadata().updated()
+}
+
+fn create_remote(file_type: FileType, path: String) -> SyncAction {
+	match file_type {
+		FileType::Directory => SyncAction::CreateRemoteDir(path),
+		_ => SyncAction::Upload(path),
+	}
+}
+
+fn create_local(file_type: FileType, path: String) -> SyncAction {
+	match file_type {
+		FileType::Directory => SyncAction::CreateLocalDir(path),
+		_ => SyncAction::Download(path),
+	}
+}
@@ -0,0 +1,371 @@
+use super::{RateLimiter, SyncAction, Throttled};
+use std::{
+	fmt,
+	fs::{self, File},
+	io::{self, Read, Write},
+	num::{NonZeroU64, NonZeroUsize},
+	path::{Path, PathBuf},
+	sync::{
+		atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+		Mutex,
+	},
+	thread,
+};
+use time::OffsetDateTime;
+use xfp_protocol::{dir::DirectoryListEntry, file::FileType, metadata::Metadata};
+
+/// The mount side of a synchronisation, such as an xfp client for one of its directories.
+///
+/// Paths are relative to the root of the synchronised tree, using `/` as the separator.
+/// The same remote is used from several threads at once when transfers run in parallel.
+pub trait Remote: Sync {
+	/// Creates a directory, whose parent is known to exist.
+	fn create_dir(&self, path: &str) -> io::Result<()>;
+
+	/// Deletes a file, symlink, or directory. Directories are only deleted once they're empty.
+	fn delete(&self, path: &str) -> io::Result<()>;
+
+	/// Replaces the contents of a file with everything read from `data`,
+	/// then sets its updated time to `updated`, if given, so that it compares equal to the local file.
+	fn upload(
+		&self,
+		path: &str,
+		data: &mut dyn Read,
+		updated: Option<OffsetDateTime>,
+	) -> io::Result<()>;
+
+	/// Writes the contents of a file to `out`, returning its updated time, if known.
+	fn download(&self, path: &str, out: &mut dyn Write) -> io::Result<Option<OffsetDateTime>>;
+}
+
+/// What a synchronisation did, or would have done in a dry run.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct SyncReport {
+	actions: Vec<SyncAction>,
+	bytes_transferred: u64,
+}
+
+impl SyncReport {
+	/// The actions which were run, in the order of the plan.
+	#[inline]
+	pub fn actions(&self) -> &[SyncAction] {
+		&self.actions
+	}
+
+	/// The total size of every file transferred. This is always 0 for dry runs.
+	#[inline]
+	pub fn bytes_transferred(&self) -> u64 {
+		self.bytes_transferred
+	}
+}
+
+/// The error returned when an action in a synchronisation fails.
+///
+/// Actions before it in the plan have completed, and so may some transfers which ran alongside it,
+/// but nothing after it was started.
+#[derive(Debug)]
+pub struct SyncError {
+	action: SyncAction,
+	error: io::Error,
+}
+
+impl SyncError {
+	/// The action which failed.
+	#[inline]
+	pub fn action(&self) -> &SyncAction {
+		&self.action
+	}
+
+	/// Why the action failed.
+	#[inline]
+	pub fn error(&self) -> &io::Error {
+		&self.error
+	}
+}
+
+impl fmt::Display for SyncError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "failed to {}: {}", self.action, self.error)
+	}
+}
+
+impl std::error::Error for SyncError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		Some(&self.error)
+	}
+}
+
+/// Runs a [synchronisation plan](super::SyncOptions::plan) between a local tree and a [`Remote`].
+///
+/// Actions run in the order of the plan, except that consecutive transfers run in parallel,
+/// up to the configured [parallelism](Self::parallelism). The plan only ever puts transfers
+/// next to each other once the directories they need exist, so this never reorders dependent actions.
+#[derive(Debug)]
+pub struct SyncRunner {
+	root: PathBuf,
+	dry_run: bool,
+	limiter: Option<RateLimiter>,
+	parallelism: NonZeroUsize,
+}
+
+impl SyncRunner {
+	/// Creates a runner for the local tree at `root`,
+	/// running one action at a time without a bandwidth limit.
+	pub fn new(root: impl Into<PathBuf>) -> Self {
+		Self {
+			root: root.into(),
+			dry_run: false,
+			limiter: None,
+			parallelism: NonZeroUsize::MIN,
+		}
+	}
+
+	/// Whether nothing is actually changed, only reported. Defaults to `false`.
+	#[inline]
+	pub fn dry_run(&self) -> bool {
+		self.dry_run
+	}
+
+	/// Duplicates this runner,
+	/// updating the dry run flag with the specified value.
+	#[inline]
+	pub fn with_dry_run(self, dry_run: bool) -> Self {
+		Self { dry_run, ..self }
+	}
+
+	/// The most bytes per second transferred across every transfer, if limited. Defaults to no limit.
+	#[inline]
+	pub fn bandwidth_limit(&self) -> Option<NonZeroU64> {
+		self.limiter.as_ref().map(RateLimiter::bytes_per_second)
+	}
+
+	/// Duplicates this runner,
+	/// updating the bandwidth limit with the specified value.
+	#[inline]
+	pub fn with_bandwidth_limit(self, bytes_per_second: impl Into<Option<NonZeroU64>>) -> Self {
+		Self {
+			limiter: bytes_per_second.into().map(RateLimiter::new),
+			..self
+		}
+	}
+
+	/// The most transfers run at once. Defaults to 1.
+	#[inline]
+	pub fn parallelism(&self) -> NonZeroUsize {
+		self.parallelism
+	}
+
+	/// Duplicates this runner,
+	/// updating the parallelism with the specified value.
+	#[inline]
+	pub fn with_parallelism(self, parallelism: NonZeroUsize) -> Self {
+		Self {
+			parallelism,
+			..self
+		}
+	}
+
+	/// Runs every action in `actions`, stopping at the first failure.
+	///
+	/// In a dry run, every action is reported without being run.
+	pub fn run(
+		&self,
+		actions: &[SyncAction],
+		remote: &impl Remote,
+	) -> Result<SyncReport, SyncError> {
+		let mut report = SyncReport::default();
+		if self.dry_run {
+			report.actions = actions.to_vec();
+			return Ok(report);
+		}
+		let mut rest = actions;
+		while let Some(action) = rest.first() {
+			let batch = rest.iter().take_while(|action| is_transfer(action)).count();
+			if batch == 0 {
+				self.apply(action, remote).map_err(|error| SyncError {
+					action: action.clone(),
+					error,
+				})?;
+				report.actions.push(action.clone());
+				rest = &rest[1..];
+			} else {
+				let (transfers, after) = rest.split_at(batch);
+				report.bytes_transferred += self.transfer_all(transfers, remote)?;
+				report.actions.extend_from_slice(transfers);
+				rest = after;
+			}
+		}
+		Ok(report)
+	}
+
+	fn transfer_all(
+		&self,
+		transfers: &[SyncAction],
+		remote: &impl Remote,
+	) -> Result<u64, SyncError> {
+		let next = AtomicUsize::new(0);
+		let failed = AtomicBool::new(false);
+		let bytes = AtomicU64::new(0);
+		let first_error = Mutex::new(None);
+		let workers = self.parallelism.get().min(transfers.len());
+		thread::scope(|scope| {
+			for _ in 0..workers {
+				scope.spawn(|| {
+					while !failed.load(Ordering::Relaxed) {
+						let Some(action) = transfers.get(next.fetch_add(1, Ordering::Relaxed))
+						else {
+							break;
+						};
+						match self.transfer(action, remote) {
+							Ok(transferred) => {
+								bytes.fetch_add(transferred, Ordering::Relaxed);
+							}
+							Err(error) => {
+								failed.store(true, Ordering::Relaxed);
+								first_error
+									.lock()
+									.unwrap_or_else(|poisoned| poisoned.into_inner())
+									.get_or_insert(SyncError {
+										action: action.clone(),
+										error,
+									});
+							}
+						}
+					}
+				});
+			}
+		});
+		match first_error
+			.into_inner()
+			.unwrap_or_else(|poisoned| poisoned.into_inner())
+		{
+			Some(error) => Err(error),
+			None => Ok(bytes.into_inner()),
+		}
+	}
+
+	fn transfer(&self, action: &SyncAction, remote: &impl Remote) -> io::Result<u64> {
+		let limiter = self.limiter.as_ref();
+		match action {
+			SyncAction::Upload(path) => {
+				let file = File::open(self.local_path(path)?)?;
+				let metadata = file.metadata()?;
+				let updated = metadata.modified().ok().map(OffsetDateTime::from);
+				remote.upload(path, &mut Throttled::new(file, limiter), updated)?;
+				Ok(metadata.len())
+			}
+			SyncAction::Download(path) => {
+				let destination = self.local_path(path)?;
+				// Download next to the destination first, so an interrupted download never leaves a partial file in its place.
+				let mut temporary = destination.clone().into_os_string();
+				temporary.push(".xfp-part");
+				let result = (|| {
+					let mut file = Throttled::new(File::create(&temporary)?, limiter);
+					let updated = remote.download(path, &mut file)?;
+					let file = file.into_inner();
+					if let Some(updated) = updated {
+						file.set_modified(updated.into())?;
+					}
+					let len = file.metadata()?.len();
+					fs::rename(&temporary, &destination)?;
+					Ok(len)
+				})();
+				if result.is_err() {
+					let _ = fs::remove_file(&temporary);
+				}
+				result
+			}
+			_ => unreachable!("only uploads and downloads are transfers"),
+		}
+	}
+
+	fn apply(&self, action: &SyncAction, remote: &impl Remote) -> io::Result<()> {
+		match action {
+			SyncAction::CreateRemoteDir(path) => remote.create_dir(path),
+			SyncAction::DeleteRemote(path) => remote.delete(path),
+			SyncAction::CreateLocalDir(path) => {
+				let path = self.local_path(path)?;
+				match fs::create_dir(&path) {
+					Err(err) if err.kind() == io::ErrorKind::AlreadyExists && path.is_dir() => {
+						Ok(())
+					}
+					result => result,
+				}
+			}
+			SyncAction::DeleteLocal(path) => {
+				let path = self.local_path(path)?;
+				let result = match fs::symlink_metadata(&path) {
+					Ok(metadata) if metadata.is_dir() => fs::remove_dir(&path),
+					Ok(_) => fs::remove_file(&path),
+					Err(err) => Err(err),
+				};
+				match result {
+					Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+					result => result,
+				}
+			}
+			SyncAction::Upload(_) | SyncAction::Download(_) => {
+				unreachable!("transfers are run in batches")
+			}
+		}
+	}
+
+	/// Resolves a path from the plan inside the local tree.
+	/// Paths come from the mount's listing, so they're checked to never point outside of the tree.
+	fn local_path(&self, path: &str) -> io::Result<PathBuf> {
+		path.split('/')
+			.try_fold(self.root.clone(), |local, component| {
+				if matches!(component, "" | "." | "..") || component.contains(['\\', '\0']) {
+					return Err(io::Error::new(
+						io::ErrorKind::InvalidData,
+						format!("invalid path '{}'", path),
+					));
+				}
+				Ok(local.join(component))
+			})
+	}
+}
+
+fn is_transfer(action: &SyncAction) -> bool {
+	matches!(action, SyncAction::Upload(_) | SyncAction::Download(_))
+}
+
+/// Lists the local tree at `root` in the same form as a [tree listing](xfp_protocol::api::get_tree),
+/// so it can be compared to a mount with [`plan`](super::SyncOptions::plan).
+///
+/// Symlinks are listed, but not followed. Only the size and updated time of each entry's metadata are filled in.
+pub fn local_tree<P: AsRef<Path>>(root: P) -> io::Result<Vec<DirectoryListEntry>> {
+	let mut entries = Vec::new();
+	list_dir(root.as_ref(), "", &mut entries)?;
+	Ok(entries)
+}
+
+fn list_dir(dir: &Path, relative: &str, entries: &mut Vec<DirectoryListEntry>) -> io::Result<()> {
+	let mut children = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+	children.sort_unstable_by_key(|child| child.file_name());
+	for child in children {
+		let metadata = child.metadata()?;
+		let name = child.file_name().to_string_lossy().into_owned();
+		let path = if relative.is_empty() {
+			name.clone()
+		} else {
+			format!("{}/{}", relative, name)
+		};
+		let file_type = FileType::from(metadata.file_type());
+		entries.push(
+			DirectoryListEntry::default()
+				.with_name(name)
+				.with_path(path.clone())
+				.with_file_type(file_type)
+				.with_metadata(
+					Metadata::default()
+						.with_size(metadata.len())
+						.with_updated(metadata.modified().ok().map(OffsetDateTime::from)),
+				),
+		);
+		if file_type == FileType::Directory {
+			list_dir(&child.path(), &path, entries)?;
+		}
+	}
+	Ok(())
+}
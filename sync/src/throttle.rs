@@ -0,0 +1,104 @@
+use std::{
+	io::{self, Read, Write},
+	num::NonZeroU64,
+	sync::Mutex,
+	thread,
+	time::{Duration, Instant},
+};
+
+/// The most that's read or written at once by a [`Throttled`] reader or writer,
+/// so a slow limit is spread out over many small waits rather than a few long ones.
+const CHUNK_SIZE: usize = 16 * 1024;
+
+/// Limits the combined throughput of every [`Throttled`] reader and writer sharing it,
+/// so transfers running in parallel stay under a single bandwidth limit.
+#[derive(Debug)]
+pub struct RateLimiter {
+	bytes_per_second: NonZeroU64,
+	next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+	/// Creates a limiter allowing `bytes_per_second` bytes per second, across all of its users.
+	pub fn new(bytes_per_second: NonZeroU64) -> Self {
+		Self {
+			bytes_per_second,
+			next: Mutex::new(Instant::now()),
+		}
+	}
+
+	/// The amount of bytes allowed per second.
+	#[inline]
+	pub fn bytes_per_second(&self) -> NonZeroU64 {
+		self.bytes_per_second
+	}
+
+	/// Accounts for `bytes` having been transferred,
+	/// blocking until the transfer fits within the limit.
+	pub fn consume(&self, bytes: u64) {
+		let cost = Duration::from_secs_f64(bytes as f64 / self.bytes_per_second.get() as f64);
+		let until = {
+			let mut next = self
+				.next
+				.lock()
+				.unwrap_or_else(|poisoned| poisoned.into_inner());
+			// Time spent idle can't be saved up for later bursts.
+			*next = (*next).max(Instant::now()) + cost;
+			*next
+		};
+		thread::sleep(until.saturating_duration_since(Instant::now()));
+	}
+}
+
+/// A reader or writer whose throughput is limited by a [`RateLimiter`].
+#[derive(Debug)]
+pub struct Throttled<'a, T> {
+	inner: T,
+	limiter: Option<&'a RateLimiter>,
+}
+
+impl<'a, T> Throttled<'a, T> {
+	/// Wraps `inner`, limiting it with `limiter`. If there is no limiter, `inner` is used as is.
+	#[inline]
+	pub fn new(inner: T, limiter: impl Into<Option<&'a RateLimiter>>) -> Self {
+		Self {
+			inner,
+			limiter: limiter.into(),
+		}
+	}
+
+	/// Consumes this wrapper, returning the underlying reader or writer.
+	#[inline]
+	pub fn into_inner(self) -> T {
+		self.inner
+	}
+}
+
+impl<T: Read> Read for Throttled<'_, T> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let Some(limiter) = self.limiter else {
+			return self.inner.read(buf);
+		};
+		let len = buf.len().min(CHUNK_SIZE);
+		let read = self.inner.read(&mut buf[..len])?;
+		limiter.consume(read as u64);
+		Ok(read)
+	}
+}
+
+impl<T: Write> Write for Throttled<'_, T> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let Some(limiter) = self.limiter else {
+			return self.inner.write(buf);
+		};
+		let len = buf.len().min(CHUNK_SIZE);
+		let written = self.inner.write(&buf[..len])?;
+		limiter.consume(written as u64);
+		Ok(written)
+	}
+
+	#[inline]
+	fn flush(&mut self) -> io::Result<()> {
+		self.inner.flush()
+	}
+}
@@ -0,0 +1,445 @@
+use std::{
+	collections::BTreeMap,
+	fs,
+	io::{self, Read, Write},
+	num::{NonZeroU64, NonZeroUsize},
+	path::Path,
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Mutex,
+	},
+	thread,
+	time::{Duration, Instant},
+};
+use time::{macros::datetime, OffsetDateTime};
+use xfp_protocol::{dir::DirectoryListEntry, file::FileType, metadata::Metadata};
+use xfp_sync::{
+	local_tree, CompareMode, RateLimiter, Remote, SyncAction, SyncDirection, SyncOptions,
+	SyncRunner, Throttled,
+};
+
+fn file(path: &str, size: u64, updated: Option<OffsetDateTime>) -> DirectoryListEntry {
+	DirectoryListEntry::default()
+		.with_name(path.rsplit('/').next().unwrap())
+		.with_path(path.to_string())
+		.with_metadata(Metadata::default().with_size(size).with_updated(updated))
+}
+
+fn dir(path: &str) -> DirectoryListEntry {
+	file(path, 0, None).with_file_type(FileType::Directory)
+}
+
+const OLD: OffsetDateTime = datetime!(2020-01-01 0:00 UTC);
+const NEW: OffsetDateTime = datetime!(2021-01-01 0:00 UTC);
+
+fn never(_: &DirectoryListEntry, _: &DirectoryListEntry) -> bool {
+	panic!("contents shouldn't be compared")
+}
+
+#[test]
+fn push_creates_directories_before_their_contents() {
+	let local = [dir("a"), file("a/b", 1, Some(OLD)), file("c", 1, Some(OLD))];
+	let remote = [file("c", 1, Some(OLD))];
+	assert_eq!(
+		SyncOptions::default().plan(&local, &remote, never),
+		[
+			SyncAction::CreateRemoteDir("a".to_string()),
+			SyncAction::Upload("a/b".to_string()),
+		]
+	);
+}
+
+#[test]
+fn extraneous_entries_are_deleted_deepest_first() {
+	let local = [file("keep", 1, Some(OLD))];
+	let remote = [
+		dir("gone"),
+		file("gone/file", 1, Some(OLD)),
+		file("keep", 1, Some(OLD)),
+		file("stray", 1, Some(OLD)),
+	];
+	let options = SyncOptions::default();
+	assert!(options.plan(&local, &remote, never).is_empty());
+	assert_eq!(
+		options
+			.with_delete_extraneous(true)
+			.plan(&local, &remote, never),
+		[
+			SyncAction::DeleteRemote("stray".to_string()),
+			SyncAction::DeleteRemote("gone/file".to_string()),
+			SyncAction::DeleteRemote("gone".to_string()),
+		]
+	);
+}
+
+#[test]
+fn replaced_directories_are_emptied_before_being_replaced() {
+	let local = [file("dir", 1, Some(NEW)), dir("dir-a")];
+	let remote = [
+		dir("dir"),
+		file("dir/x", 1, Some(OLD)),
+		dir("dir/y"),
+		file("dir/y/z", 1, Some(OLD)),
+		dir("dir-a"),
+	];
+	let expected = [
+		SyncAction::DeleteRemote("dir/y/z".to_string()),
+		SyncAction::DeleteRemote("dir/y".to_string()),
+		SyncAction::DeleteRemote("dir/x".to_string()),
+		SyncAction::DeleteRemote("dir".to_string()),
+		SyncAction::Upload("dir".to_string()),
+	];
+	let options = SyncOptions::default();
+	assert_eq!(options.plan(&local, &remote, never), expected);
+	assert_eq!(
+		options
+			.with_delete_extraneous(true)
+			.plan(&local, &remote, never),
+		expected
+	);
+}
+
+#[test]
+fn replaced_local_directories_are_emptied_when_pulling() {
+	let local = [dir("dir"), file("dir/x", 1, Some(OLD))];
+	let remote = [file("dir", 1, Some(NEW))];
+	assert_eq!(
+		SyncOptions::default()
+			.with_direction(SyncDirection::Pull)
+			.plan(&local, &remote, never),
+		[
+			SyncAction::DeleteLocal("dir/x".to_string()),
+			SyncAction::DeleteLocal("dir".to_string()),
+			SyncAction::Download("dir".to_string()),
+		]
+	);
+}
+
+#[test]
+fn files_without_times_are_always_transferred() {
+	let local = [
+		file("a", 1, None),
+		file("b", 1, Some(OLD)),
+		file("c", 1, None),
+	];
+	let remote = [
+		file("a", 1, None),
+		file("b", 1, None),
+		file("c", 1, Some(OLD)),
+	];
+	assert_eq!(
+		SyncOptions::default().plan(&local, &remote, never),
+		[
+			SyncAction::Upload("a".to_string()),
+			SyncAction::Upload("b".to_string()),
+			SyncAction::Upload("c".to_string()),
+		]
+	);
+}
+
+#[test]
+fn time_comparisons_ignore_subseconds() {
+	let local = [file("a", 1, Some(datetime!(2020-01-01 0:00:00.9 UTC)))];
+	let remote = [file("a", 1, Some(OLD))];
+	assert!(SyncOptions::default()
+		.plan(&local, &remote, never)
+		.is_empty());
+}
+
+#[test]
+fn checksums_are_only_compared_for_files_of_the_same_size() {
+	let local = [
+		file("same", 1, Some(NEW)),
+		file("changed", 1, Some(NEW)),
+		file("grown", 2, None),
+	];
+	let remote = [
+		file("same", 1, Some(OLD)),
+		file("changed", 1, Some(OLD)),
+		file("grown", 1, None),
+	];
+	let mut compared = Vec::new();
+	let actions = SyncOptions::default()
+		.with_compare(CompareMode::Checksum)
+		.plan(&local, &remote, |local, _| {
+			compared.push(local.name().to_string());
+			local.name() == "same"
+		});
+	assert_eq!(
+		actions,
+		[
+			SyncAction::Upload("changed".to_string()),
+			SyncAction::Upload("grown".to_string()),
+		]
+	);
+	assert_eq!(compared, ["changed", "same"]);
+}
+
+#[test]
+fn two_way_sync_lets_the_newest_side_win_and_never_deletes() {
+	let local = [
+		file("local-newer", 1, Some(NEW)),
+		file("remote-newer", 1, Some(OLD)),
+		file("local-only", 1, None),
+	];
+	let remote = [
+		file("local-newer", 2, Some(OLD)),
+		file("remote-newer", 2, Some(NEW)),
+		file("remote-only", 1, None),
+	];
+	assert_eq!(
+		SyncOptions::default()
+			.with_direction(SyncDirection::Both)
+			.with_delete_extraneous(true)
+			.plan(&local, &remote, never),
+		[
+			SyncAction::Upload("local-newer".to_string()),
+			SyncAction::Upload("local-only".to_string()),
+			SyncAction::Download("remote-newer".to_string()),
+			SyncAction::Download("remote-only".to_string()),
+		]
+	);
+}
+
+/// The contents of a remote entry, or `None` for directories, and its updated time.
+type RemoteEntry = (Option<Vec<u8>>, Option<OffsetDateTime>);
+
+/// An in-memory mount.
+#[derive(Default)]
+struct MemoryRemote {
+	entries: Mutex<BTreeMap<String, RemoteEntry>>,
+	active: AtomicUsize,
+	most_active: AtomicUsize,
+	fail_on: Option<&'static str>,
+	delay: Duration,
+}
+
+impl MemoryRemote {
+	fn listing(&self) -> Vec<DirectoryListEntry> {
+		self.entries
+			.lock()
+			.unwrap()
+			.iter()
+			.map(|(path, (contents, updated))| match contents {
+				Some(contents) => file(path, contents.len() as u64, *updated),
+				None => dir(path),
+			})
+			.collect()
+	}
+
+	fn insert(&self, path: &str, contents: Option<&[u8]>) {
+		self.entries
+			.lock()
+			.unwrap()
+			.insert(path.to_string(), (contents.map(<[u8]>::to_vec), Some(OLD)));
+	}
+
+	fn contents(&self, path: &str) -> Option<Vec<u8>> {
+		self.entries.lock().unwrap().get(path)?.0.clone()
+	}
+
+	fn busy<T>(&self, path: &str, f: impl FnOnce() -> io::Result<T>) -> io::Result<T> {
+		let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+		self.most_active.fetch_max(active, Ordering::SeqCst);
+		thread::sleep(self.delay);
+		let result = if self.fail_on == Some(path) {
+			Err(io::Error::other("refused"))
+		} else {
+			f()
+		};
+		self.active.fetch_sub(1, Ordering::SeqCst);
+		result
+	}
+}
+
+impl Remote for MemoryRemote {
+	fn create_dir(&self, path: &str) -> io::Result<()> {
+		self.busy(path, || {
+			self.insert(path, None);
+			Ok(())
+		})
+	}
+
+	fn delete(&self, path: &str) -> io::Result<()> {
+		self.busy(path, || {
+			self.entries.lock().unwrap().remove(path);
+			Ok(())
+		})
+	}
+
+	fn upload(
+		&self,
+		path: &str,
+		data: &mut dyn Read,
+		updated: Option<OffsetDateTime>,
+	) -> io::Result<()> {
+		self.busy(path, || {
+			let mut contents = Vec::new();
+			data.read_to_end(&mut contents)?;
+			self.entries
+				.lock()
+				.unwrap()
+				.insert(path.to_string(), (Some(contents), updated));
+			Ok(())
+		})
+	}
+
+	fn download(&self, path: &str, out: &mut dyn Write) -> io::Result<Option<OffsetDateTime>> {
+		self.busy(path, || {
+			let (contents, updated) = self.entries.lock().unwrap().get(path).cloned().unwrap();
+			out.write_all(&contents.unwrap())?;
+			Ok(updated)
+		})
+	}
+}
+
+fn write_tree(root: &Path) {
+	fs::create_dir_all(root.join("dir/nested")).unwrap();
+	fs::write(root.join("top"), "top").unwrap();
+	fs::write(root.join("dir/file"), "file").unwrap();
+	fs::write(root.join("dir/nested/deep"), "deep").unwrap();
+}
+
+#[test]
+fn pushing_mirrors_the_local_tree() {
+	let local = tempfile::tempdir().unwrap();
+	write_tree(local.path());
+	let remote = MemoryRemote::default();
+	remote.insert("stale", Some(b"stale"));
+	let options = SyncOptions::default().with_delete_extraneous(true);
+	let runner = SyncRunner::new(local.path());
+	let plan = options.plan(&local_tree(local.path()).unwrap(), &remote.listing(), never);
+	let report = runner.run(&plan, &remote).unwrap();
+	assert_eq!(report.actions(), plan);
+	assert_eq!(report.bytes_transferred(), 11);
+	assert_eq!(remote.contents("dir/nested/deep").unwrap(), b"deep");
+	assert_eq!(remote.contents("stale"), None);
+	// Times are carried over, so nothing is left to do afterwards.
+	assert!(options
+		.plan(&local_tree(local.path()).unwrap(), &remote.listing(), never)
+		.is_empty());
+}
+
+#[test]
+fn pulling_mirrors_the_mount() {
+	let local = tempfile::tempdir().unwrap();
+	fs::write(local.path().join("stale"), "stale").unwrap();
+	let remote = MemoryRemote::default();
+	remote.insert("dir", None);
+	remote.insert("dir/file", Some(b"file"));
+	let options = SyncOptions::default()
+		.with_direction(SyncDirection::Pull)
+		.with_delete_extraneous(true);
+	let runner = SyncRunner::new(local.path());
+	let plan = options.plan(&local_tree(local.path()).unwrap(), &remote.listing(), never);
+	runner.run(&plan, &remote).unwrap();
+	assert_eq!(fs::read(local.path().join("dir/file")).unwrap(), b"file");
+	assert!(!local.path().join("stale").exists());
+	assert!(options
+		.plan(&local_tree(local.path()).unwrap(), &remote.listing(), never)
+		.is_empty());
+}
+
+#[test]
+fn dry_runs_change_nothing() {
+	let local = tempfile::tempdir().unwrap();
+	write_tree(local.path());
+	let remote = MemoryRemote::default();
+	let plan =
+		SyncOptions::default().plan(&local_tree(local.path()).unwrap(), &remote.listing(), never);
+	let report = SyncRunner::new(local.path())
+		.with_dry_run(true)
+		.run(&plan, &remote)
+		.unwrap();
+	assert_eq!(report.actions(), plan);
+	assert_eq!(report.bytes_transferred(), 0);
+	assert!(remote.listing().is_empty());
+	assert_eq!(plan[0].to_string(), "create remote directory dir");
+}
+
+#[test]
+fn transfers_run_in_parallel() {
+	let local = tempfile::tempdir().unwrap();
+	for idx in 0..8 {
+		fs::write(local.path().join(idx.to_string()), "x").unwrap();
+	}
+	let remote = MemoryRemote {
+		delay: Duration::from_millis(50),
+		..MemoryRemote::default()
+	};
+	let plan =
+		SyncOptions::default().plan(&local_tree(local.path()).unwrap(), &remote.listing(), never);
+	let report = SyncRunner::new(local.path())
+		.with_parallelism(NonZeroUsize::new(4).unwrap())
+		.run(&plan, &remote)
+		.unwrap();
+	assert_eq!(report.actions().len(), 8);
+	assert_eq!(remote.most_active.load(Ordering::SeqCst), 4);
+}
+
+#[test]
+fn failures_stop_the_run() {
+	let local = tempfile::tempdir().unwrap();
+	write_tree(local.path());
+	let remote = MemoryRemote {
+		fail_on: Some("dir/nested"),
+		..MemoryRemote::default()
+	};
+	let plan =
+		SyncOptions::default().plan(&local_tree(local.path()).unwrap(), &remote.listing(), never);
+	let err = SyncRunner::new(local.path())
+		.run(&plan, &remote)
+		.unwrap_err();
+	assert_eq!(
+		err.action(),
+		&SyncAction::CreateRemoteDir("dir/nested".to_string())
+	);
+	assert_eq!(
+		err.to_string(),
+		"failed to create remote directory dir/nested: refused"
+	);
+	assert_eq!(remote.contents("dir/nested/deep"), None);
+}
+
+#[test]
+fn paths_outside_the_local_tree_are_refused() {
+	let local = tempfile::tempdir().unwrap();
+	let remote = MemoryRemote::default();
+	remote.insert("..", Some(b"escape"));
+	for path in ["../escape", "a//b", "./a", ""] {
+		let err = SyncRunner::new(local.path().join("root"))
+			.run(&[SyncAction::Download(path.to_string())], &remote)
+			.unwrap_err();
+		assert_eq!(err.error().kind(), io::ErrorKind::InvalidData, "{path:?}");
+	}
+	assert!(!local.path().join("escape").exists());
+}
+
+#[test]
+fn bandwidth_is_shared_between_transfers() {
+	let limiter = RateLimiter::new(NonZeroU64::new(200_000).unwrap());
+	let start = Instant::now();
+	thread::scope(|scope| {
+		for _ in 0..2 {
+			scope.spawn(|| {
+				let mut reader = Throttled::new(&[0u8; 50_000][..], &limiter);
+				io::copy(&mut reader, &mut io::sink()).unwrap();
+			});
+		}
+	});
+	// 100 KB at 200 KB/s takes half a second, even when split across two readers.
+	assert!(
+		start.elapsed() >= Duration::from_millis(450),
+		"{:?}",
+		start.elapsed()
+	);
+}
+
+#[test]
+fn unlimited_throttles_pass_through() {
+	let mut written = Vec::new();
+	Throttled::new(&mut written, None)
+		.write_all(&[1; 100_000])
+		.unwrap();
+	assert_eq!(written.len(), 100_000);
+}